@@ -0,0 +1,67 @@
+//! Benchmark of annotating a large map, comparing annotating a copy of its lexemes with
+//! moving its lexemes into the annotated file.
+//!
+//! Run with `cargo bench --bench annotate`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use aoe2_rms::{annotater::AnnotatedFile, lexer};
+
+/// The system allocator, counting the number of allocations made.
+struct CountingAllocator;
+
+/// The number of allocations made by the benchmark.
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// The number of times each variant is run. The fastest run is reported.
+const RUNS: usize = 10;
+
+/// Runs `annotate` on a fresh copy of `source`'s lexemes `RUNS` times.
+/// Returns the fastest time and the allocations of annotating, excluding lexing.
+fn measure(
+    source: &str,
+    annotate: impl Fn(lexer::LexemeFile) -> AnnotatedFile,
+) -> (Duration, usize) {
+    let mut fastest = Duration::MAX;
+    let mut allocations = 0;
+    for _ in 0..RUNS {
+        let lexemes = lexer::lex_str(source);
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        let annotated_file = black_box(annotate(lexemes));
+        fastest = fastest.min(start.elapsed());
+        allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        drop(annotated_file);
+    }
+    (fastest, allocations)
+}
+
+fn main() {
+    let map = std::fs::read_to_string("maps/Basic Map.rms").expect("The map must exist.");
+    let source = map.repeat(500);
+    let num_lexemes = lexer::lex_str(&source).lexemes().len();
+    println!("annotating {num_lexemes} lexemes, fastest of {RUNS} runs");
+    let (time, allocations) = measure(&source, |lexemes| AnnotatedFile::annotate(&lexemes));
+    println!("  annotate:       {time:>12?} {allocations:>9} allocations");
+    let (time, allocations) = measure(&source, AnnotatedFile::annotate_owned);
+    println!("  annotate_owned: {time:>12?} {allocations:>9} allocations");
+}
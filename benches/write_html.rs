@@ -0,0 +1,59 @@
+//! Benchmark of writing the html of a large annotated map, counting the allocations
+//! made while escaping and writing its tokens.
+//!
+//! Run with `cargo bench --bench write_html`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use aoe2_rms::{annotater::AnnotatedFile, html_writer, lexer};
+
+/// The system allocator, counting the number of allocations made.
+struct CountingAllocator;
+
+/// The number of allocations made by the benchmark.
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// The number of times the html is written. The fastest run is reported.
+const RUNS: usize = 10;
+
+fn main() {
+    let map = std::fs::read_to_string("maps/Basic Map.rms").expect("The map must exist.");
+    let source = map.repeat(500);
+    let annotated_file = AnnotatedFile::annotate_owned(lexer::lex_str(&source));
+    let output = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("write_html_bench.html");
+    println!(
+        "writing {} tokens, fastest of {RUNS} runs",
+        annotated_file.tokens().len()
+    );
+    let mut fastest = Duration::MAX;
+    let mut allocations = 0;
+    for _ in 0..RUNS {
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        html_writer::write_annotated_debug_file(black_box(&annotated_file), &output)
+            .expect("The html must be written.");
+        fastest = fastest.min(start.elapsed());
+        allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    }
+    println!("  write: {fastest:>12?} {allocations:>9} allocations");
+}
@@ -0,0 +1,22 @@
+//! Fuzz target feeding arbitrary input through the lexer, annotater, and checkers.
+//!
+//! Run with `cargo +nightly fuzz run lex_annotate` from the repository root.
+
+#![no_main]
+
+use aoe2_rms::{
+    annotater::AnnotatedFile,
+    checker::{self, CheckOptions},
+    lexer, tokenizer,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Map scripts are read as UTF-8, so invalid sequences are replaced.
+    let source = String::from_utf8_lossy(data);
+    let lexemes = lexer::lex_str(&source);
+    assert_eq!(lexemes.to_source_string(), source);
+    let annotated_file = AnnotatedFile::annotate(&lexemes);
+    tokenizer::logical_lines(&annotated_file);
+    checker::check(&annotated_file, &CheckOptions::default());
+});
@@ -1,26 +1,99 @@
 /* Annotates a tokenized file produced by the lexer. */
 
-use crate::lexer::{Lexeme, LexemeFile};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    ops::Range,
+};
+
+use crate::{
+    diagnostics::{Diagnostic, Severity},
+    lexer::{Lexeme, LexemeFile},
+    rms_data, tokenizer,
+};
+
+/// The kind of syntax highlighting applied to a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightKind {
+    /// A comment delimiter or a token within a comment.
+    Comment,
+    /// A function call, such as `rnd`, or its punctuation.
+    Function,
+    /// A numeric literal.
+    Number,
+    /// A built-in constant, such as an assign type.
+    Constant,
+    /// A resource constant, such as `AMOUNT_STARTING_FOOD`.
+    Resource,
+    /// A section header, such as `<PLAYER_SETUP>`.
+    Section,
+    /// A command, such as `create_land` or `if`.
+    Command,
+    /// A directive, such as `#const` or `#include`.
+    Directive,
+}
+
+impl HighlightKind {
+    /// All kinds of highlighting.
+    pub const ALL: [HighlightKind; 8] = [
+        HighlightKind::Comment,
+        HighlightKind::Function,
+        HighlightKind::Number,
+        HighlightKind::Constant,
+        HighlightKind::Resource,
+        HighlightKind::Section,
+        HighlightKind::Command,
+        HighlightKind::Directive,
+    ];
+
+    /// Returns the name of the css class used for this kind of highlighting.
+    pub fn class_name(self) -> &'static str {
+        use HighlightKind::*;
+        match self {
+            Comment => "comment",
+            Function => "function",
+            Number => "number",
+            Constant => "constant",
+            Resource => "resource",
+            Section => "section",
+            Command => "command",
+            Directive => "directive",
+        }
+    }
+}
 
 /// TODO
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Annotation {
-    /// The class name used for syntax highlighting this token.
-    highlight: Option<String>,
+    /// The kind of syntax highlighting for this token.
+    highlight: Option<HighlightKind>,
     /// The Id number for a comment's opening or closing token.
     comment_id: Option<usize>,
+    /// Whether this token is the first non-comment text token of its line.
+    line_start: bool,
 }
 
 impl Annotation {
     /// Returns the name of the class used for syntax highlighting this token.
     pub fn highlight(&self) -> Option<&str> {
-        self.highlight.as_ref().map(|s| &s[..])
+        self.highlight.map(HighlightKind::class_name)
+    }
+
+    /// Returns the kind of syntax highlighting for this token.
+    pub fn highlight_kind(&self) -> Option<HighlightKind> {
+        self.highlight
     }
 
     /// Returns the id of the comment, if present.
     pub fn comment_id(&self) -> Option<usize> {
         self.comment_id
     }
+
+    /// Returns `true` if this token is the first non-comment text token of its line,
+    /// `false` otherwise.
+    pub fn is_line_start(&self) -> bool {
+        self.line_start
+    }
 }
 
 /// A token with annotations.
@@ -41,6 +114,39 @@ impl AnnotatedToken {
     pub fn annotation(&self) -> Option<&Annotation> {
         self.annotation.as_ref()
     }
+
+    /// Returns `true` if this token is a comment delimiter or is within a comment,
+    /// `false` otherwise.
+    pub fn is_comment(&self) -> bool {
+        self.annotation
+            .as_ref()
+            .is_some_and(|a| a.highlight == Some(HighlightKind::Comment))
+    }
+
+    /// Returns the kind of syntax highlighting for this token, if it is highlighted.
+    pub fn highlight_kind(&self) -> Option<HighlightKind> {
+        self.annotation
+            .as_ref()
+            .and_then(Annotation::highlight_kind)
+    }
+
+    /// Returns `true` if this token is the first non-comment text token of its line,
+    /// `false` otherwise.
+    /// Lines that are blank, consist only of whitespace, or lie entirely within a
+    /// comment have no line start token.
+    pub fn is_line_start(&self) -> bool {
+        self.annotation
+            .as_ref()
+            .is_some_and(Annotation::is_line_start)
+    }
+}
+
+/// Options for reporting the comment matching of an annotated file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AnnotateOptions {
+    /// Whether an unmatched comment delimiter is an error, for a validation gate, rather
+    /// than a warning. Rendering is lenient either way.
+    pub strict_comments: bool,
 }
 
 /// A file of tokens along with their annotations.
@@ -58,15 +164,404 @@ impl AnnotatedFile {
         self.num_matched_comments
     }
 
-    /// TODO
+    /// Annotates a copy of the lexemes of `tokenized_file`.
+    /// Prefer [`AnnotatedFile::annotate_owned`] when the lexeme file is no longer needed,
+    /// as it avoids copying the text of every lexeme.
     pub fn annotate(tokenized_file: &LexemeFile) -> Self {
-        AnnotationBuilder::new(tokenized_file).build()
+        Self::annotate_lexemes(tokenized_file.lexemes().clone())
+    }
+
+    /// Annotates the lexemes of `tokenized_file`, moving them into the annotated file.
+    pub fn annotate_owned(tokenized_file: LexemeFile) -> Self {
+        Self::annotate_lexemes(tokenized_file.into_lexemes())
+    }
+
+    /// Annotates `lexemes`, moving them into the annotated file.
+    fn annotate_lexemes(lexemes: Vec<Lexeme>) -> Self {
+        let (annotations, num_matched_comments) = AnnotationBuilder::new(&lexemes).build();
+        Self {
+            tokens: zip_annotations(lexemes, annotations),
+            num_matched_comments,
+        }
+    }
+
+    /// Returns `true` if this file and `other` have the same sequence of text tokens, each
+    /// with the same text and highlight kind, `false` otherwise.
+    /// Unlike `==`, the positions of the tokens and the whitespace and line breaks
+    /// between them are ignored, so reformatting a file preserves its equality.
+    pub fn semantic_eq(&self, other: &AnnotatedFile) -> bool {
+        self.text_highlights().eq(other.text_highlights())
+    }
+
+    /// Returns the text and highlight kind of each text token of this file, in order.
+    fn text_highlights(&self) -> impl Iterator<Item = (&str, Option<HighlightKind>)> {
+        self.tokens
+            .iter()
+            .filter(|token| matches!(token.token, Lexeme::Text(_)))
+            .map(|token| (token.token.get_info().characters(), token.highlight_kind()))
     }
 
     /// Reference to the annotated tokens of this file.
     pub fn tokens(&self) -> &Vec<AnnotatedToken> {
         &self.tokens
     }
+
+    /// Replaces the lines in `edited_lines` with `new_lexemes` and updates the annotations.
+    ///
+    /// `edited_lines` is the half-open range of 1-indexed line numbers being replaced.
+    /// Lines past the end of the file are ignored, so an empty range starting after the
+    /// final line appends to the file.
+    /// `new_lexemes` are the lexemes of the replacement lines, numbered from line 1,
+    /// such as those returned by `lexer::lex_str`. Unless the edit extends to the end of
+    /// the file, `new_lexemes` must be empty or end with a line break.
+    /// The line numbers and byte offsets of all lines following the edit are shifted
+    /// accordingly.
+    ///
+    /// Only the edited lines are annotated again if neither the replaced lines nor the
+    /// new lexemes contain a comment delimiter, as then the comment matching of the rest
+    /// of the file is unaffected. Otherwise the whole file is annotated again.
+    /// Returns `true` if only the edited lines were annotated, `false` otherwise.
+    pub fn reannotate_range(&mut self, edited_lines: Range<usize>, new_lexemes: &[Lexeme]) -> bool {
+        debug_assert!(edited_lines.start >= 1 && edited_lines.start <= edited_lines.end);
+        let start = self
+            .tokens
+            .partition_point(|t| t.token.get_info().line_number() < edited_lines.start);
+        let end = self
+            .tokens
+            .partition_point(|t| t.token.get_info().line_number() < edited_lines.end);
+        debug_assert!(
+            end == self.tokens.len()
+                || matches!(new_lexemes.last(), None | Some(Lexeme::LineBreak(_))),
+            "The new lexemes must end with a line break."
+        );
+
+        // The number of lines removed and added, used to shift the following lines.
+        let old_num_lines = match (
+            self.tokens[start..end].first(),
+            self.tokens[start..end].last(),
+        ) {
+            (Some(first), Some(last)) => {
+                last.token.get_info().line_number() - first.token.get_info().line_number() + 1
+            }
+            _ => 0,
+        };
+        let new_num_lines = match new_lexemes.last() {
+            Some(Lexeme::LineBreak(info)) => info.line_number(),
+            Some(lexeme) => lexeme.get_info().line_number(),
+            None => 0,
+        };
+
+        // Moves the new lexemes to start at the first edited line and its first byte.
+        let first_line = edited_lines.start.min(
+            self.tokens
+                .last()
+                .map_or(1, |t| t.token.get_info().line_number() + 1),
+        );
+        let first_byte = match self.tokens.get(start) {
+            Some(t) => t.token.get_info().byte_range().start,
+            None => self
+                .tokens
+                .last()
+                .map_or(0, |t| t.token.get_info().byte_range().end),
+        };
+        let new_lexemes: Vec<Lexeme> = new_lexemes
+            .iter()
+            .map(|lexeme| {
+                let mut lexeme = lexeme.clone();
+                let info = lexeme.get_info();
+                let line_number = info.line_number() + first_line - 1;
+                let start_byte = info.byte_range().start + first_byte;
+                lexeme.set_line_number(line_number);
+                lexeme.set_start_byte(start_byte);
+                lexeme
+            })
+            .collect();
+        let old_num_bytes: usize = self.tokens[start..end]
+            .iter()
+            .map(|t| t.token.get_info().characters().len())
+            .sum();
+        let new_num_bytes: usize = new_lexemes
+            .iter()
+            .map(|lexeme| lexeme.get_info().characters().len())
+            .sum();
+        for token in self.tokens[end..].iter_mut() {
+            let info = token.token.get_info();
+            let line_number = info.line_number() + new_num_lines - old_num_lines;
+            let start_byte = info.byte_range().start + new_num_bytes - old_num_bytes;
+            token.token.set_line_number(line_number);
+            token.token.set_start_byte(start_byte);
+        }
+
+        if self.tokens[start..end]
+            .iter()
+            .any(|t| is_comment_delimiter(&t.token))
+            || new_lexemes.iter().any(is_comment_delimiter)
+        {
+            let mut tokens = std::mem::take(&mut self.tokens);
+            let following = tokens.split_off(end);
+            tokens.truncate(start);
+            let lexemes: Vec<Lexeme> = tokens
+                .into_iter()
+                .map(|t| t.token)
+                .chain(new_lexemes)
+                .chain(following.into_iter().map(|t| t.token))
+                .collect();
+            *self = Self::annotate_lexemes(lexemes);
+            return false;
+        }
+
+        // The edited lines are within a comment if there are more opening delimiters
+        // than matched closing delimiters before them.
+        let comment_depth = self.tokens[..start]
+            .iter()
+            .filter(|t| t.annotation().and_then(Annotation::comment_id).is_some())
+            .map(|t| match t.token.get_info().characters() {
+                "/*" => 1isize,
+                _ => -1isize,
+            })
+            .sum::<isize>();
+        let mut builder = AnnotationBuilder::new(&new_lexemes);
+        // No delimiters are in the new lexemes, so these placeholders are never popped.
+        builder.open_comments = vec![(0, 0); comment_depth as usize];
+        let (annotations, _) = builder.build();
+        self.tokens
+            .splice(start..end, zip_annotations(new_lexemes, annotations));
+        true
+    }
+
+    /// Returns a summary of the highlighting and comment matching of this file.
+    pub fn stats(&self) -> AnnotationStats {
+        let mut stats = AnnotationStats {
+            num_matched_comments: self.num_matched_comments,
+            ..AnnotationStats::default()
+        };
+        let mut num_opening_delimiters = 0;
+        for token in self.tokens.iter() {
+            let Lexeme::Text(info) = &token.token else {
+                continue;
+            };
+            stats.num_text_tokens += 1;
+            if let Some(kind) = token.highlight_kind() {
+                *stats.highlight_counts.entry(kind).or_default() += 1;
+            }
+            let is_delimiter = token
+                .annotation()
+                .and_then(Annotation::comment_id)
+                .is_some();
+            match info.characters() {
+                "/*" => num_opening_delimiters += 1,
+                "*/" if !is_delimiter => stats.num_unmatched_delimiters += 1,
+                _ => (),
+            }
+        }
+        stats.num_unmatched_delimiters += num_opening_delimiters - self.num_matched_comments;
+        stats
+    }
+
+    /// Reports each comment delimiter without a matching delimiter: an opening `/*` that is
+    /// never closed, which comments out the rest of the file, or a stray closing `*/`.
+    /// The diagnostics are errors under `options.strict_comments` and warnings otherwise,
+    /// and are sorted by their position in the file.
+    pub fn comment_diagnostics(&self, options: &AnnotateOptions) -> Vec<Diagnostic> {
+        let severity = if options.strict_comments {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        let mut unmatched = vec![];
+        // The indices of the opening delimiters not yet closed.
+        let mut open = vec![];
+        for (i, token) in self.tokens.iter().enumerate() {
+            let Lexeme::Text(info) = &token.token else {
+                continue;
+            };
+            let is_delimiter = token
+                .annotation()
+                .and_then(Annotation::comment_id)
+                .is_some();
+            match info.characters() {
+                "/*" => open.push(i),
+                "*/" if is_delimiter => {
+                    open.pop();
+                }
+                "*/" => unmatched.push((i, "Stray `*/` closes no comment.")),
+                _ => (),
+            }
+        }
+        unmatched.extend(
+            open.into_iter()
+                .map(|i| (i, "Unclosed `/*` comments out the rest of the file.")),
+        );
+        unmatched.sort_unstable();
+        unmatched
+            .into_iter()
+            .map(|(i, message)| {
+                Diagnostic::new(
+                    severity,
+                    "unmatched-comment-delimiter",
+                    String::from(message),
+                    self.tokens[i].token.get_info(),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the outermost comment regions of this file, in order.
+    /// Comments nested within another comment are part of the enclosing region.
+    pub fn comment_regions(&self) -> Vec<CommentRegion> {
+        let mut regions = vec![];
+        // The index and trailing status of the opening delimiter of the current region.
+        let mut open: Option<(usize, bool)> = None;
+        let mut depth = 0usize;
+        // Whether code precedes the current token on its line.
+        let mut code_on_line = false;
+        for (i, token) in self.tokens.iter().enumerate() {
+            let Lexeme::Text(info) = &token.token else {
+                if let Lexeme::LineBreak(_) = token.token {
+                    code_on_line = false;
+                }
+                continue;
+            };
+            let is_delimiter = token
+                .annotation()
+                .and_then(Annotation::comment_id)
+                .is_some();
+            match info.characters() {
+                "/*" if is_delimiter => {
+                    if depth == 0 {
+                        open = Some((i, code_on_line));
+                    }
+                    depth += 1;
+                }
+                "*/" if is_delimiter => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let (start, trailing) = open.take().expect("A region must be open.");
+                        regions.push(CommentRegion {
+                            token_range: start..i + 1,
+                            terminated: true,
+                            trailing,
+                        });
+                    }
+                }
+                _ if !token.is_comment() => code_on_line = true,
+                _ => (),
+            }
+        }
+        if let Some((start, trailing)) = open {
+            regions.push(CommentRegion {
+                token_range: start..self.tokens.len(),
+                terminated: false,
+                trailing,
+            });
+        }
+        regions
+    }
+
+    /// Returns the index of the token containing the 1-indexed `line` and `column`,
+    /// or `None` if no token contains the position.
+    pub fn token_index_at(&self, line: usize, column: usize) -> Option<usize> {
+        let first = self
+            .tokens
+            .partition_point(|t| t.token.get_info().line_number() < line);
+        self.tokens[first..]
+            .iter()
+            .take_while(|t| t.token.get_info().line_number() == line)
+            .position(|t| {
+                let info = t.token.get_info();
+                info.start_column() <= column && column <= info.end_column()
+            })
+            .map(|i| first + i)
+    }
+
+    /// Returns the outermost comment region containing the 1-indexed `line` and `column`,
+    /// or `None` if the position is not within a comment.
+    /// A position on one of the region's delimiters is within the region.
+    pub fn comment_at(&self, line: usize, column: usize) -> Option<CommentRegion> {
+        let index = self.token_index_at(line, column)?;
+        self.comment_regions()
+            .into_iter()
+            .find(|region| region.token_range.contains(&index))
+    }
+}
+
+/// A summary of the highlighting and comment matching of an annotated file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnnotationStats {
+    /// The number of text tokens.
+    num_text_tokens: usize,
+    /// The number of text tokens with each kind of highlighting.
+    highlight_counts: HashMap<HighlightKind, usize>,
+    /// The number of pairs of matching comment delimiters.
+    num_matched_comments: usize,
+    /// The number of comment delimiters without a matching delimiter.
+    num_unmatched_delimiters: usize,
+}
+
+impl AnnotationStats {
+    /// Returns the number of text tokens.
+    pub fn num_text_tokens(&self) -> usize {
+        self.num_text_tokens
+    }
+
+    /// Returns the number of text tokens highlighted as `kind`.
+    pub fn highlight_count(&self, kind: HighlightKind) -> usize {
+        self.highlight_counts.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of pairs of matching comment delimiters.
+    pub fn num_matched_comments(&self) -> usize {
+        self.num_matched_comments
+    }
+
+    /// Returns the number of comment delimiters without a matching delimiter.
+    pub fn num_unmatched_delimiters(&self) -> usize {
+        self.num_unmatched_delimiters
+    }
+}
+
+impl Display for AnnotationStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} text tokens", self.num_text_tokens)?;
+        for kind in HighlightKind::ALL {
+            write!(f, ", {} {}", self.highlight_count(kind), kind.class_name())?;
+        }
+        write!(
+            f,
+            ", {} matched comments, {} unmatched delimiters",
+            self.num_matched_comments, self.num_unmatched_delimiters
+        )
+    }
+}
+
+/// A region of an annotated file within an outermost pair of comment delimiters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CommentRegion {
+    /// The range of indices of the region's tokens, from the opening delimiter through
+    /// the closing delimiter, or through the end of the file if the region is unterminated.
+    token_range: Range<usize>,
+    /// Whether the region has a closing delimiter.
+    terminated: bool,
+    /// Whether code precedes the opening delimiter on the same line.
+    trailing: bool,
+}
+
+impl CommentRegion {
+    /// Returns the range of indices of this region's tokens within the annotated file.
+    pub fn token_range(&self) -> Range<usize> {
+        self.token_range.clone()
+    }
+
+    /// Returns `true` if this region has a closing delimiter, `false` otherwise.
+    pub fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+
+    /// Returns `true` if this is a trailing comment, whose opening delimiter is preceded
+    /// on the same line by code, or `false` if it is a standalone comment.
+    pub fn is_trailing(&self) -> bool {
+        self.trailing
+    }
 }
 
 /// TODO
@@ -75,97 +570,634 @@ struct AnnotationBuilder<'a> {
     index: usize,
     comment_id: usize,
     num_matched_comments: usize,
-    /// The first `usize` is the index in `annotated_tokens` of the open comment token.
+    /// Whether a line start token has been annotated on the current line.
+    line_started: bool,
+    /// The highlights of the upcoming text tokens of a spaced `rnd` call.
+    pending_highlights: VecDeque<HighlightKind>,
+    /// The first `usize` is the index in `original_tokens` of the open comment token.
     /// The second `usize` is the comment id of the comment.
     open_comments: Vec<(usize, usize)>,
-    original_tokens: &'a LexemeFile,
-    annotated_tokens: Vec<AnnotatedToken>,
+    original_tokens: &'a [Lexeme],
+    /// The annotation of each token stepped over so far, in order.
+    /// The tokens themselves are not copied, so that they may be moved into the
+    /// annotated file afterward.
+    annotations: Vec<Option<Annotation>>,
 }
 
 impl<'a> AnnotationBuilder<'a> {
-    fn new(original_tokens: &'a LexemeFile) -> Self {
+    fn new(original_tokens: &'a [Lexeme]) -> Self {
         Self {
             index: 0,
             comment_id: 0,
             num_matched_comments: 0,
+            line_started: false,
+            pending_highlights: VecDeque::new(),
             open_comments: vec![],
             original_tokens,
-            annotated_tokens: Vec::with_capacity(original_tokens.lexemes().len()),
+            annotations: Vec::with_capacity(original_tokens.len()),
         }
     }
 
     fn step(&mut self) -> bool {
-        debug_assert!(self.index < self.original_tokens.lexemes().len());
+        debug_assert!(self.index < self.original_tokens.len());
         // TODO
-        let token = &self.original_tokens.lexemes()[self.index];
+        let token = &self.original_tokens[self.index];
 
         if let Lexeme::Text(token_info) = token {
             match token_info.characters() {
                 "/*" => {
-                    let annotated_token = AnnotatedToken {
-                        token: token.clone(),
-                        annotation: Some(Annotation {
-                            highlight: Some(String::from("comment")),
-                            comment_id: Some(self.comment_id),
-                        }),
-                    };
-                    self.annotated_tokens.push(annotated_token);
+                    self.annotations.push(Some(Annotation {
+                        highlight: Some(HighlightKind::Comment),
+                        comment_id: Some(self.comment_id),
+                        line_start: false,
+                    }));
                     self.open_comments.push((self.index, self.comment_id));
                     self.comment_id += 1;
                 }
                 "*/" => {
-                    if let Some((index, id)) = self.open_comments.pop() {
+                    if let Some((_index, id)) = self.open_comments.pop() {
                         // TODO add comment index to open token
                         self.num_matched_comments += 1;
-                        self.annotated_tokens.push(AnnotatedToken {
-                            token: token.clone(),
-                            annotation: Some(Annotation {
-                                highlight: Some(String::from("comment")),
-                                comment_id: Some(id),
-                            }),
-                        })
+                        self.annotations.push(Some(Annotation {
+                            highlight: Some(HighlightKind::Comment),
+                            comment_id: Some(id),
+                            line_start: false,
+                        }))
                     } else {
                         // TODO handle mismatched comments properly, for now just avoid highlighting
-                        self.annotated_tokens.push(AnnotatedToken {
-                            token: token.clone(),
-                            annotation: None,
-                        })
+                        let annotation = self.code_annotation(None);
+                        self.annotations.push(annotation)
                     }
                 }
                 _ => {
                     let annotation = if self.open_comments.is_empty() {
-                        None
+                        let highlight = self.code_highlight(token_info.characters());
+                        self.code_annotation(highlight)
                     } else {
                         Some(Annotation {
-                            highlight: Some(String::from("comment")),
+                            highlight: Some(HighlightKind::Comment),
                             comment_id: None,
+                            line_start: false,
                         })
                     };
-                    self.annotated_tokens.push(AnnotatedToken {
-                        token: token.clone(),
-                        annotation,
-                    })
+                    self.annotations.push(annotation)
                 }
             }
         } else {
-            self.annotated_tokens.push(AnnotatedToken {
-                token: token.clone(),
-                annotation: None,
-            })
+            if let Lexeme::LineBreak(_) = token {
+                self.line_started = false;
+            }
+            self.annotations.push(None)
         }
         self.index += 1; // Update the index for the next step.
                          // Return whether the index is at the end of the file.
-        self.index != self.original_tokens.lexemes().len()
+        self.index != self.original_tokens.len()
     }
 
-    fn build(mut self) -> AnnotatedFile {
-        for _ in 0..self.original_tokens.lexemes().len() {
+    /// Returns the annotation of a text token outside of a comment with the given
+    /// `highlight`, or `None` if the token needs no annotation.
+    /// The token is marked as the line start if no other token on the current line
+    /// has been marked.
+    fn code_annotation(&mut self, highlight: Option<HighlightKind>) -> Option<Annotation> {
+        let line_start = !self.line_started;
+        self.line_started = true;
+        if highlight.is_none() && !line_start {
+            return None;
+        }
+        Some(Annotation {
+            highlight,
+            comment_id: None,
+            line_start,
+        })
+    }
+
+    /// Returns the highlight of the text token at the current index with the given
+    /// `characters`, where the token is outside of a comment.
+    ///
+    /// An `rnd` call is highlighted as a function with any spacing, such as the single
+    /// glued lexeme `rnd(1,4)`, the separate lexemes of `rnd ( 1 , 4 )`, or the mixed
+    /// `rnd(1, 4)`. A lexeme that is a whole numeric argument is highlighted as a number.
+    /// Assign type constants, such as `AT_PLAYER`, are highlighted as constants, and
+    /// resource constants, such as `AMOUNT_GOLD`, are highlighted as resources.
+    /// Section headers, such as `<PLAYER_SETUP>`, are highlighted as sections, commands
+    /// beginning with `#`, such as `#const`, as directives, and the other commands, such
+    /// as `create_land`, as commands.
+    fn code_highlight(&mut self, characters: &str) -> Option<HighlightKind> {
+        if let Some(highlight) = self.pending_highlights.pop_front() {
+            return Some(highlight);
+        }
+        if rms_data::assign_type(characters).is_some() {
+            return Some(HighlightKind::Constant);
+        }
+        if rms_data::resource(characters).is_some() {
+            return Some(HighlightKind::Resource);
+        }
+        if rms_data::Section::from_header(characters).is_some() {
+            return Some(HighlightKind::Section);
+        }
+        if rms_data::command(characters).is_some() {
+            return Some(if characters.starts_with('#') {
+                HighlightKind::Directive
+            } else {
+                HighlightKind::Command
+            });
+        }
+        if !characters.starts_with("rnd") {
+            return None;
+        }
+        // Looks ahead for the text lexemes of the call on the same line, splitting each on
+        // its punctuation, until the call's six pieces `rnd ( min , max )` are collected.
+        let mut pieces = vec![];
+        let mut lexeme_pieces = vec![];
+        let lexemes = self.original_tokens[self.index..]
+            .iter()
+            .take_while(|t| !matches!(t, Lexeme::LineBreak(_)))
+            .filter(|t| matches!(t, Lexeme::Text(_)));
+        for lexeme in lexemes {
+            let split = tokenizer::split_punctuation(lexeme);
+            pieces.extend(split.iter().map(|p| p.get_info().characters().to_string()));
+            lexeme_pieces.push(split.len());
+            if pieces.len() >= 6 {
+                break;
+            }
+        }
+        match &pieces[..] {
+            [rnd, open, min, comma, max, close]
+                if rnd == "rnd"
+                    && open == "("
+                    && is_number(min)
+                    && comma == ","
+                    && is_number(max)
+                    && close == ")" =>
+            {
+                // A lexeme that is a whole argument is a number, and any other lexeme of
+                // the call is part of the function.
+                let mut highlights = lexeme_pieces.iter().scan(0, |start, &len| {
+                    let highlight = match len {
+                        1 if is_number(&pieces[*start]) => HighlightKind::Number,
+                        _ => HighlightKind::Function,
+                    };
+                    *start += len;
+                    Some(highlight)
+                });
+                let highlight = highlights.next();
+                self.pending_highlights = highlights.collect();
+                highlight
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the annotation of each token, in order, and the number of pairs of
+    /// matching comment delimiters.
+    fn build(mut self) -> (Vec<Option<Annotation>>, usize) {
+        for _ in 0..self.original_tokens.len() {
             self.step();
         }
-        // TODO cleanup
-        AnnotatedFile {
-            tokens: self.annotated_tokens,
-            num_matched_comments: self.num_matched_comments,
+        (self.annotations, self.num_matched_comments)
+    }
+}
+
+/// Pairs each of `lexemes` with its annotation, moving the lexemes into the tokens.
+fn zip_annotations(
+    lexemes: Vec<Lexeme>,
+    annotations: Vec<Option<Annotation>>,
+) -> Vec<AnnotatedToken> {
+    debug_assert_eq!(lexemes.len(), annotations.len());
+    lexemes
+        .into_iter()
+        .zip(annotations)
+        .map(|(token, annotation)| AnnotatedToken { token, annotation })
+        .collect()
+}
+
+/// Returns `true` if `lexeme` is a comment delimiter, `/*` or `*/`, `false` otherwise.
+fn is_comment_delimiter(lexeme: &Lexeme) -> bool {
+    match lexeme {
+        Lexeme::Text(info) => matches!(info.characters(), "/*" | "*/"),
+        _ => false,
+    }
+}
+
+/// Returns `true` if `s` is an integer literal, `false` otherwise.
+fn is_number(s: &str) -> bool {
+    s.parse::<i32>().is_ok()
+}
+
+/// Returns the number of pairs of matching comment delimiters in `file`, the same as
+/// [`AnnotatedFile::num_comments`], without annotating the file.
+pub fn count_matched_comments(file: &LexemeFile) -> usize {
+    let mut open_comments = 0usize;
+    let mut num_matched_comments = 0;
+    for lexeme in file.lexemes() {
+        match lexeme {
+            Lexeme::Text(info) if info.characters() == "/*" => open_comments += 1,
+            Lexeme::Text(info) if info.characters() == "*/" && open_comments > 0 => {
+                open_comments -= 1;
+                num_matched_comments += 1;
+            }
+            _ => (),
+        }
+    }
+    num_matched_comments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+
+    /// Returns the characters and highlights of the highlighted tokens in `source`.
+    fn highlights(source: &str) -> Vec<(String, String)> {
+        AnnotatedFile::annotate(&lexer::lex_str(source))
+            .tokens()
+            .iter()
+            .filter_map(|t| {
+                let highlight = t.annotation()?.highlight()?;
+                let characters = t.token().get_info().characters();
+                Some((String::from(characters), String::from(highlight)))
+            })
+            .collect()
+    }
+
+    /// Tests that reformatting preserves semantic equality, while changing the text or
+    /// highlight kind of a token breaks it.
+    #[test]
+    fn semantic_eq() {
+        let annotate = |source| AnnotatedFile::annotate(&lexer::lex_str(source));
+        let original = annotate("/* land */\ncreate_land {\n  base_size rnd(1,4)\n}\n");
+        let reformatted = annotate("\t/* land */ create_land\r\n{ base_size   rnd(1,4) }");
+        assert_ne!(original, reformatted);
+        assert!(original.semantic_eq(&reformatted));
+        assert!(!original.semantic_eq(&annotate("/* land */ create_land { base_size 4 }")));
+
+        let mut recolored = reformatted.clone();
+        let token = recolored
+            .tokens
+            .iter_mut()
+            .find(|token| token.token.get_info().characters() == "rnd(1,4)")
+            .unwrap();
+        token.annotation = Some(Annotation {
+            highlight: Some(HighlightKind::Number),
+            comment_id: None,
+            line_start: false,
+        });
+        assert!(!original.semantic_eq(&recolored));
+    }
+
+    /// Tests that annotating moved lexemes matches annotating a copy of them.
+    #[test]
+    fn annotate_owned() {
+        let lexemes = lexer::lex_str("/* a /* b */\nrnd ( 1 , 4 ) AT_PLAYER */ */ x");
+        let annotated = AnnotatedFile::annotate(&lexemes);
+        assert_eq!(AnnotatedFile::annotate_owned(lexemes), annotated);
+    }
+
+    /// Tests the highlight kinds of comment and code tokens.
+    #[test]
+    fn highlight_kind() {
+        let annotated = AnnotatedFile::annotate(&lexer::lex_str("/* note */ rnd(1,2) x"));
+        let kinds: Vec<Option<HighlightKind>> = annotated
+            .tokens()
+            .iter()
+            .filter(|t| matches!(t.token(), Lexeme::Text(_)))
+            .map(AnnotatedToken::highlight_kind)
+            .collect();
+        use HighlightKind::*;
+        assert_eq!(
+            kinds,
+            vec![
+                Some(Comment),
+                Some(Comment),
+                Some(Comment),
+                Some(Function),
+                None
+            ]
+        );
+    }
+
+    /// Tests highlighting an `rnd` call with spaces between its lexemes.
+    #[test]
+    fn rnd_spaced() {
+        let expected: Vec<(String, String)> = [
+            ("number_of_objects", "command"),
+            ("rnd", "function"),
+            ("(", "function"),
+            ("1", "number"),
+            (",", "function"),
+            ("4", "number"),
+            (")", "function"),
+        ]
+        .iter()
+        .map(|(c, h)| (String::from(*c), String::from(*h)))
+        .collect();
+        assert_eq!(highlights("number_of_objects rnd ( 1 , 4 ) 5"), expected);
+    }
+
+    /// Tests highlighting an `rnd` call written as a single lexeme.
+    #[test]
+    fn rnd_glued() {
+        assert_eq!(
+            highlights("number_of_objects rnd(1,4)\nrnd(1)"),
+            vec![
+                (String::from("number_of_objects"), String::from("command")),
+                (String::from("rnd(1,4)"), String::from("function"))
+            ]
+        );
+    }
+
+    /// Tests highlighting `rnd` calls that are partially glued.
+    #[test]
+    fn rnd_mixed() {
+        let expected = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(c, h)| (String::from(*c), String::from(*h)))
+                .collect()
+        };
+        assert_eq!(
+            highlights("rnd(1, 4)"),
+            expected(&[("rnd(1,", "function"), ("4)", "function")])
+        );
+        assert_eq!(
+            highlights("rnd (1,4)"),
+            expected(&[("rnd", "function"), ("(1,4)", "function")])
+        );
+        assert_eq!(
+            highlights("rnd( 1 ,4 )"),
+            expected(&[
+                ("rnd(", "function"),
+                ("1", "number"),
+                (",4", "function"),
+                (")", "function")
+            ])
+        );
+        assert!(highlights("rnd(1, 4)x\nrnd(1,\n4)\nrnd (1, x)").is_empty());
+    }
+
+    /// Tests that counting the matched comments agrees with annotating the file.
+    #[test]
+    fn count_matched_comments_agrees() {
+        let mut sources: Vec<String> = [
+            "",
+            "/* a */",
+            "*/ /* /* b */",
+            "/* /* nested */ */ */",
+            "/*glued*/ /* open",
+        ]
+        .map(String::from)
+        .to_vec();
+        for entry in std::fs::read_dir("maps/").unwrap() {
+            sources.push(std::fs::read_to_string(entry.unwrap().path()).unwrap());
         }
+        for source in sources {
+            let lexemes = lexer::lex_str(&source);
+            assert_eq!(
+                count_matched_comments(&lexemes),
+                AnnotatedFile::annotate(&lexemes).num_comments(),
+                "{source}"
+            );
+        }
+    }
+
+    /// Tests that unclosed and stray delimiters are warnings, or errors when strict.
+    #[test]
+    fn comment_diagnostics() {
+        let annotated = AnnotatedFile::annotate(&lexer::lex_str(
+            "*/ /* a /* b */
+/* c */",
+        ));
+        let diagnostics = annotated.comment_diagnostics(&AnnotateOptions::default());
+        let found: Vec<(Severity, usize, usize, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.severity(), d.line_number(), d.start_column(), d.message()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Severity::Warning, 1, 1, "Stray `*/` closes no comment."),
+                (
+                    Severity::Warning,
+                    1,
+                    4,
+                    "Unclosed `/*` comments out the rest of the file."
+                ),
+            ]
+        );
+        let strict = AnnotateOptions {
+            strict_comments: true,
+        };
+        assert!(annotated
+            .comment_diagnostics(&strict)
+            .iter()
+            .all(|d| d.severity() == Severity::Error));
+        let balanced = AnnotatedFile::annotate(&lexer::lex_str("/* a /* b */ */ x"));
+        assert!(balanced.comment_diagnostics(&strict).is_empty());
+    }
+
+    /// Tests the statistics of a file with known quantities of each kind of token.
+    #[test]
+    fn stats() {
+        let source =
+            "*/ /* one /* two */ */ /* three\nassign_to AT_TEAM 1 rnd(1,2) rnd ( 3 , 4 ) */";
+        let stats = AnnotatedFile::annotate(&lexer::lex_str(source)).stats();
+        assert_eq!(stats.num_text_tokens(), 20);
+        assert_eq!(stats.highlight_count(HighlightKind::Comment), 19);
+        assert_eq!(stats.highlight_count(HighlightKind::Number), 0);
+        assert_eq!(stats.num_matched_comments(), 3);
+        assert_eq!(stats.num_unmatched_delimiters(), 1);
+
+        let source = "assign_to AT_TEAM 1 rnd(1,2) rnd ( 3 , 4 ) /* x */ */";
+        let stats = AnnotatedFile::annotate(&lexer::lex_str(source)).stats();
+        assert_eq!(stats.num_text_tokens(), 14);
+        assert_eq!(stats.highlight_count(HighlightKind::Comment), 3);
+        assert_eq!(stats.highlight_count(HighlightKind::Function), 5);
+        assert_eq!(stats.highlight_count(HighlightKind::Number), 2);
+        assert_eq!(stats.highlight_count(HighlightKind::Constant), 1);
+        assert_eq!(stats.highlight_count(HighlightKind::Command), 1);
+        assert_eq!(stats.num_matched_comments(), 1);
+        assert_eq!(stats.num_unmatched_delimiters(), 1);
+        assert_eq!(
+            stats.to_string(),
+            "14 text tokens, 3 comment, 5 function, 2 number, 1 constant, 0 resource, 0 section, 1 command, 0 directive, 1 matched comments, 1 unmatched delimiters"
+        );
+    }
+
+    /// Tests finding the comment region containing positions inside, on a delimiter of,
+    /// and outside of a comment.
+    #[test]
+    fn comment_at() {
+        let source = "x /* a\n b */ y\n/* c */";
+        let annotated = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let regions = annotated.comment_regions();
+        assert_eq!(annotated.comment_at(1, 3), Some(regions[0].clone()));
+        assert_eq!(annotated.comment_at(1, 4), Some(regions[0].clone()));
+        assert_eq!(annotated.comment_at(2, 1), Some(regions[0].clone()));
+        assert_eq!(annotated.comment_at(2, 5), Some(regions[0].clone()));
+        assert_eq!(annotated.comment_at(3, 6), Some(regions[1].clone()));
+        assert_eq!(annotated.comment_at(1, 1), None);
+        assert_eq!(annotated.comment_at(1, 2), None);
+        assert_eq!(annotated.comment_at(2, 7), None);
+        assert_eq!(annotated.comment_at(2, 20), None);
+        assert_eq!(annotated.comment_at(4, 1), None);
+    }
+
+    /// Tests classifying trailing and standalone comment regions.
+    #[test]
+    fn comment_regions() {
+        let source = "/* full line */\nbase_terrain GRASS /* trailing /* nested */ */\n  /* indented\n*/ x /* open";
+        let annotated = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let regions = annotated.comment_regions();
+        let summary: Vec<(String, bool, bool)> = regions
+            .iter()
+            .map(|region| {
+                let text: String = annotated.tokens()[region.token_range()]
+                    .iter()
+                    .map(|t| t.token().get_info().characters())
+                    .collect();
+                (text, region.is_trailing(), region.is_terminated())
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (String::from("/* full line */"), false, true),
+                (String::from("/* trailing /* nested */ */"), true, true),
+                (String::from("/* indented\n*/"), false, true),
+                (String::from("/* open"), true, false),
+            ]
+        );
+    }
+
+    /// Tests highlighting assign type constants.
+    #[test]
+    fn assign_type_constant() {
+        assert_eq!(
+            highlights("assign_to AT_TEAM 1 0 0\nAT_TEAMS"),
+            vec![
+                (String::from("assign_to"), String::from("command")),
+                (String::from("AT_TEAM"), String::from("constant"))
+            ]
+        );
+    }
+
+    /// Tests highlighting section headers, commands, and directives, but not unknown
+    /// headers or commands in another case.
+    #[test]
+    fn section_command_directive() {
+        let expected: Vec<(String, String)> = [
+            ("<PLAYER_SETUP>", "section"),
+            ("random_placement", "command"),
+            ("#const", "directive"),
+            ("if", "command"),
+        ]
+        .iter()
+        .map(|(c, h)| (String::from(*c), String::from(*h)))
+        .collect();
+        let source = "<PLAYER_SETUP>\nrandom_placement\n#const X 1\nif X\n<SETUP> Random_Placement";
+        assert_eq!(highlights(source), expected);
+    }
+
+    /// Tests highlighting resource constants.
+    #[test]
+    fn resource_constant() {
+        assert_eq!(
+            highlights("effect_amount MOD_RESOURCE AMOUNT_STARTING_GOLD ATTR_ADD 100"),
+            vec![(
+                String::from("AMOUNT_STARTING_GOLD"),
+                String::from("resource")
+            )]
+        );
+    }
+
+    /// Tests that incomplete or commented `rnd` calls are not highlighted as functions.
+    #[test]
+    fn rnd_incomplete() {
+        assert!(highlights("rnd ( 1 ,\n4 )").is_empty());
+        assert!(highlights("rnd ( 1 , x )").is_empty());
+        assert!(highlights("/* rnd(1,4) */")
+            .iter()
+            .all(|(_, h)| h == "comment"));
+    }
+
+    /// Tests that editing lines without comment delimiters annotates only the edited lines
+    /// and produces the same result as annotating the edited file.
+    #[test]
+    fn reannotate_range_fast_path() {
+        let source = "/* Header\n*/\nbase_terrain GRASS\n/* inner\nline\n*/ enable_waves 0\n";
+        let mut annotated = AnnotatedFile::annotate(&lexer::lex_str(source));
+        // Replaces line 3 with two lines, then line 5 inside the comment with one line.
+        let edit = lexer::lex_str("  base_terrain DESERT\nland_percent 50\n");
+        assert!(annotated.reannotate_range(3..4, edit.lexemes()));
+        let edit = lexer::lex_str("text\n");
+        assert!(annotated.reannotate_range(6..7, edit.lexemes()));
+        let expected_source =
+            "/* Header\n*/\n  base_terrain DESERT\nland_percent 50\n/* inner\ntext\n*/ enable_waves 0\n";
+        assert_eq!(
+            annotated,
+            AnnotatedFile::annotate(&lexer::lex_str(expected_source))
+        );
+    }
+
+    /// Tests that edits adding or removing comment delimiters annotate the whole file.
+    #[test]
+    fn reannotate_range_fallback() {
+        let source = "base_terrain GRASS\n/* comment */\nenable_waves 0";
+        let mut annotated = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let edit = lexer::lex_str("/* open\n");
+        assert!(!annotated.reannotate_range(1..2, edit.lexemes()));
+        assert_eq!(
+            annotated,
+            AnnotatedFile::annotate(&lexer::lex_str("/* open\n/* comment */\nenable_waves 0"))
+        );
+        assert!(!annotated.reannotate_range(2..3, &[]));
+        assert_eq!(
+            annotated,
+            AnnotatedFile::annotate(&lexer::lex_str("/* open\nenable_waves 0"))
+        );
+    }
+
+    /// Returns the characters of the line start tokens in `source`.
+    fn line_starts(source: &str) -> Vec<(usize, String)> {
+        AnnotatedFile::annotate(&lexer::lex_str(source))
+            .tokens()
+            .iter()
+            .filter(|t| t.is_line_start())
+            .map(|t| {
+                let info = t.token().get_info();
+                (info.line_number(), String::from(info.characters()))
+            })
+            .collect()
+    }
+
+    /// Tests that the first text token of indented lines is marked and blank lines are not.
+    #[test]
+    fn line_start_indented_and_blank() {
+        let source = "<PLAYER_SETUP>\n  random_placement\n\n   \t\ncreate_object VILLAGER {\r\n  number_of_objects 3\n}";
+        assert_eq!(
+            line_starts(source),
+            vec![
+                (1, String::from("<PLAYER_SETUP>")),
+                (2, String::from("random_placement")),
+                (5, String::from("create_object")),
+                (6, String::from("number_of_objects")),
+                (7, String::from("}")),
+            ]
+        );
+    }
+
+    /// Tests that lines within a multi-line comment are not marked,
+    /// while text following the end of a comment on the same line is marked.
+    #[test]
+    fn line_start_multi_line_comment() {
+        let source =
+            "/* A comment\n  spanning lines\n */ base_terrain GRASS\n/* only */\nenable_waves 0";
+        assert_eq!(
+            line_starts(source),
+            vec![
+                (3, String::from("base_terrain")),
+                (5, String::from("enable_waves")),
+            ]
+        );
     }
 }
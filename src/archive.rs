@@ -0,0 +1,50 @@
+//! Writes the html of a whole map pack into a single zip archive.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    annotater::AnnotatedFile,
+    html_writer::{self, HtmlOptions},
+};
+
+/// Writes a zip archive to `output` containing the html of every map in `maps`, using
+/// the given `options`, together with the stylesheet.
+/// Each map is a pair of its file name within the `maps` folder and its annotated tokens,
+/// and is written to the entry of that name with an `.html` extension.
+/// The stylesheet is written to the `style.css` entry beside the maps, consisting of
+/// `stylesheet` followed by the rules of the options' highlight theme and the comment
+/// highlighting rules for the maps.
+/// If a file already exists at `output`, it is overwritten.
+/// Returns an IO error if there is an error writing to the `output` file.
+pub fn write_zip_archive(
+    maps: &[(&str, &AnnotatedFile)],
+    stylesheet: &str,
+    output: &Path,
+    options: &HtmlOptions,
+) -> io::Result<()> {
+    let mut zip = ZipWriter::new(File::create(output)?);
+    let entry_options =
+        SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let num_comments = maps
+        .iter()
+        .map(|(_, annotated_file)| annotated_file.num_comments())
+        .max()
+        .unwrap_or(0);
+    zip.start_file("style.css", entry_options)?;
+    zip.write_all(stylesheet.as_bytes())?;
+    zip.write_all(options.highlight_theme.css().as_bytes())?;
+    zip.write_all(html_writer::comment_highlight_css(num_comments).as_bytes())?;
+    for (name, annotated_file) in maps {
+        let entry = Path::new(name).with_extension("html");
+        zip.start_file(entry.to_string_lossy(), entry_options)?;
+        html_writer::write_annotated_html(&mut zip, annotated_file, options)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
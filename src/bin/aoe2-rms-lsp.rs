@@ -0,0 +1,18 @@
+//! A language server for map scripts, speaking the Language Server Protocol over stdio.
+
+use std::{io, process};
+
+use aoe2_rms::lsp;
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    match lsp::run(stdin.lock(), stdout.lock()) {
+        // The protocol requires exiting with 1 if the client exits without a shutdown.
+        Ok(shut_down) => process::exit(if shut_down { 0 } else { 1 }),
+        Err(e) => {
+            eprintln!("Error running the language server: {e}");
+            process::exit(1);
+        }
+    }
+}
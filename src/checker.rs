@@ -0,0 +1,1685 @@
+//! Checks an annotated map script for likely mistakes, reporting them as diagnostics.
+
+use std::{collections::HashSet, fmt::Display};
+
+use crate::{
+    annotater::{AnnotateOptions, AnnotatedFile},
+    definitions::{Definitions, ResolveError},
+    diagnostics::{Diagnostic, Severity},
+    lexer::{Lexeme, LexemeInfo},
+    rms_data::{self, GameVersion, LabelType, PlayerSetup, Section},
+    suggest::did_you_mean,
+    tokenizer,
+};
+
+/// Returns an iterator over the information of the text tokens of `file` that are not
+/// part of a comment.
+fn code_tokens(file: &AnnotatedFile) -> impl Iterator<Item = &LexemeInfo> {
+    file.tokens().iter().filter_map(|t| match t.token() {
+        Lexeme::Text(info) if !t.is_comment() => Some(info),
+        _ => None,
+    })
+}
+
+/// Options selecting which checks are run by [`check`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CheckOptions {
+    /// The version of the game targeted by the map script.
+    pub version: GameVersion,
+    /// Whether to report section headers within comments.
+    pub commented_sections: bool,
+    /// Whether to report statements repeating the previous statement exactly, which are
+    /// often accidental duplicates.
+    pub repeated_statements: bool,
+    /// If present, the maximum depth to which `if` and `start_random` blocks may be
+    /// nested before a warning is reported.
+    pub max_nesting_depth: Option<usize>,
+    /// The constants and labels defined outside of the file, such as by a shared
+    /// prelude, which are not reported as unknown.
+    pub known_names: Vec<String>,
+    /// The options setting the severity of unmatched comment delimiters.
+    pub annotate_options: AnnotateOptions,
+    /// If present, how many more players than are given lands a handled player count may
+    /// have before [`check_player_lands`] reports that some players may not be placed.
+    pub unplaced_players_margin: Option<usize>,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        Self {
+            version: GameVersion::default(),
+            commented_sections: true,
+            repeated_statements: true,
+            max_nesting_depth: None,
+            known_names: vec![],
+            annotate_options: AnnotateOptions::default(),
+            unplaced_players_margin: Some(0),
+        }
+    }
+}
+
+/// Runs every check selected by `options` on `file`.
+/// Returns the diagnostics sorted by their position in the file.
+pub fn check(file: &AnnotatedFile, options: &CheckOptions) -> Vec<Diagnostic> {
+    let mut diagnostics = check_if_labels(file, &options.known_names);
+    diagnostics.extend(file.comment_diagnostics(&options.annotate_options));
+    diagnostics.extend(check_player_setup(file));
+    diagnostics.extend(check_lobby_settings(file));
+    diagnostics.extend(check_constant_versions(file, options.version));
+    diagnostics.extend(check_deprecated_constants(file));
+    diagnostics.extend(check_const_cycles(file));
+    diagnostics.extend(check_assign_types(file));
+    diagnostics.extend(check_resource_arguments(file));
+    diagnostics.extend(check_numeric_arguments(file));
+    diagnostics.extend(check_unexpected_arguments(file));
+    diagnostics.extend(check_argument_kinds(file));
+    diagnostics.extend(check_non_ascii(file));
+    diagnostics.extend(check_invisible_characters(file));
+    diagnostics.extend(check_capitalization(file));
+    diagnostics.extend(check_preamble_commands(file));
+    diagnostics.extend(check_conditional_chains(file));
+    diagnostics.extend(check_object_properties(file));
+    if options.commented_sections {
+        diagnostics.extend(check_commented_sections(file));
+    }
+    if options.repeated_statements {
+        diagnostics.extend(check_repeated_statements(file));
+    }
+    if let Some(margin) = options.unplaced_players_margin {
+        diagnostics.extend(check_player_lands(file, margin));
+    }
+    if let Some(max_depth) = options.max_nesting_depth {
+        diagnostics.extend(check_nesting_depth(file, max_depth));
+    }
+    diagnostics.sort_by_key(|d| (d.line_number(), d.start_column()));
+    diagnostics
+}
+
+/// Checks that the labels used in `if` and `elseif` conditions are either built-in
+/// labels or flags created with `#define`.
+/// Flags defined anywhere in the file are accepted, regardless of whether the
+/// `#define` appears before or after the condition, as are the `known_names` defined
+/// outside of the file.
+/// Each label within a compound condition is checked.
+pub fn check_if_labels(file: &AnnotatedFile, known_names: &[String]) -> Vec<Diagnostic> {
+    let tokens: Vec<&LexemeInfo> = code_tokens(file).collect();
+    let mut definitions = Definitions::collect(file);
+    definitions.seed(known_names.iter().cloned());
+    let is_known =
+        |name: &str| definitions.is_defined(name) || rms_data::built_in_label(name).is_some();
+
+    let mut diagnostics = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        if !matches!(token.characters(), "if" | "elseif") {
+            continue;
+        }
+        for (_, label) in parse_condition(&tokens, i + 1).labels {
+            if is_known(label.characters()) {
+                continue;
+            }
+            let candidates = rms_data::built_in_labels()
+                .iter()
+                .map(|label| label.name())
+                .chain(definitions.names())
+                .chain(definitions.seeded_names());
+            let message = match did_you_mean(label.characters(), candidates) {
+                Some(suggestion) => format!(
+                    "Unknown label `{}`, did you mean `{suggestion}`?",
+                    label.characters()
+                ),
+                None => format!("Unknown label `{}`.", label.characters()),
+            };
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                "unknown-label",
+                message,
+                label,
+            ));
+        }
+    }
+    diagnostics
+}
+
+/// Which of the built-in labels of a type are referenced by the conditions of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LabelCoverage {
+    /// The type of the labels.
+    label_type: LabelType,
+    /// The labels referenced by an `if` or `elseif` condition, in the order of the data.
+    handled: Vec<&'static str>,
+    /// The labels not referenced by any condition, in the order of the data.
+    missing: Vec<&'static str>,
+}
+
+impl LabelCoverage {
+    /// Returns the type of the labels.
+    pub fn label_type(&self) -> &LabelType {
+        &self.label_type
+    }
+
+    /// Returns the labels referenced by a condition.
+    pub fn handled(&self) -> &[&'static str] {
+        &self.handled
+    }
+
+    /// Returns the labels not referenced by any condition.
+    pub fn missing(&self) -> &[&'static str] {
+        &self.missing
+    }
+}
+
+impl Display for LabelCoverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |names: &[&str]| {
+            let names: Vec<String> = names.iter().map(|name| format!("`{name}`")).collect();
+            names.join(", ")
+        };
+        write!(f, "{}: ", self.label_type)?;
+        match (self.handled.is_empty(), self.missing.is_empty()) {
+            (true, _) => write!(f, "no labels are handled."),
+            (false, true) => write!(f, "handles all of {}.", list(&self.handled)),
+            (false, false) => write!(
+                f,
+                "handles {} but not {}.",
+                list(&self.handled),
+                list(&self.missing)
+            ),
+        }
+    }
+}
+
+/// Returns which of the built-in labels of `label_type` are referenced by the `if` and
+/// `elseif` conditions of `file`, such as which map sizes a script handles.
+/// A negated label is referenced, as its condition still distinguishes the setting.
+pub fn label_coverage(file: &AnnotatedFile, label_type: &LabelType) -> LabelCoverage {
+    let tokens: Vec<&LexemeInfo> = code_tokens(file).collect();
+    let referenced: HashSet<&str> = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| matches!(token.characters(), "if" | "elseif"))
+        .flat_map(|(i, _)| parse_condition(&tokens, i + 1).labels)
+        .map(|(_, label)| label.characters())
+        .collect();
+    let (handled, missing) = rms_data::built_in_labels()
+        .iter()
+        .filter(|label| label.label_type() == Some(label_type))
+        .map(|label| label.name())
+        .partition(|name| referenced.contains(name));
+    LabelCoverage {
+        label_type: label_type.clone(),
+        handled,
+        missing,
+    }
+}
+
+/// Checks that the player and team labels of nested conditions are consistent,
+/// for example that a `PLAYER3_TEAM1` condition is not nested within `2_PLAYER_GAME`.
+///
+/// Only conditions whose labels are all joined by `and` are considered,
+/// and negated labels are ignored, as only then must each label hold within the
+/// condition's branch. Labels joined by `and` within the same condition are also
+/// checked against each other.
+pub fn check_player_setup(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    let tokens: Vec<&LexemeInfo> = code_tokens(file).collect();
+    let mut diagnostics = vec![];
+    // The player setup labels that hold within each enclosing branch.
+    let mut branches: Vec<Vec<(PlayerSetup, &LexemeInfo)>> = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        match token.characters() {
+            "if" | "elseif" => {
+                if token.characters() == "elseif" {
+                    branches.pop();
+                }
+                let condition = parse_condition(&tokens, i + 1);
+                let mut setups: Vec<(PlayerSetup, &LexemeInfo)> = vec![];
+                if condition.conjunctive {
+                    for (negated, label) in condition.labels {
+                        let Some(setup) = rms_data::player_setup(label.characters()) else {
+                            continue;
+                        };
+                        if negated {
+                            continue;
+                        }
+                        let enclosing = branches.iter().flatten().chain(setups.iter());
+                        for (other, other_label) in enclosing {
+                            if setup.is_consistent_with(other) {
+                                continue;
+                            }
+                            let message = format!(
+                                "`{}` is inconsistent with the enclosing condition `{}`.",
+                                label.characters(),
+                                other_label.characters()
+                            );
+                            diagnostics.push(Diagnostic::new(
+                                Severity::Warning,
+                                "inconsistent-player-setup",
+                                message,
+                                label,
+                            ));
+                        }
+                        setups.push((setup, label));
+                    }
+                }
+                branches.push(setups);
+            }
+            "else" => {
+                branches.pop();
+                branches.push(vec![]);
+            }
+            "endif" => {
+                branches.pop();
+            }
+            _ => (),
+        }
+    }
+    diagnostics
+}
+
+/// A lobby setting known to be on or off along a path through the conditions of a script.
+#[derive(Clone, Copy)]
+struct LobbyFact<'a> {
+    /// The label of the setting.
+    label: &'a LexemeInfo,
+    /// The type of the label.
+    label_type: &'static LabelType,
+    /// Whether the setting is on.
+    on: bool,
+}
+
+impl LobbyFact<'_> {
+    /// Returns `true` if this fact and `other` cannot both hold: the same setting both on
+    /// and off, or two settings on that are chosen from the same lobby dropdown menu,
+    /// such as the game modes `REGICIDE` and `DEATH_MATCH`.
+    fn conflicts_with(&self, other: &LobbyFact) -> bool {
+        if self.label.characters() == other.label.characters() {
+            return self.on != other.on;
+        }
+        self.on
+            && other.on
+            && self.label_type == other.label_type
+            && matches!(
+                self.label_type,
+                LabelType::GameMode | LabelType::StartingResources | LabelType::StartingAge
+            )
+    }
+
+    /// Returns the fact that holds when this fact does not.
+    fn negated(self) -> Self {
+        Self {
+            on: !self.on,
+            ..self
+        }
+    }
+}
+
+impl Display for LobbyFact<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let not = if self.on { "" } else { "not " };
+        write!(f, "{not}{}", self.label.characters())
+    }
+}
+
+/// The lobby settings known within the current branch of an `if` block.
+#[derive(Default)]
+struct LobbyBranch<'a> {
+    /// The settings known from the previous branches of the block not being generated.
+    excluded: Vec<LobbyFact<'a>>,
+    /// The settings required by the condition of the current branch.
+    required: Vec<LobbyFact<'a>>,
+    /// The setting of the current branch's condition, if it is a single lobby label, whose
+    /// negation holds in the following branches.
+    single: Option<LobbyFact<'a>>,
+}
+
+/// Checks that the lobby settings assumed along each path through the conditions of
+/// `file` can hold together, reporting a label whose branch is never generated, such as
+/// `REGICIDE` within a `DEATH_MATCH` branch, as only one game mode is selected, or
+/// `FIXED_POSITIONS` within a `not FIXED_POSITIONS` branch.
+///
+/// The settings are the game modes, starting resources, starting ages, and additional
+/// lobby settings. The labels of conditions joined only by `and` are known to hold
+/// within their branch. The label of a single-label condition is known not to hold
+/// in the following `elseif` and `else` branches of its block.
+pub fn check_lobby_settings(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    let tokens: Vec<&LexemeInfo> = code_tokens(file).collect();
+    let mut diagnostics = vec![];
+    let mut blocks: Vec<LobbyBranch> = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        match token.characters() {
+            "if" => blocks.push(LobbyBranch::default()),
+            "elseif" | "else" => {
+                let Some(block) = blocks.last_mut() else {
+                    continue;
+                };
+                block
+                    .excluded
+                    .extend(block.single.take().map(LobbyFact::negated));
+                block.required.clear();
+            }
+            "endif" => {
+                blocks.pop();
+                continue;
+            }
+            _ => continue,
+        }
+        if token.characters() == "else" {
+            continue;
+        }
+        let condition = parse_condition(&tokens, i + 1);
+        let facts: Vec<LobbyFact> = condition
+            .labels
+            .iter()
+            .filter_map(|&(negated, label)| {
+                let label_type = rms_data::built_in_label(label.characters())?
+                    .label_type()
+                    .filter(|label_type| {
+                        matches!(
+                            label_type,
+                            LabelType::GameMode
+                                | LabelType::StartingResources
+                                | LabelType::StartingAge
+                                | LabelType::AdditionalLobbySettings
+                        )
+                    })?;
+                Some(LobbyFact {
+                    label,
+                    label_type,
+                    on: !negated,
+                })
+            })
+            .collect();
+        let mut required = vec![];
+        if condition.conjunctive {
+            for fact in facts.iter() {
+                let known = blocks
+                    .iter()
+                    .flat_map(|block| block.excluded.iter().chain(block.required.iter()))
+                    .chain(required.iter());
+                if let Some(other) = known.into_iter().find(|other| fact.conflicts_with(other)) {
+                    let message = format!(
+                        "`{fact}` conflicts with `{other}`, which holds along this path, so this branch is never generated."
+                    );
+                    diagnostics.push(Diagnostic::new(
+                        Severity::Warning,
+                        "conflicting-lobby-settings",
+                        message,
+                        fact.label,
+                    ));
+                }
+                required.push(*fact);
+            }
+        }
+        let Some(block) = blocks.last_mut() else {
+            continue;
+        };
+        block.single = match (&facts[..], &condition.labels[..]) {
+            ([fact], [_]) => Some(*fact),
+            _ => None,
+        };
+        block.required = required;
+    }
+    diagnostics
+}
+
+/// Reports a handled player count, such as `8_PLAYER_GAME`, exceeding by more than
+/// `margin` the number of players given lands, as some players may not be placed.
+///
+/// This is a heuristic that only flags obvious mismatches. It applies to a script with a
+/// `<LAND_GENERATION>` section and no `create_player_lands`, which places every player.
+/// The players given lands are the distinct literal player numbers of the
+/// `assign_to_player` and `assign_to AT_PLAYER` commands. If a land is assigned to a
+/// team, a color, or a player given by a constant, the players cannot be counted, so
+/// nothing is reported. Only the largest player count of a non-negated label in an `if`
+/// or `elseif` condition is reported, at its last label if several handle it.
+pub fn check_player_lands(file: &AnnotatedFile, margin: usize) -> Vec<Diagnostic> {
+    let text = |i: usize| file.tokens()[i].token().get_info().characters();
+    let mut has_land_generation = false;
+    let mut players = HashSet::new();
+    for line in tokenizer::logical_lines(file) {
+        let arguments: Vec<&str> = line.arguments(file).iter().map(|a| text(a[0])).collect();
+        let player = match (text(line.token_indices()[0]), &arguments[..]) {
+            ("<LAND_GENERATION>", _) => {
+                has_land_generation = true;
+                continue;
+            }
+            ("create_player_lands", _) => return vec![],
+            ("assign_to_player", [player, ..]) | ("assign_to", ["AT_PLAYER", player, ..]) => player,
+            ("assign_to_player" | "assign_to", _) => return vec![],
+            _ => continue,
+        };
+        match player.parse::<usize>() {
+            Ok(player) => players.insert(player),
+            Err(_) => return vec![],
+        };
+    }
+    if !has_land_generation {
+        return vec![];
+    }
+    let tokens: Vec<&LexemeInfo> = code_tokens(file).collect();
+    let largest = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| matches!(token.characters(), "if" | "elseif"))
+        .flat_map(|(i, _)| parse_condition(&tokens, i + 1).labels)
+        .filter(|&(negated, _)| !negated)
+        .filter_map(
+            |(_, label)| match rms_data::player_setup(label.characters())? {
+                PlayerSetup::PlayerCount(count) => Some((count, label)),
+                _ => None,
+            },
+        )
+        .max_by_key(|&(count, _)| count);
+    let Some((count, label)) = largest else {
+        return vec![];
+    };
+    if count <= players.len() + margin {
+        return vec![];
+    }
+    let plural = if players.len() == 1 { "" } else { "s" };
+    let message = format!(
+        "`{}` is handled, but lands are assigned to {} player{plural} and there is no `create_player_lands`, so some players may not be placed.",
+        label.characters(),
+        players.len()
+    );
+    vec![Diagnostic::new(
+        Severity::Info,
+        "unplaced-players",
+        message,
+        label,
+    )]
+}
+
+/// The labels of an `if` or `elseif` condition.
+struct Condition<'a> {
+    /// The labels of the condition, each paired with whether the label is negated.
+    labels: Vec<(bool, &'a LexemeInfo)>,
+    /// Whether the labels are joined only by `and`.
+    conjunctive: bool,
+}
+
+/// Parses the condition beginning at index `i` of `tokens`.
+///
+/// Conditions may be compound, combining labels with `and` and `or` and negating them
+/// with `not`. The condition ends at the first token that does not continue it.
+fn parse_condition<'a>(tokens: &[&'a LexemeInfo], mut i: usize) -> Condition<'a> {
+    let mut labels = vec![];
+    let mut conjunctive = true;
+    loop {
+        let mut negated = false;
+        while i < tokens.len() && tokens[i].characters() == "not" {
+            negated = !negated;
+            i += 1;
+        }
+        let Some(label) = tokens.get(i) else {
+            break;
+        };
+        labels.push((negated, *label));
+        i += 1;
+        match tokens.get(i).map(|t| t.characters()) {
+            Some("and") => i += 1,
+            Some("or") => {
+                conjunctive = false;
+                i += 1;
+            }
+            _ => break,
+        }
+    }
+    Condition {
+        labels,
+        conjunctive,
+    }
+}
+
+/// Reports the section headers that appear within comments, as forgetting to
+/// un-comment a section after debugging silently breaks a map.
+pub fn check_commented_sections(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    file.tokens()
+        .iter()
+        .filter_map(|t| match t.token() {
+            Lexeme::Text(info) if t.is_comment() => Some(info),
+            _ => None,
+        })
+        .filter_map(|info| {
+            let section = Section::from_header(info.characters())?;
+            Some(Diagnostic::new(
+                Severity::Info,
+                "commented-section",
+                format!("Section `{section}` is commented out."),
+                info,
+            ))
+        })
+        .collect()
+}
+
+/// Reports section headers that are not in upper snake case, such as `<player_setup>`,
+/// and known commands written in mixed case, such as `Create_Object`, suggesting the
+/// conventional casing.
+/// A command name written entirely in uppercase, such as `BASE_TERRAIN`, is not
+/// reported, as constants are conventionally named that way.
+pub fn check_capitalization(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    code_tokens(file)
+        .filter_map(|info| {
+            let characters = info.characters();
+            let is_header =
+                characters.len() > 2 && characters.starts_with('<') && characters.ends_with('>');
+            let message = if is_header {
+                let upper = characters.to_ascii_uppercase();
+                if upper == characters {
+                    return None;
+                }
+                format!("Section header `{characters}` should be upper snake case: `{upper}`.")
+            } else {
+                let lower = characters.to_ascii_lowercase();
+                let is_mixed = characters.contains(|c: char| c.is_ascii_lowercase())
+                    && characters.contains(|c: char| c.is_ascii_uppercase());
+                if !is_mixed || !rms_data::commands().iter().any(|c| c.name() == lower) {
+                    return None;
+                }
+                format!("Command `{characters}` should be lower snake case: `{lower}`.")
+            };
+            Some(Diagnostic::new(
+                Severity::Warning,
+                "capitalization",
+                message,
+                info,
+            ))
+        })
+        .collect()
+}
+
+/// Checks that each `assign_to` command is given an assign type constant
+/// as its first argument.
+pub fn check_assign_types(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    let tokens: Vec<&LexemeInfo> = code_tokens(file).collect();
+    tokens
+        .windows(2)
+        .filter(|pair| pair[0].characters() == "assign_to")
+        .filter(|pair| rms_data::assign_type(pair[1].characters()).is_none())
+        .map(|pair| {
+            let expected: Vec<String> = rms_data::assign_types()
+                .iter()
+                .map(|constant| format!("`{}`", constant.name()))
+                .collect();
+            let message = format!(
+                "`{}` is not an assign type, expected one of {}.",
+                pair[1].characters(),
+                expected.join(", ")
+            );
+            Diagnostic::new(Severity::Error, "invalid-assign-type", message, pair[1])
+        })
+        .collect()
+}
+
+/// Checks that each command taking a resource constant, such as
+/// `effect_amount MOD_RESOURCE`, is given a resource constant as its second argument.
+pub fn check_resource_arguments(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    let tokens: Vec<&LexemeInfo> = code_tokens(file).collect();
+    tokens
+        .windows(3)
+        .filter(|args| {
+            rms_data::RESOURCE_COMMANDS.contains(&(args[0].characters(), args[1].characters()))
+        })
+        .filter(|args| rms_data::resource(args[2].characters()).is_none())
+        .map(|args| {
+            let expected: Vec<String> = rms_data::resources()
+                .iter()
+                .map(|constant| format!("`{}`", constant.name()))
+                .collect();
+            let message = format!(
+                "`{}` is not a resource, expected one of {}.",
+                args[2].characters(),
+                expected.join(", ")
+            );
+            Diagnostic::new(Severity::Error, "invalid-resource", message, args[2])
+        })
+        .collect()
+}
+
+/// Returns `true` if the argument `text` may be a number: a numeric literal, an `rnd`
+/// call, or a constant, which may be defined with `#const`.
+fn is_numeric_argument(text: &str) -> bool {
+    text.parse::<i64>().is_ok()
+        || text == "rnd"
+        || text.starts_with("rnd(")
+        || text.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+/// Checks that each command taking a fixed number of numeric arguments, such as the
+/// x and y pair of `land_position`, is given that many, reporting the commands given
+/// fewer, such as only one number of a pair.
+pub fn check_numeric_arguments(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    let info = |i: usize| file.tokens()[i].token().get_info();
+    tokenizer::logical_lines(file)
+        .iter()
+        .filter_map(|line| {
+            let command = info(line.token_indices()[0]);
+            let expected = rms_data::command(command.characters())?.numeric_arguments()?;
+            let given = line
+                .arguments(file)
+                .iter()
+                .take_while(|argument| is_numeric_argument(info(argument[0]).characters()))
+                .count();
+            if given >= expected {
+                return None;
+            }
+            let plural = if expected == 1 { "" } else { "s" };
+            let message = format!(
+                "`{}` takes {expected} numeric argument{plural}, but {given} {} given.",
+                command.characters(),
+                if given == 1 { "was" } else { "were" }
+            );
+            Some(Diagnostic::new(
+                Severity::Error,
+                "missing-numeric-argument",
+                message,
+                command,
+            ))
+        })
+        .collect()
+}
+
+/// Reports the arguments of a kind other than the command expects: a numeric literal
+/// where a named constant is expected, such as `create_object 285`, and a built-in
+/// constant among the leading numeric arguments of a command, such as `base_size GRASS`.
+/// Constants defined with `#const` may be numbers, so they are not reported.
+pub fn check_argument_kinds(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    let info = |i: usize| file.tokens()[i].token().get_info();
+    let mut diagnostics = vec![];
+    for line in tokenizer::logical_lines(file).iter() {
+        let command = info(line.token_indices()[0]);
+        let Some(signature) = rms_data::command(command.characters()) else {
+            continue;
+        };
+        let arguments: Vec<&LexemeInfo> = line
+            .arguments(file)
+            .iter()
+            .map(|argument| info(argument[0]))
+            .collect();
+        if let Some(argument) = arguments.first() {
+            if signature.takes_constant_argument() && argument.characters().parse::<i64>().is_ok() {
+                let message = format!(
+                    "`{}` expects a constant, but was given the number `{}`.",
+                    command.characters(),
+                    argument.characters()
+                );
+                diagnostics.push(Diagnostic::new(
+                    Severity::Warning,
+                    "argument-kind-mismatch",
+                    message,
+                    argument,
+                ));
+            }
+        }
+        let num_numeric = signature.numeric_arguments().unwrap_or(0);
+        for argument in arguments.into_iter().take(num_numeric) {
+            if rms_data::is_built_in_constant(argument.characters()) {
+                let message = format!(
+                    "`{}` expects a number, but was given the constant `{}`.",
+                    command.characters(),
+                    argument.characters()
+                );
+                diagnostics.push(Diagnostic::new(
+                    Severity::Warning,
+                    "argument-kind-mismatch",
+                    message,
+                    argument,
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Reports the tokens following a command that takes no arguments, such as
+/// `random_placement`, as the game does not use them as arguments.
+/// The tokens are not reported if they begin a statement, such as another command.
+pub fn check_unexpected_arguments(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    let info = |i: usize| file.tokens()[i].token().get_info();
+    tokenizer::logical_lines(file)
+        .windows(2)
+        .filter_map(|pair| {
+            let command = info(pair[0].token_indices()[0]);
+            let argument = info(pair[1].token_indices()[0]);
+            let is_argument = argument.characters().starts_with(|c: char| {
+                !c.is_ascii_lowercase() && !matches!(c, '#' | '<' | '{' | '}')
+            });
+            if !rms_data::command(command.characters())?.takes_no_arguments() || !is_argument {
+                return None;
+            }
+            let message = format!(
+                "`{}` takes no arguments, but is followed by `{}`.",
+                command.characters(),
+                argument.characters()
+            );
+            Some(Diagnostic::new(
+                Severity::Warning,
+                "unexpected-argument",
+                message,
+                argument,
+            ))
+        })
+        .collect()
+}
+
+/// Checks the property commands of each `create_object` block.
+///
+/// Blocks missing an expected property, such as `number_of_objects`, are reported with
+/// an info diagnostic at the `create_object` command. Properties that contradict an
+/// earlier property of the same block are reported with a warning.
+pub fn check_object_properties(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    let info = |i: usize| file.tokens()[i].token().get_info();
+    let mut diagnostics = vec![];
+    for block in tokenizer::object_blocks(file) {
+        let command = info(block.command().token_indices()[0]);
+        let object = block.object_name(file).unwrap_or_default();
+        let properties: Vec<&LexemeInfo> = block
+            .properties()
+            .iter()
+            .map(|line| info(line.token_indices()[0]))
+            .collect();
+        for expected in rms_data::EXPECTED_OBJECT_PROPERTIES {
+            if properties.iter().all(|p| p.characters() != expected) {
+                let message = format!("`create_object {object}` has no `{expected}` command.");
+                diagnostics.push(Diagnostic::new(
+                    Severity::Info,
+                    "missing-object-property",
+                    message,
+                    command,
+                ));
+            }
+        }
+        for (i, property) in properties.iter().enumerate() {
+            let contradicted = properties[..i].iter().find(|earlier| {
+                rms_data::CONTRADICTORY_OBJECT_PROPERTIES
+                    .iter()
+                    .any(|&(a, b)| {
+                        (earlier.characters(), property.characters()) == (a, b)
+                            || (earlier.characters(), property.characters()) == (b, a)
+                    })
+            });
+            if let Some(earlier) = contradicted {
+                let message = format!(
+                    "`{}` contradicts `{}` on line {} of the same `create_object {object}`.",
+                    property.characters(),
+                    earlier.characters(),
+                    earlier.line_number()
+                );
+                diagnostics.push(Diagnostic::new(
+                    Severity::Warning,
+                    "contradictory-object-properties",
+                    message,
+                    property,
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Returns `true` if `c` is a zero-width or control character, which may silently join
+/// a text lexeme, `false` otherwise.
+/// The whitespace characters of map scripts are never part of a text lexeme.
+fn is_invisible(c: char) -> bool {
+    c.is_control() || matches!(c, '\u{200b}'..='\u{200d}' | '\u{2060}' | '\u{feff}')
+}
+
+/// Reports each zero-width or control character anywhere in the file, including within
+/// comments, such as a byte order mark left in the middle of a file by a bad merge.
+/// A byte order mark at the start of the file is not reported.
+pub fn check_invisible_characters(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    for token in file.tokens() {
+        let Lexeme::Text(info) = token.token() else {
+            continue;
+        };
+        for (i, (byte_index, c)) in info.characters().char_indices().enumerate() {
+            let is_leading_bom = c == '\u{feff}' && info.byte_range().start + byte_index == 0;
+            if is_invisible(c) && !is_leading_bom {
+                let message = format!(
+                    "Invisible character U+{:04X} in `{}`, remove it.",
+                    u32::from(c),
+                    info.characters().replace(is_invisible, "")
+                );
+                diagnostics.push(Diagnostic::at_char(
+                    Severity::Warning,
+                    "invisible-character",
+                    message,
+                    info,
+                    i,
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Reports each non-ASCII character in code, such as a smart quote pasted from a
+/// document, as the commands and constants of map scripts are ASCII.
+/// Invisible characters are reported by [`check_invisible_characters`] instead.
+/// Characters within comments or within a quoted string, between ASCII double quotes on
+/// the same line, are not reported.
+pub fn check_non_ascii(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut line_number = 0;
+    let mut in_string = false;
+    for info in code_tokens(file) {
+        if info.line_number() != line_number {
+            line_number = info.line_number();
+            in_string = false;
+        }
+        for (i, c) in info.characters().chars().enumerate() {
+            if c == '"' {
+                in_string = !in_string;
+            } else if !c.is_ascii() && !is_invisible(c) && !in_string {
+                let message = format!(
+                    "Non-ASCII character `{c}` (U+{:04X}) in code.",
+                    u32::from(c)
+                );
+                diagnostics.push(Diagnostic::at_char(
+                    Severity::Warning,
+                    "non-ascii",
+                    message,
+                    info,
+                    i,
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Reports `if` and `start_random` blocks nested deeper than `max_depth`.
+/// Each run of blocks exceeding the depth is reported once, at the opening token of its
+/// most deeply nested block.
+pub fn check_nesting_depth(file: &AnnotatedFile, max_depth: usize) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut depth = 0usize;
+    // The depth and opening token of the deepest block exceeding the maximum depth.
+    let mut deepest: Option<(usize, &LexemeInfo)> = None;
+    let mut report = |deepest: &mut Option<(usize, &LexemeInfo)>| {
+        if let Some((depth, info)) = deepest.take() {
+            let message =
+                format!("Blocks are nested {depth} deep, more than the maximum of {max_depth}.");
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                "nesting-depth",
+                message,
+                info,
+            ));
+        }
+    };
+    for info in code_tokens(file) {
+        match info.characters() {
+            "if" | "start_random" => {
+                depth += 1;
+                if depth > max_depth && deepest.is_none_or(|(d, _)| depth > d) {
+                    deepest = Some((depth, info));
+                }
+            }
+            "endif" | "end_random" => {
+                depth = depth.saturating_sub(1);
+                if depth <= max_depth {
+                    report(&mut deepest);
+                }
+            }
+            _ => (),
+        }
+    }
+    report(&mut deepest);
+    diagnostics
+}
+
+/// Reports the first command that appears before the first section header, as commands
+/// placing objects must be within a section.
+/// Directives such as `#const` and `#define`, conditionals, and random blocks are
+/// allowed before the first section.
+pub fn check_preamble_commands(file: &AnnotatedFile) -> Option<Diagnostic> {
+    for line in tokenizer::logical_lines(file) {
+        let Lexeme::Text(info) = file.tokens()[line.token_indices()[0]].token() else {
+            continue;
+        };
+        let command = info.characters();
+        if Section::from_header(command).is_some() {
+            return None;
+        }
+        let is_allowed = matches!(
+            command,
+            "if" | "elseif" | "else" | "endif" | "start_random" | "percent_chance" | "end_random"
+        );
+        if command.starts_with(|c: char| c.is_ascii_lowercase()) && !is_allowed {
+            let message = format!("Command `{command}` appears before any section header.");
+            return Some(Diagnostic::new(
+                Severity::Warning,
+                "command-before-section",
+                message,
+                info,
+            ));
+        }
+    }
+    None
+}
+
+/// Checks the structure of `if` chains, reporting branches that follow an `else` and are
+/// therefore unreachable, `elseif`, `else`, and `endif` without an `if`, and `if`
+/// without an `endif`.
+pub fn check_conditional_chains(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut report = |message: String, info: &LexemeInfo| {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            "malformed-conditional",
+            message,
+            info,
+        ));
+    };
+    // The opening `if` of each enclosing chain, and its `else`, if one has been seen.
+    let mut chains: Vec<(&LexemeInfo, Option<&LexemeInfo>)> = vec![];
+    for info in code_tokens(file) {
+        let keyword = info.characters();
+        match keyword {
+            "if" => chains.push((info, None)),
+            "elseif" | "else" => match chains.last_mut() {
+                None => report(format!("`{keyword}` without a matching `if`."), info),
+                Some((_, Some(else_info))) => {
+                    let message = format!(
+                        "`{keyword}` follows the `else` on line {}, so it is unreachable.",
+                        else_info.line_number()
+                    );
+                    report(message, info);
+                }
+                Some((_, else_info)) => {
+                    if keyword == "else" {
+                        *else_info = Some(info);
+                    }
+                }
+            },
+            "endif" if chains.pop().is_none() => {
+                report(String::from("`endif` without a matching `if`."), info);
+            }
+            _ => (),
+        }
+    }
+    for (if_info, _) in chains {
+        report(String::from("`if` is missing its `endif`."), if_info);
+    }
+    diagnostics
+}
+
+/// Reports each statement that repeats the previous statement with identical arguments,
+/// which is often an accidental duplicate made by copying and pasting.
+/// As some duplicates are intentional, such as placing the same object twice, the
+/// diagnostic is informational. The keywords of conditionals and random blocks and
+/// curly braces are not reported, as they are commonly repeated.
+pub fn check_repeated_statements(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    let text = |line: &tokenizer::LogicalLine| -> Vec<&str> {
+        line.token_indices()
+            .iter()
+            .map(|&i| file.tokens()[i].token().get_info().characters())
+            .collect()
+    };
+    let lines = tokenizer::logical_lines(file);
+    lines
+        .windows(2)
+        .filter_map(|pair| {
+            let statement = text(&pair[1]);
+            let is_structural = matches!(
+                statement[0],
+                "{" | "}"
+                    | "if"
+                    | "elseif"
+                    | "else"
+                    | "endif"
+                    | "start_random"
+                    | "percent_chance"
+                    | "end_random"
+            );
+            if is_structural || statement != text(&pair[0]) {
+                return None;
+            }
+            let message = format!(
+                "`{}` repeats the identical statement on line {}.",
+                statement.join(" "),
+                pair[0].start_line()
+            );
+            Some(Diagnostic::with_span(
+                Severity::Info,
+                "repeated-statement",
+                message,
+                *pair[1].span(),
+            ))
+        })
+        .collect()
+}
+
+/// Checks that the terrain constants used in `file` are available in `version`.
+pub fn check_constant_versions(file: &AnnotatedFile, version: GameVersion) -> Vec<Diagnostic> {
+    code_tokens(file)
+        .filter_map(|info| {
+            let terrain = rms_data::any_terrain_from_name(info.characters())?;
+            if terrain.is_available(version) {
+                return None;
+            }
+            let message = format!("`{}` is not available in {version}.", terrain.name());
+            Some(Diagnostic::new(
+                Severity::Error,
+                "unavailable-constant",
+                message,
+                info,
+            ))
+        })
+        .collect()
+}
+
+/// Reports each use of a constant that was renamed by a later patch, suggesting the
+/// name that replaces it.
+pub fn check_deprecated_constants(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    code_tokens(file)
+        .filter_map(|info| {
+            let constant = rms_data::deprecated_constant(info.characters())?;
+            let message = format!(
+                "`{}` is deprecated, use `{}` instead.",
+                constant.name(),
+                constant.replacement()
+            );
+            Some(Diagnostic::new(
+                Severity::Warning,
+                "deprecated-constant",
+                message,
+                info,
+            ))
+        })
+        .collect()
+}
+
+/// Reports each `#const` whose value refers back to itself, either directly or through
+/// other constants, so it cannot be resolved to a number.
+pub fn check_const_cycles(file: &AnnotatedFile) -> Vec<Diagnostic> {
+    let definitions = Definitions::collect(file);
+    definitions
+        .const_names()
+        .filter_map(|name| match definitions.resolve_const(name) {
+            // Constants that only lead into a cycle are reported where the cycle is.
+            Err(ResolveError::Cycle(names)) if names[0] == name => Some(Diagnostic::new(
+                Severity::Error,
+                "const-cycle",
+                ResolveError::Cycle(names).to_string(),
+                definitions.const_name(name)?,
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+
+    /// Returns the diagnostics of checking the labels of `source`.
+    fn check(source: &str) -> Vec<Diagnostic> {
+        check_if_labels(&AnnotatedFile::annotate(&lexer::lex_str(source)), &[])
+    }
+
+    /// Tests that built-in and defined labels are valid.
+    #[test]
+    fn valid_labels() {
+        let source = "#define MY_FLAG\nif REGICIDE\nelseif MY_FLAG\nelseif TEAM1_SIZE2\nendif";
+        assert!(check(source).is_empty());
+    }
+
+    /// Tests that an unknown label is reported with a suggestion.
+    #[test]
+    fn unknown_label_suggestion() {
+        let diagnostics = check("if REGICDE\nendif");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code(), "unknown-label");
+        assert_eq!(diagnostics[0].line_number(), 1);
+        assert_eq!(diagnostics[0].start_column(), 4);
+        assert_eq!(
+            diagnostics[0].message(),
+            "Unknown label `REGICDE`, did you mean `REGICIDE`?"
+        );
+    }
+
+    /// Tests that each label of a compound condition is checked.
+    #[test]
+    fn compound_condition() {
+        let diagnostics = check("if not REGICIDE and UNKNOWN or MAPSIZE_TINY\nendif");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message(), "Unknown label `UNKNOWN`.");
+    }
+
+    /// Returns the diagnostics of checking the player setup of `source`.
+    fn check_setup(source: &str) -> Vec<Diagnostic> {
+        check_player_setup(&AnnotatedFile::annotate(&lexer::lex_str(source)))
+    }
+
+    /// Tests that consistent nested player and team labels are not reported.
+    #[test]
+    fn player_setup_consistent() {
+        let source = "if 4_PLAYER_GAME\n  if PLAYER3_TEAM2 and TEAM1_SIZE2\n  endif\nelseif 2_TEAM_GAME\n  if PLAYER7_TEAM2\n  endif\nendif";
+        assert!(check_setup(source).is_empty());
+    }
+
+    /// Tests that a player beyond the player count of an enclosing condition is reported.
+    #[test]
+    fn player_setup_inconsistent() {
+        let source = "if 2_PLAYER_GAME\n  if PLAYER3_TEAM1\n  elseif 3_TEAM_GAME or PLAYER4_TEAM1\n  endif\nelse\n  if PLAYER3_TEAM1\n  endif\nendif\nif 2_TEAM_GAME and TEAM3_SIZE1\nendif";
+        let diagnostics = check_setup(source);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].message(),
+            "`PLAYER3_TEAM1` is inconsistent with the enclosing condition `2_PLAYER_GAME`."
+        );
+        assert_eq!(diagnostics[0].line_number(), 2);
+        assert_eq!(
+            diagnostics[1].message(),
+            "`TEAM3_SIZE1` is inconsistent with the enclosing condition `2_TEAM_GAME`."
+        );
+    }
+
+    /// Tests that a constant unavailable in the targeted version is reported.
+    #[test]
+    fn constant_versions() {
+        let file = AnnotatedFile::annotate(&lexer::lex_str("base_terrain BEACH_WHITE"));
+        let diagnostics = check_constant_versions(&file, GameVersion::Hd);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message(),
+            "`BEACH_WHITE` is not available in HD."
+        );
+        assert!(check_constant_versions(&file, GameVersion::De).is_empty());
+    }
+
+    /// Tests that a section header within a comment is reported.
+    #[test]
+    fn commented_section() {
+        let source = "<PLAYER_SETUP>\n/* <LAND_GENERATION>\n*/\n/* /* <CLIFF_GENERATION> */ */";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let diagnostics = check_commented_sections(&file);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity(), Severity::Info);
+        assert_eq!(
+            diagnostics[0].message(),
+            "Section `<LAND_GENERATION>` is commented out."
+        );
+        assert_eq!(diagnostics[0].line_number(), 2);
+        assert_eq!(
+            diagnostics[1].message(),
+            "Section `<CLIFF_GENERATION>` is commented out."
+        );
+
+        let options = CheckOptions {
+            commented_sections: false,
+            ..CheckOptions::default()
+        };
+        assert!(super::check(&file, &options).is_empty());
+        assert_eq!(super::check(&file, &CheckOptions::default()).len(), 2);
+    }
+
+    /// Tests a valid and an invalid assign type argument.
+    #[test]
+    fn assign_types() {
+        let source =
+            "create_land {\n  assign_to AT_COLOR 3 0 0\n}\ncreate_land {\n  assign_to 3 0 0\n}";
+        let diagnostics = check_assign_types(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number(), 5);
+        assert_eq!(
+            diagnostics[0].message(),
+            "`3` is not an assign type, expected one of `AT_PLAYER`, `AT_COLOR`, `AT_TEAM`."
+        );
+    }
+
+    /// Tests that a smart quote in code is reported but an accent in a comment is not.
+    #[test]
+    fn non_ascii() {
+        let source =
+            "/* by José */\ncreate_land {\n  terrain_type “GRASS\"\n}\n#include_drs \"é.def\"";
+        let diagnostics = check_non_ascii(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number(), 3);
+        assert_eq!(diagnostics[0].start_column(), 16);
+        assert_eq!(
+            diagnostics[0].message(),
+            "Non-ASCII character `“` (U+201C) in code."
+        );
+    }
+
+    /// Tests reporting a stray byte order mark within a command name, but not at the start
+    /// of the file.
+    #[test]
+    fn invisible_characters() {
+        let source = "\u{feff}<PLAYER_SETUP>\ncreate\u{feff}_land { }\n/* \u{7} */";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let diagnostics = check_invisible_characters(&file);
+        let positions: Vec<(usize, usize)> = diagnostics
+            .iter()
+            .map(|d| (d.line_number(), d.start_column()))
+            .collect();
+        assert_eq!(positions, vec![(2, 7), (3, 4)]);
+        assert_eq!(
+            diagnostics[0].message(),
+            "Invisible character U+FEFF in `create_land`, remove it."
+        );
+        assert!(check_non_ascii(&file).is_empty());
+    }
+
+    /// Tests reporting blocks nested beyond the maximum depth, and not those within it.
+    #[test]
+    fn nesting_depth() {
+        let source = "if A\n  start_random\n    percent_chance 50\n    if B\n    endif\n    if C\n      if D\n      endif\n    endif\n  end_random\nendif";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        assert!(check_nesting_depth(&file, 4).is_empty());
+        let diagnostics = check_nesting_depth(&file, 2);
+        let lines: Vec<usize> = diagnostics.iter().map(Diagnostic::line_number).collect();
+        assert_eq!(lines, vec![4, 7]);
+        assert_eq!(
+            diagnostics[1].message(),
+            "Blocks are nested 4 deep, more than the maximum of 2."
+        );
+        let diagnostics = check_nesting_depth(&file, 3);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number(), 7);
+        assert!(super::check(&file, &CheckOptions::default())
+            .iter()
+            .all(|d| d.code() != "nesting-depth"));
+    }
+
+    /// Tests reporting the first command before the first section header.
+    #[test]
+    fn preamble_commands() {
+        let check =
+            |source| check_preamble_commands(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+        let source = "/* Map */\n#const MY_LAND 1\nif REGICIDE\n  #define KING\nendif\nstart_random\n  percent_chance 50 #define A\nend_random\n<PLAYER_SETUP>\nrandom_placement";
+        assert_eq!(check(source), None);
+        let source = "#const X 1\nbase_terrain GRASS\nland_percent 10\n<PLAYER_SETUP>";
+        let diagnostic = check(source).unwrap();
+        assert_eq!(diagnostic.line_number(), 2);
+        assert_eq!(
+            diagnostic.message(),
+            "Command `base_terrain` appears before any section header."
+        );
+    }
+
+    /// Tests reporting branches after an `else` and unbalanced conditionals.
+    #[test]
+    fn conditional_chains() {
+        let check =
+            |source| check_conditional_chains(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+        assert!(check("if A\nelseif B\n  if C\n  else\n  endif\nelse\nendif").is_empty());
+        let diagnostics = check("if A\nelse\nelseif B\nelse\nendif\nendif\nif C");
+        let summary: Vec<(usize, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.line_number(), d.message()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    3,
+                    "`elseif` follows the `else` on line 2, so it is unreachable."
+                ),
+                (
+                    4,
+                    "`else` follows the `else` on line 2, so it is unreachable."
+                ),
+                (6, "`endif` without a matching `if`."),
+                (7, "`if` is missing its `endif`."),
+            ]
+        );
+    }
+
+    /// Tests reporting the tokens following commands that take no arguments.
+    #[test]
+    fn unexpected_arguments() {
+        let check =
+            |source| check_unexpected_arguments(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+        assert!(check("<PLAYER_SETUP>\nrandom_placement\n<LAND_GENERATION>\nif A\nelse\nendif\nbase_terrain GRASS").is_empty());
+        let diagnostics =
+            check("create_object GOLD {\n  set_gaia_object_only 12\n}\nrandom_placement\n  TEAM");
+        let summary: Vec<(usize, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.line_number(), d.message()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    2,
+                    "`set_gaia_object_only` takes no arguments, but is followed by `12`."
+                ),
+                (
+                    5,
+                    "`random_placement` takes no arguments, but is followed by `TEAM`."
+                ),
+            ]
+        );
+    }
+
+    /// Tests reporting a handled player count exceeding the players given lands, and the
+    /// setups that are not reported.
+    #[test]
+    fn player_lands() {
+        let check = |source: &str, margin| {
+            check_player_lands(&AnnotatedFile::annotate(&lexer::lex_str(source)), margin)
+        };
+        let source = "<LAND_GENERATION>\nif 2_PLAYER_GAME\nelseif 8_PLAYER_GAME\nendif\ncreate_land {\n  assign_to_player 1\n}\ncreate_land {\n  assign_to AT_PLAYER 2 0 0\n}";
+        let diagnostics = check(source, 0);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity(), Severity::Info);
+        assert_eq!(
+            (diagnostics[0].line_number(), diagnostics[0].start_column()),
+            (3, 8)
+        );
+        assert_eq!(
+            diagnostics[0].message(),
+            "`8_PLAYER_GAME` is handled, but lands are assigned to 2 players and there is no `create_player_lands`, so some players may not be placed."
+        );
+        assert!(check(source, 6).is_empty());
+        assert!(check(&source.replace("8_PLAYER", "not 8_PLAYER"), 0).is_empty());
+        let with_player_lands = format!("{source}\ncreate_player_lands {{ }}");
+        assert!(check(&with_player_lands, 0).is_empty());
+        assert!(check(&source.replace("AT_PLAYER 2", "AT_TEAM 1"), 0).is_empty());
+        let repeated = source.replace("if 2_PLAYER_GAME", "if 8_PLAYER_GAME");
+        let diagnostics = check(&repeated, 0);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            (diagnostics[0].line_number(), diagnostics[0].start_column()),
+            (3, 8)
+        );
+    }
+
+    /// Tests reporting lobby settings that conflict with the settings holding along the
+    /// path of their branch, and the structures that are not reported.
+    #[test]
+    fn lobby_settings() {
+        let check =
+            |source| check_lobby_settings(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+        let source = "if DEATH_MATCH
+  if REGICIDE
+  endif
+elseif FIXED_POSITIONS
+else
+  if FIXED_POSITIONS
+  endif
+endif";
+        let diagnostics = check(source);
+        let found: Vec<(usize, usize, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.line_number(), d.start_column(), d.message()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    2,
+                    6,
+                    "`REGICIDE` conflicts with `DEATH_MATCH`, which holds along this path, so this branch is never generated."
+                ),
+                (
+                    6,
+                    6,
+                    "`FIXED_POSITIONS` conflicts with `not FIXED_POSITIONS`, which holds along this path, so this branch is never generated."
+                ),
+            ]
+        );
+        let compatible = "if DEATH_MATCH
+  if FIXED_POSITIONS
+  elseif not FULL_TECH_TREE
+  endif
+endif
+if REGICIDE or DEATH_MATCH
+  if REGICIDE
+  endif
+endif
+if not REGICIDE
+  if DEATH_MATCH
+  endif
+endif";
+        assert!(check(compatible).is_empty());
+        assert_eq!(
+            check(
+                "if LOW_RESOURCES and HIGH_RESOURCES
+endif"
+            )
+            .len(),
+            1
+        );
+    }
+
+    /// Tests reporting numbers where constants are expected and built-in constants where
+    /// numbers are expected, but not constants defined with `#const`.
+    #[test]
+    fn argument_kinds() {
+        let check =
+            |source| check_argument_kinds(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+        let source = "#const SIZE 5\ncreate_land {\n  base_terrain GRASS\n  base_size SIZE\n  land_position 50 rnd(10,20)\n}";
+        assert!(check(source).is_empty());
+        let diagnostics = check(
+            "create_object 285 {\n  number_of_objects 1\n}\ncreate_land {\n  base_size GRASS\n}",
+        );
+        let summary: Vec<(usize, usize, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.line_number(), d.start_column(), d.message()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    1,
+                    15,
+                    "`create_object` expects a constant, but was given the number `285`."
+                ),
+                (
+                    5,
+                    13,
+                    "`base_size` expects a number, but was given the constant `GRASS`."
+                ),
+            ]
+        );
+    }
+
+    /// Tests reporting object blocks without `number_of_objects` or with contradictory
+    /// properties.
+    #[test]
+    fn object_properties() {
+        let check =
+            |source| check_object_properties(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+        assert!(check("create_object RELIC {\n  number_of_objects 5\n}").is_empty());
+        let diagnostics = check("create_object RELIC {\n  set_gaia_object_only\n}");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity(), Severity::Info);
+        assert_eq!(
+            diagnostics[0].message(),
+            "`create_object RELIC` has no `number_of_objects` command."
+        );
+        assert_eq!(diagnostics[0].line_number(), 1);
+        let diagnostics = check(
+            "create_object GOLD {\n  number_of_objects 3\n  set_tight_grouping\n  set_loose_grouping\n}",
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message(),
+            "`set_loose_grouping` contradicts `set_tight_grouping` on line 3 of the same `create_object GOLD`."
+        );
+        assert_eq!(diagnostics[0].line_number(), 4);
+    }
+
+    /// Tests reporting a statement repeated on consecutive lines, but not repeated
+    /// conditional keywords or statements with different arguments.
+    #[test]
+    fn repeated_statements() {
+        let source = "<LAND_GENERATION>\ncreate_land {\n  terrain_type GRASS\n  land_percent 10\n  land_percent /* x */ 10\n  base_size 5\n  base_size 6\n}\nif A\nendif\nendif\nstart_random\n  percent_chance 50\n  percent_chance 50\nend_random";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let diagnostics = check_repeated_statements(&file);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity(), Severity::Info);
+        assert_eq!(
+            diagnostics[0].message(),
+            "`land_percent 10` repeats the identical statement on line 4."
+        );
+        let span = diagnostics[0].span();
+        assert_eq!((span.start_line(), span.start_column()), (5, 3));
+        assert_eq!((span.end_line(), span.end_column()), (5, 25));
+
+        let options = CheckOptions {
+            repeated_statements: false,
+            ..CheckOptions::default()
+        };
+        assert!(super::check(&file, &options)
+            .iter()
+            .all(|d| d.code() != "repeated-statement"));
+    }
+
+    /// Tests that a deprecated constant is reported with its replacement.
+    #[test]
+    fn deprecated_constants() {
+        let source = "base_terrain DLC_ROCK\n/* DLC_BEACH2 */ base_terrain ROCK_1";
+        let diagnostics =
+            check_deprecated_constants(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code(), "deprecated-constant");
+        assert_eq!(
+            diagnostics[0].message(),
+            "`DLC_ROCK` is deprecated, use `ROCK_1` instead."
+        );
+        assert_eq!(
+            (diagnostics[0].start_column(), diagnostics[0].end_column()),
+            (14, 21)
+        );
+    }
+
+    /// Tests that each constant in a cycle is reported at its definition.
+    #[test]
+    fn const_cycles() {
+        let source = "#const A 5\n#const B A\n#const C D\n#const D C\n#const E C";
+        let mut diagnostics = check_const_cycles(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+        diagnostics.sort_by_key(Diagnostic::line_number);
+        let summary: Vec<(usize, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.line_number(), d.message()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (3, "Constants are defined in a cycle: `C` -> `D` -> `C`."),
+                (4, "Constants are defined in a cycle: `D` -> `C` -> `D`."),
+            ]
+        );
+    }
+
+    /// Tests the coverage of the map sizes handled by a conditional.
+    #[test]
+    fn map_size_coverage() {
+        let source = "if TINY_MAP or SMALL_MAP\nelseif not MEDIUM_MAP\nelseif LARGE_MAP and REGICIDE\nelseif HUGE_MAP\nendif\n/* if GIGANTIC_MAP */";
+        let coverage = label_coverage(
+            &AnnotatedFile::annotate(&lexer::lex_str(source)),
+            &LabelType::MapSizeLegacy,
+        );
+        assert_eq!(
+            coverage.handled(),
+            [
+                "TINY_MAP",
+                "SMALL_MAP",
+                "MEDIUM_MAP",
+                "LARGE_MAP",
+                "HUGE_MAP"
+            ]
+        );
+        assert_eq!(coverage.missing(), ["GIGANTIC_MAP", "LUDIKRIS_MAP"]);
+        assert_eq!(
+            coverage.to_string(),
+            "Map Size Legacy: handles `TINY_MAP`, `SMALL_MAP`, `MEDIUM_MAP`, `LARGE_MAP`, `HUGE_MAP` but not `GIGANTIC_MAP`, `LUDIKRIS_MAP`."
+        );
+        assert_eq!("MapSizeLegacy".parse(), Ok(LabelType::MapSizeLegacy));
+    }
+
+    /// Tests that labels seeded from outside of the file are not reported.
+    #[test]
+    fn seeded_labels() {
+        let file = AnnotatedFile::annotate(&lexer::lex_str("if SHARED_FLAG\nendif"));
+        let options = CheckOptions {
+            known_names: vec![String::from("SHARED_FLAG")],
+            ..CheckOptions::default()
+        };
+        assert!(super::check(&file, &options)
+            .iter()
+            .all(|d| d.code() != "unknown-label"));
+        assert_eq!(
+            super::check(&file, &CheckOptions::default())[0].code(),
+            "unknown-label"
+        );
+    }
+
+    /// Tests a lowercase section header and a title case command.
+    #[test]
+    fn capitalization() {
+        let source = "<player_setup>\nrandom_placement\n<OBJECTS_GENERATION>\nCreate_Object VILLAGER\n/* <lowercase> */\n#const BASE_TERRAIN 0";
+        let diagnostics = check_capitalization(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+        let messages: Vec<&str> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Section header `<player_setup>` should be upper snake case: `<PLAYER_SETUP>`.",
+                "Command `Create_Object` should be lower snake case: `create_object`."
+            ]
+        );
+        assert_eq!(diagnostics[1].line_number(), 4);
+    }
+
+    /// Tests that the diagnostics of the checks have explanations.
+    #[test]
+    fn explained_codes() {
+        let source = "base_terrain DLC_ROCK\n<LAND_GENERATION>\nif UNKNOWN\nendif\nendif\nassign_to AT_X 0 0 0\nland_position 5\n/* <ELEVATION_GENERATION> */\nCreate_Land { }\n#const A A";
+        let diagnostics = super::check(
+            &AnnotatedFile::annotate(&lexer::lex_str(source)),
+            &CheckOptions::default(),
+        );
+        assert!(diagnostics.len() >= 8);
+        for diagnostic in diagnostics {
+            assert!(diagnostic.id().is_some(), "{}", diagnostic.code());
+        }
+    }
+
+    /// Tests a command given one versus two numbers of a pair.
+    #[test]
+    fn numeric_arguments() {
+        let check =
+            |source| check_numeric_arguments(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+        assert!(check("land_position 50 rnd ( 10 , 90 )\nbase_size MAX_SIZE").is_empty());
+        let diagnostics = check("create_land {\n  land_position 50\n  base_size 7\n}");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number(), 2);
+        assert_eq!(
+            diagnostics[0].message(),
+            "`land_position` takes 2 numeric arguments, but 1 was given."
+        );
+        assert_eq!(
+            check("percent_chance\n")[0].message(),
+            "`percent_chance` takes 1 numeric argument, but 0 were given."
+        );
+    }
+
+    /// Tests valid and invalid resource arguments.
+    #[test]
+    fn resource_arguments() {
+        let check =
+            |source| check_resource_arguments(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+        assert!(check("effect_amount MOD_RESOURCE AMOUNT_STARTING_FOOD ATTR_ADD 50").is_empty());
+        assert!(check("effect_amount SET_ATTRIBUTE VILLAGER ATTR_SET 0").is_empty());
+        let diagnostics = check("effect_percent MOD_RESOURCE AMOUNT_RELICS ATTR_ADD 50");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].start_column(), 29);
+        assert_eq!(
+            diagnostics[0].message(),
+            "`AMOUNT_RELICS` is not a resource, expected one of `AMOUNT_FOOD`, `AMOUNT_WOOD`, `AMOUNT_STONE`, `AMOUNT_GOLD`, `AMOUNT_STARTING_FOOD`, `AMOUNT_STARTING_WOOD`, `AMOUNT_STARTING_STONE`, `AMOUNT_STARTING_GOLD`."
+        );
+    }
+
+    /// Tests that labels within comments are not checked.
+    #[test]
+    fn commented_condition() {
+        assert!(check("/* if UNKNOWN */").is_empty());
+    }
+}
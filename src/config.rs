@@ -0,0 +1,143 @@
+//! Defaults for the command-line application read from an `.rms-tools.toml` file, so
+//! that a map repository need not repeat its flags on every run.
+
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+/// The name of the configuration file.
+pub const CONFIG_FILE_NAME: &str = ".rms-tools.toml";
+
+/// The error of reading a configuration file.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be read.
+    Unreadable(io::Error),
+    /// The file is not a valid configuration.
+    Invalid(toml::de::Error),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unreadable(e) => write!(f, "could not be read: {e}"),
+            Self::Invalid(e) => write!(f, "is not a valid configuration: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The defaults read from a configuration file. Every value is optional, and a value
+/// that is absent leaves the application's default in place.
+///
+/// For example:
+///
+/// ```toml
+/// maps_dir = "scripts"
+/// out_dir = "site"
+/// lints = ["unknown-label", "invalid-assign-type"]
+/// fail_on = "warning"
+/// theme = "style/dark.css"
+/// ```
+///
+/// Relative paths are relative to the directory containing the configuration file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The directory of the map scripts.
+    maps_dir: Option<PathBuf>,
+    /// The directory to which the html is written.
+    out_dir: Option<PathBuf>,
+    /// The codes of the diagnostics to report, or `None` to report every diagnostic.
+    lints: Option<Vec<String>>,
+    /// The least severe diagnostic that causes a nonzero exit, as accepted by
+    /// `--fail-on`.
+    fail_on: Option<String>,
+    /// The stylesheet copied beside the rendered html.
+    theme: Option<PathBuf>,
+    /// The directory containing the configuration file.
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+impl Config {
+    /// Parses the configuration `text` of a file in the directory `dir`.
+    pub fn parse(text: &str, dir: &Path) -> Result<Self, ConfigError> {
+        let config: Config = toml::from_str(text).map_err(ConfigError::Invalid)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            ..config
+        })
+    }
+
+    /// Reads the configuration file at `path`.
+    pub fn read(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(ConfigError::Unreadable)?;
+        Self::parse(&text, path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// Returns the configured directory of the map scripts.
+    pub fn maps_dir(&self) -> Option<PathBuf> {
+        self.maps_dir.as_ref().map(|dir| self.dir.join(dir))
+    }
+
+    /// Returns the configured directory to which the html is written.
+    pub fn out_dir(&self) -> Option<PathBuf> {
+        self.out_dir.as_ref().map(|dir| self.dir.join(dir))
+    }
+
+    /// Returns the configured stylesheet copied beside the rendered html.
+    pub fn theme(&self) -> Option<PathBuf> {
+        self.theme.as_ref().map(|theme| self.dir.join(theme))
+    }
+
+    /// Returns the configured least severe diagnostic that causes a nonzero exit, such as
+    /// `warning` or `never`.
+    pub fn fail_on(&self) -> Option<&str> {
+        self.fail_on.as_deref()
+    }
+
+    /// Returns whether diagnostics with the code `code` are reported.
+    /// Every diagnostic is reported if the configuration does not list its lints.
+    pub fn lint_enabled(&self, code: &str) -> bool {
+        self.lints
+            .as_ref()
+            .is_none_or(|lints| lints.iter().any(|lint| lint == code))
+    }
+}
+
+/// Returns the path of the configuration file in `dir` or in the nearest of its
+/// ancestors, or `None` if there is no configuration file.
+pub fn discover(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|ancestor| ancestor.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that paths are relative to the directory of the file and that only the
+    /// listed lints are enabled.
+    #[test]
+    fn parse_values() {
+        let text = "maps_dir = \"scripts\"\nlints = [\"unknown-label\"]\nfail_on = \"warning\"\n";
+        let config = Config::parse(text, Path::new("repo")).unwrap();
+        assert_eq!(config.maps_dir(), Some(PathBuf::from("repo/scripts")));
+        assert_eq!(config.out_dir(), None);
+        assert_eq!(config.fail_on(), Some("warning"));
+        assert!(config.lint_enabled("unknown-label"));
+        assert!(!config.lint_enabled("invalid-assign-type"));
+        assert!(Config::default().lint_enabled("invalid-assign-type"));
+        assert!(matches!(
+            Config::parse("map_dir = \"x\"", Path::new("")),
+            Err(ConfigError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,214 @@
+//! Definitions of constants and labels made by the directives of a map script.
+
+use std::{collections::HashMap, collections::HashSet, fmt::Display};
+
+use crate::{
+    annotater::AnnotatedFile,
+    lexer::{Lexeme, LexemeInfo},
+};
+
+/// The error of resolving a `#const` to a number.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ResolveError {
+    /// The name is not defined by a `#const`.
+    Undefined(String),
+    /// The value of the constant is neither a number nor another constant.
+    NotANumber(String),
+    /// The constants are defined in terms of each other. The names are listed in the order
+    /// in which they were followed, ending with the first name repeated.
+    Cycle(Vec<String>),
+}
+
+impl Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Undefined(name) => write!(f, "`{name}` is not defined by a `#const`."),
+            Self::NotANumber(value) => write!(f, "`{value}` is not a number."),
+            Self::Cycle(names) => {
+                let names: Vec<String> = names.iter().map(|name| format!("`{name}`")).collect();
+                write!(
+                    f,
+                    "Constants are defined in a cycle: {}.",
+                    names.join(" -> ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// The constants and labels defined by the `#const` and `#define` directives of a file.
+/// Directives within comments are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definitions<'a> {
+    /// The name and value tokens of each `#const`, keyed by name.
+    /// If a constant is defined more than once, the final definition is kept.
+    consts: HashMap<&'a str, (&'a LexemeInfo, &'a LexemeInfo)>,
+    /// The labels created with `#define`.
+    defines: HashSet<&'a str>,
+    /// The names defined outside of the file, such as by a shared prelude.
+    seeded: HashSet<String>,
+}
+
+impl<'a> Definitions<'a> {
+    /// Collects the definitions of `file`.
+    pub fn collect(file: &'a AnnotatedFile) -> Self {
+        let tokens: Vec<&LexemeInfo> = file
+            .tokens()
+            .iter()
+            .filter_map(|t| match t.token() {
+                Lexeme::Text(info) if !t.is_comment() => Some(info),
+                _ => None,
+            })
+            .collect();
+        let mut consts = HashMap::new();
+        let mut defines = HashSet::new();
+        for (i, token) in tokens.iter().enumerate() {
+            match token.characters() {
+                "#const" => {
+                    if let [name, value, ..] = tokens[i + 1..] {
+                        consts.insert(name.characters(), (name, value));
+                    }
+                }
+                "#define" => {
+                    if let Some(label) = tokens.get(i + 1) {
+                        defines.insert(label.characters());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self {
+            consts,
+            defines,
+            seeded: HashSet::new(),
+        }
+    }
+
+    /// Adds `names` as defined outside of the file, such as by a standard library of
+    /// constants and labels shared by many maps.
+    /// The names of another file's definitions may be seeded with [`Definitions::names`].
+    pub fn seed<S: Into<String>>(&mut self, names: impl IntoIterator<Item = S>) {
+        self.seeded.extend(names.into_iter().map(Into::into));
+    }
+
+    /// Returns the names of the constants and labels defined by the file's directives,
+    /// in no particular order. Seeded names are not included.
+    pub fn names(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.consts.keys().chain(self.defines.iter()).copied()
+    }
+
+    /// Returns `true` if `label` is created with `#define` or is seeded, `false` otherwise.
+    pub fn is_defined(&self, label: &str) -> bool {
+        self.defines.contains(label) || self.seeded.contains(label)
+    }
+
+    /// Returns the names that are seeded, in no particular order.
+    pub fn seeded_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.seeded.iter().map(String::as_str)
+    }
+
+    /// Returns the names of the constants defined with `#const`, in no particular order.
+    pub fn const_names(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.consts.keys().copied()
+    }
+
+    /// Returns the name token of the `#const` defining `name`, or `None` if there is none.
+    pub fn const_name(&self, name: &str) -> Option<&'a LexemeInfo> {
+        self.consts.get(name).map(|&(name, _)| name)
+    }
+
+    /// Returns the value token of the `#const` defining `name`, or `None` if there is none.
+    pub fn const_value(&self, name: &str) -> Option<&'a LexemeInfo> {
+        self.consts.get(name).map(|&(_, value)| value)
+    }
+
+    /// Resolves the `#const` named `name` to a number.
+    /// A value that names another `#const` is resolved in turn.
+    ///
+    /// Returns an error if `name` or a constant it refers to is not defined,
+    /// if a value is neither a number nor a constant, or if the constants refer to
+    /// each other in a cycle.
+    pub fn resolve_const(&self, name: &str) -> Result<i64, ResolveError> {
+        let mut followed: Vec<&str> = vec![];
+        let mut current = name;
+        loop {
+            if followed.contains(&current) {
+                let start = followed.iter().position(|&n| n == current).unwrap_or(0);
+                let mut cycle: Vec<String> =
+                    followed[start..].iter().map(|&n| String::from(n)).collect();
+                cycle.push(String::from(current));
+                return Err(ResolveError::Cycle(cycle));
+            }
+            let value = match self.const_value(current) {
+                Some(value) => value.characters(),
+                None if followed.is_empty() => {
+                    return Err(ResolveError::Undefined(String::from(current)))
+                }
+                None => return Err(ResolveError::NotANumber(String::from(current))),
+            };
+            if let Ok(number) = value.parse() {
+                return Ok(number);
+            }
+            followed.push(current);
+            current = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+
+    /// Tests resolving direct values, indirections, and cycles.
+    #[test]
+    fn resolve_consts() {
+        let source = "#const A 5\n#const B A\n/* #const A 6 */\n#const C D\n#const D C\n#const E E\n#const F GRASS\n#define FLAG";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let definitions = Definitions::collect(&file);
+        assert_eq!(definitions.resolve_const("A"), Ok(5));
+        assert_eq!(definitions.resolve_const("B"), Ok(5));
+        assert_eq!(
+            definitions.resolve_const("C"),
+            Err(ResolveError::Cycle(vec![
+                String::from("C"),
+                String::from("D"),
+                String::from("C")
+            ]))
+        );
+        assert_eq!(
+            definitions.resolve_const("E").unwrap_err().to_string(),
+            "Constants are defined in a cycle: `E` -> `E`."
+        );
+        assert_eq!(
+            definitions.resolve_const("F"),
+            Err(ResolveError::NotANumber(String::from("GRASS")))
+        );
+        assert_eq!(
+            definitions.resolve_const("G"),
+            Err(ResolveError::Undefined(String::from("G")))
+        );
+        assert!(definitions.is_defined("FLAG"));
+        assert!(!definitions.is_defined("SHARED"));
+        assert_eq!(definitions.const_name("B").unwrap().line_number(), 2);
+    }
+
+    /// Tests seeding the names of a prelude file.
+    #[test]
+    fn seed_prelude() {
+        let prelude = AnnotatedFile::annotate(&lexer::lex_str("#define SHARED\n#const MAX 9"));
+        let file = AnnotatedFile::annotate(&lexer::lex_str("#define LOCAL"));
+        let mut definitions = Definitions::collect(&file);
+        definitions.seed(Definitions::collect(&prelude).names());
+        definitions.seed(["LISTED"]);
+        assert!(definitions.is_defined("SHARED"));
+        assert!(definitions.is_defined("MAX"));
+        assert!(definitions.is_defined("LISTED"));
+        assert!(definitions.is_defined("LOCAL"));
+        let mut names: Vec<&str> = definitions.names().collect();
+        names.sort();
+        assert_eq!(names, vec!["LOCAL"]);
+    }
+}
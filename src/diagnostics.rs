@@ -0,0 +1,259 @@
+//! Diagnostics reported by the checkers for problems found in a map script.
+
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::{
+    explain::{self, Explanation},
+    lexer::{Lexeme, LexemeInfo},
+    source::SourceFile,
+    span::Span,
+};
+
+/// How severe a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Information that does not indicate a problem.
+    Info,
+    /// A likely mistake that does not prevent the script from running.
+    Warning,
+    /// A mistake that prevents the script from working as intended.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Severity::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Info => "info",
+                Warning => "warning",
+                Error => "error",
+            }
+        )
+    }
+}
+
+/// A problem found in a map script, located at a range of columns within a line.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    /// How severe the problem is.
+    severity: Severity,
+    /// The short, kebab-case code identifying the kind of problem.
+    code: &'static str,
+    /// The message describing the problem.
+    message: String,
+    /// The location of the problem.
+    span: Span,
+}
+
+impl Diagnostic {
+    /// Constructs a new diagnostic located at the lexeme with information `info`.
+    pub fn new(severity: Severity, code: &'static str, message: String, info: &LexemeInfo) -> Self {
+        Self {
+            severity,
+            code,
+            message,
+            span: info.span(),
+        }
+    }
+
+    /// Constructs a new diagnostic located at `span`, such as that of a logical line.
+    pub fn with_span(severity: Severity, code: &'static str, message: String, span: Span) -> Self {
+        Self {
+            severity,
+            code,
+            message,
+            span,
+        }
+    }
+
+    /// Constructs a new diagnostic located at the single character at the 0-indexed
+    /// `char_index` of the lexeme with information `info`.
+    pub fn at_char(
+        severity: Severity,
+        code: &'static str,
+        message: String,
+        info: &LexemeInfo,
+        char_index: usize,
+    ) -> Self {
+        let (byte_index, c) = info
+            .characters()
+            .char_indices()
+            .nth(char_index)
+            .expect("The character must be within the lexeme.");
+        let column = info.start_column() + char_index;
+        let start_byte = info.byte_range().start + byte_index;
+        Self {
+            severity,
+            code,
+            message,
+            span: Span::new(
+                info.line_number(),
+                column,
+                info.line_number(),
+                column,
+                start_byte..start_byte + c.len_utf8(),
+            ),
+        }
+    }
+
+    /// Returns the severity of this diagnostic.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Returns the code identifying the kind of this diagnostic.
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    /// Returns the stable numbered id of the kind of problem, such as `RMS001`, whose
+    /// explanation is shown by `--explain`, or `None` if the kind has no explanation.
+    pub fn id(&self) -> Option<&'static str> {
+        explain::explanation(self.code).map(Explanation::id)
+    }
+
+    /// Returns the message describing this diagnostic.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the location of this diagnostic.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Returns this diagnostic's 1-indexed line number.
+    pub fn line_number(&self) -> usize {
+        self.span.start_line()
+    }
+
+    /// Returns this diagnostic's 1-indexed start column.
+    pub fn start_column(&self) -> usize {
+        self.span.start_column()
+    }
+
+    /// Returns this diagnostic's 1-indexed end column.
+    pub fn end_column(&self) -> usize {
+        self.span.end_column()
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}[{}]: {}",
+            self.line_number(),
+            self.start_column(),
+            self.severity,
+            self.code,
+            self.message
+        )
+    }
+}
+
+/// The number of columns to which a tab is expanded in a rendered snippet.
+const TAB_WIDTH: usize = 4;
+
+/// Returns `diagnostic` of the file `source` rendered for a terminal in the style of
+/// `rustc`, with the source line of its start and a `^` caret underline beneath its span:
+///
+/// ```text
+/// warning[unknown-label]: Unknown label `UNKNOWN`.
+///  --> maps/a.rms:2:4
+///   |
+/// 2 | if UNKNOWN
+///   |    ^^^^^^^
+/// ```
+///
+/// Tabs in the source line are expanded to spaces, and the caret is aligned with the
+/// expanded line. A span continuing onto later lines is underlined to the end of its
+/// first line.
+pub fn render_snippet(source: &SourceFile, diagnostic: &Diagnostic) -> String {
+    let path = source.path().display();
+    let line_number = diagnostic.line_number();
+    let gutter = " ".repeat(line_number.to_string().len());
+    let mut snippet = format!(
+        "{}[{}]: {}\n{gutter}--> {path}:{line_number}:{}\n",
+        diagnostic.severity(),
+        diagnostic.code(),
+        diagnostic.message(),
+        diagnostic.start_column(),
+    );
+    let Some(line) = source.lexemes().lines().nth(line_number - 1) else {
+        return snippet;
+    };
+    let end_column = if diagnostic.span().end_line() == line_number {
+        diagnostic.end_column()
+    } else {
+        usize::MAX
+    };
+    let (mut text, mut underline) = (String::new(), String::new());
+    let characters = line
+        .iter()
+        .filter(|lexeme| !matches!(lexeme, Lexeme::LineBreak(_)))
+        .flat_map(|lexeme| lexeme.get_info().characters().chars());
+    for (i, c) in characters.enumerate() {
+        let column = i + 1;
+        let width = if c == '\t' { TAB_WIDTH } else { 1 };
+        if c == '\t' {
+            text.push_str(&" ".repeat(TAB_WIDTH));
+        } else {
+            text.push(c);
+        }
+        if column <= end_column {
+            let mark = if column < diagnostic.start_column() {
+                " "
+            } else {
+                "^"
+            };
+            underline.push_str(&mark.repeat(width));
+        }
+    }
+    // A span past the final character, such as that of a missing token, gets one caret.
+    if !underline.contains('^') {
+        underline.push('^');
+    }
+    snippet.push_str(&format!(
+        "{gutter} |\n{line_number} | {}\n{gutter} | {underline}\n",
+        text.trim_end()
+    ));
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{annotater::AnnotatedFile, checker};
+
+    /// Tests that the caret is aligned beneath a span following a leading tab.
+    #[test]
+    fn snippet_after_tab() {
+        let source = SourceFile::from_str(
+            "maps/a.rms",
+            "<PLAYER_SETUP>\nif A\n\tif UNKNOWN /* x */\n\tendif\nendif",
+        );
+        let file = AnnotatedFile::annotate(source.lexemes());
+        let diagnostics = checker::check(&file, &Default::default());
+        let diagnostic = diagnostics
+            .iter()
+            .find(|d| d.code() == "unknown-label" && d.line_number() == 3)
+            .unwrap();
+        assert_eq!(
+            render_snippet(&source, diagnostic),
+            concat!(
+                "warning[unknown-label]: Unknown label `UNKNOWN`.\n",
+                " --> maps/a.rms:3:5\n",
+                "  |\n",
+                "3 |     if UNKNOWN /* x */\n",
+                "  |        ^^^^^^^\n",
+            )
+        );
+    }
+}
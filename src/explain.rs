@@ -0,0 +1,112 @@
+//! Longer explanations of the diagnostics reported by the checker, each identified by a
+//! stable numbered id, such as `RMS001`, and by its kebab-case code.
+
+use std::{fmt::Display, sync::OnceLock};
+
+use serde::Deserialize;
+
+/// The contents of the explanations file.
+const CODES_FILE: &str = include_str!("../data/diagnostic_codes.toml");
+
+/// The explanation of a kind of diagnostic, with an example and its fix.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Deserialize)]
+pub struct Explanation {
+    /// The stable numbered id of the diagnostic, such as `RMS001`.
+    id: String,
+    /// The kebab-case code of the diagnostic, such as `unknown-label`.
+    code: String,
+    /// The description of the problem and why it matters.
+    explanation: String,
+    /// An example script with the problem.
+    example: String,
+    /// The example script with the problem fixed.
+    fix: String,
+}
+
+impl Explanation {
+    /// Returns the stable numbered id of the diagnostic, such as `RMS001`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the kebab-case code of the diagnostic, such as `unknown-label`.
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Returns the description of the problem.
+    pub fn explanation(&self) -> &str {
+        &self.explanation
+    }
+
+    /// Returns an example script with the problem.
+    pub fn example(&self) -> &str {
+        &self.example
+    }
+
+    /// Returns the example script with the problem fixed.
+    pub fn fix(&self) -> &str {
+        &self.fix
+    }
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let indent = |script: &str| -> String {
+            script.lines().map(|line| format!("    {line}\n")).collect()
+        };
+        writeln!(f, "{} ({})", self.id, self.code)?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.explanation)?;
+        writeln!(f)?;
+        writeln!(f, "For example:")?;
+        writeln!(f)?;
+        write!(f, "{}", indent(&self.example))?;
+        writeln!(f)?;
+        writeln!(f, "Fixed:")?;
+        writeln!(f)?;
+        write!(f, "{}", indent(&self.fix))
+    }
+}
+
+/// The contents of the explanations file.
+#[derive(Debug, Deserialize)]
+struct Codes {
+    /// The explanations, in the order of their ids.
+    codes: Vec<Explanation>,
+}
+
+/// Returns the explanation of every diagnostic, in the order of their ids.
+pub fn explanations() -> &'static [Explanation] {
+    static CODES: OnceLock<Codes> = OnceLock::new();
+    &CODES
+        .get_or_init(|| toml::from_str(CODES_FILE).expect("The codes file must be valid."))
+        .codes
+}
+
+/// Returns the explanation of the diagnostic with the id or code `name`, or `None` if
+/// there is no such diagnostic. Ids are matched case-insensitively.
+pub fn explanation(name: &str) -> Option<&'static Explanation> {
+    explanations()
+        .iter()
+        .find(|explanation| explanation.id.eq_ignore_ascii_case(name) || explanation.code == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the ids are numbered in order and that ids and codes find explanations.
+    #[test]
+    fn ids_and_codes() {
+        for (i, entry) in explanations().iter().enumerate() {
+            assert_eq!(entry.id(), format!("RMS{:03}", i + 1));
+            assert_eq!(explanation(entry.code()), Some(entry));
+        }
+        assert_eq!(explanation("rms001").unwrap().code(), "unknown-label");
+        assert_eq!(explanation("RMS999"), None);
+        let text = explanation("deprecated-constant").unwrap().to_string();
+        assert!(text.starts_with("RMS004 (deprecated-constant)\n\n"));
+        assert!(text.ends_with("Fixed:\n\n    base_terrain ROCK_1\n"));
+    }
+}
@@ -0,0 +1,649 @@
+//! Exports annotated files to formats used by other tools.
+
+use std::{fmt::Write, io};
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    annotater::AnnotatedFile,
+    annotater::HighlightKind,
+    diagnostics::{Diagnostic, Severity},
+    explain,
+    lexer::Lexeme,
+};
+
+/// The legend of the semantic token types, in the order of their indices.
+/// A language server advertises this list in its `semanticTokensProvider` capability.
+pub const SEMANTIC_TOKEN_TYPES: &[&str] = &[
+    "comment",
+    "function",
+    "number",
+    "enumMember",
+    "variable",
+    "namespace",
+    "keyword",
+    "macro",
+];
+
+/// Returns the index of `kind` in [`SEMANTIC_TOKEN_TYPES`].
+fn semantic_token_type(kind: HighlightKind) -> u32 {
+    match kind {
+        HighlightKind::Comment => 0,
+        HighlightKind::Function => 1,
+        HighlightKind::Number => 2,
+        HighlightKind::Constant => 3,
+        HighlightKind::Resource => 4,
+        HighlightKind::Section => 5,
+        HighlightKind::Command => 6,
+        HighlightKind::Directive => 7,
+    }
+}
+
+/// Returns the highlighted tokens of `file` in the Language Server Protocol's
+/// semantic tokens encoding, as returned by `textDocument/semanticTokens/full`.
+///
+/// Each highlighted text token is encoded as five integers: the line relative to the
+/// previous token, the start character relative to the previous token if on the same
+/// line or to the start of the line otherwise, the length, the token type index into
+/// [`SEMANTIC_TOKEN_TYPES`], and the token modifiers, which are always 0.
+/// Characters are counted in UTF-16 code units, the protocol's default position encoding.
+pub fn semantic_tokens(file: &AnnotatedFile) -> Vec<u32> {
+    let mut data = vec![];
+    // The zero-indexed line and UTF-16 character of the current lexeme.
+    let (mut line, mut character) = (0u32, 0u32);
+    // The position of the previously encoded token.
+    let (mut previous_line, mut previous_character) = (0u32, 0u32);
+    for token in file.tokens() {
+        let info = token.token().get_info();
+        let length = info.characters().encode_utf16().count() as u32;
+        match token.token() {
+            Lexeme::LineBreak(_) => {
+                line += 1;
+                character = 0;
+                continue;
+            }
+            Lexeme::Whitespace(_) => (),
+            Lexeme::Text(_) => {
+                if let Some(kind) = token.highlight_kind() {
+                    let delta_line = line - previous_line;
+                    let delta_start = if delta_line == 0 {
+                        character - previous_character
+                    } else {
+                        character
+                    };
+                    data.extend([
+                        delta_line,
+                        delta_start,
+                        length,
+                        semantic_token_type(kind),
+                        0,
+                    ]);
+                    (previous_line, previous_character) = (line, character);
+                }
+            }
+        }
+        character += length;
+    }
+    data
+}
+
+/// The range of a text token in the style of a tree-sitter node.
+/// Points are zero-indexed `(row, column)` pairs, where the column is a byte offset
+/// within the row, and the end point and end byte are exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeRange {
+    /// The kind of the node: the highlight kind's class name, or `text` if the token
+    /// is not highlighted.
+    pub kind: &'static str,
+    /// The point of the first byte of the node.
+    pub start_point: (usize, usize),
+    /// The point after the last byte of the node.
+    pub end_point: (usize, usize),
+    /// The offset of the first byte of the node within the file.
+    pub start_byte: usize,
+    /// The offset after the last byte of the node within the file.
+    pub end_byte: usize,
+}
+
+/// Returns the ranges of the text tokens of `file` as tree-sitter style nodes.
+pub fn node_ranges(file: &AnnotatedFile) -> Vec<NodeRange> {
+    let mut nodes = vec![];
+    // The byte offset of the start of the current line.
+    let mut line_start_byte = 0;
+    for token in file.tokens() {
+        let info = token.token().get_info();
+        let range = info.byte_range();
+        match token.token() {
+            Lexeme::LineBreak(_) => line_start_byte = range.end,
+            Lexeme::Whitespace(_) => (),
+            Lexeme::Text(_) => {
+                let row = info.line_number() - 1;
+                nodes.push(NodeRange {
+                    kind: token
+                        .highlight_kind()
+                        .map_or("text", HighlightKind::class_name),
+                    start_point: (row, range.start - line_start_byte),
+                    end_point: (row, range.end - line_start_byte),
+                    start_byte: range.start,
+                    end_byte: range.end,
+                });
+            }
+        }
+    }
+    nodes
+}
+
+/// A text or whitespace token of a line, as exported by [`annotations_by_line`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct TokenAnnotation {
+    /// The characters of the token.
+    pub text: String,
+    /// The kind of the token: the highlight kind's class name, `text` if the token is
+    /// not highlighted, or `whitespace`.
+    pub kind: &'static str,
+    /// The 1-indexed column of the first character of the token.
+    pub start_column: usize,
+    /// The 1-indexed column of the final character of the token.
+    pub end_column: usize,
+    /// The offset of the first byte of the token within the file.
+    pub start_byte: usize,
+    /// The offset after the last byte of the token within the file.
+    pub end_byte: usize,
+}
+
+/// The tokens of a single line, as exported by [`annotations_by_line`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct LineAnnotations {
+    /// The 1-indexed number of the line.
+    pub line: usize,
+    /// The text and whitespace tokens of the line, in order, excluding its line break.
+    pub tokens: Vec<TokenAnnotation>,
+}
+
+/// Returns the tokens of `file` grouped by line, in order, so that a viewer rendering
+/// only its visible rows may render a line without scanning the whole file.
+/// Every line has an entry, including empty lines, grouped as by
+/// [`LexemeFile::lines`](crate::lexer::LexemeFile::lines). Concatenating the text of a
+/// line's tokens yields the line without its line break.
+pub fn annotations_by_line(file: &AnnotatedFile) -> Vec<LineAnnotations> {
+    file.tokens()
+        .split_inclusive(|token| matches!(token.token(), Lexeme::LineBreak(_)))
+        .enumerate()
+        .map(|(i, line)| LineAnnotations {
+            line: i + 1,
+            tokens: line
+                .iter()
+                .filter_map(|token| {
+                    let kind = match token.token() {
+                        Lexeme::LineBreak(_) => return None,
+                        Lexeme::Whitespace(_) => "whitespace",
+                        Lexeme::Text(_) => token
+                            .highlight_kind()
+                            .map_or("text", HighlightKind::class_name),
+                    };
+                    let info = token.token().get_info();
+                    let range = info.byte_range();
+                    Some(TokenAnnotation {
+                        text: String::from(info.characters()),
+                        kind,
+                        start_column: info.start_column(),
+                        end_column: info.end_column(),
+                        start_byte: range.start,
+                        end_byte: range.end,
+                    })
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Returns the text of the comment regions of `file`, in order, for publishing a map's
+/// inline documentation as plain text or Markdown.
+///
+/// The outer delimiters of each region are removed, along with the indentation and
+/// `*` decoration at the start of each line, and leading and trailing blank lines.
+/// Delimiters of nested comments are kept. Regions are separated by a blank line.
+pub fn comment_text(file: &AnnotatedFile) -> String {
+    let mut paragraphs = vec![];
+    for region in file.comment_regions() {
+        let text: String = file.tokens()[region.token_range()]
+            .iter()
+            .map(|t| t.token().get_info().characters())
+            .collect();
+        let text = text.strip_prefix("/*").unwrap_or(&text);
+        let text = match region.is_terminated() {
+            true => text.strip_suffix("*/").unwrap_or(text),
+            false => text,
+        };
+        let lines: Vec<&str> = text
+            .lines()
+            .map(|line| {
+                let line = line.trim();
+                match line.strip_prefix('*') {
+                    Some(rest) if !rest.starts_with('/') => rest.trim_start(),
+                    _ => line,
+                }
+            })
+            .skip_while(|line| line.is_empty())
+            .collect();
+        let end = lines
+            .iter()
+            .rposition(|line| !line.is_empty())
+            .map_or(0, |i| i + 1);
+        if end > 0 {
+            paragraphs.push(lines[..end].join("\n"));
+        }
+    }
+    paragraphs.join("\n\n")
+}
+
+/// Returns a dump of every lexeme of `file`, one per line, for debugging the lexer and
+/// annotater.
+///
+/// Each line has the tab-separated fields `{line}:{start_column}-{end_column}`, the
+/// lexeme kind (`line-break`, `whitespace`, or `text`), the highlight kind of the
+/// token or `-` if it has none, `comment-{id}` for a comment delimiter or `-` otherwise,
+/// `line-start` if the token starts its line or `-` otherwise, and the quoted characters
+/// of the lexeme.
+/// Control and other invisible characters are escaped, so each lexeme occupies exactly
+/// one line of the dump.
+/// The dump is deterministic, so it may be compared against saved snapshots.
+pub fn token_dump(file: &AnnotatedFile) -> String {
+    let mut dump = String::new();
+    for token in file.tokens() {
+        let info = token.token().get_info();
+        let kind = match token.token() {
+            Lexeme::LineBreak(_) => "line-break",
+            Lexeme::Whitespace(_) => "whitespace",
+            Lexeme::Text(_) => "text",
+        };
+        let highlight = token
+            .highlight_kind()
+            .map_or("-", HighlightKind::class_name);
+        let comment_id = match token.annotation().and_then(|a| a.comment_id()) {
+            Some(id) => format!("comment-{id}"),
+            None => String::from("-"),
+        };
+        let line_start = if token.is_line_start() {
+            "line-start"
+        } else {
+            "-"
+        };
+        // Writing to a string cannot fail.
+        let _ = writeln!(
+            dump,
+            "{}:{}-{}\t{kind}\t{highlight}\t{comment_id}\t{line_start}\t\"{}\"",
+            info.line_number(),
+            info.start_column(),
+            info.end_column(),
+            info.characters().escape_debug()
+        );
+    }
+    dump
+}
+
+/// A diagnostic together with the path of its file, as written to a JSON record.
+#[derive(Serialize)]
+struct DiagnosticRecord<'a> {
+    path: &'a str,
+    line: usize,
+    start_column: usize,
+    end_column: usize,
+    severity: Severity,
+    code: &'a str,
+    message: &'a str,
+}
+
+impl<'a> DiagnosticRecord<'a> {
+    /// Returns the record of `diagnostic` of the file at `path`.
+    fn new(path: &'a str, diagnostic: &'a Diagnostic) -> Self {
+        Self {
+            path,
+            line: diagnostic.line_number(),
+            start_column: diagnostic.start_column(),
+            end_column: diagnostic.end_column(),
+            severity: diagnostic.severity(),
+            code: diagnostic.code(),
+            message: diagnostic.message(),
+        }
+    }
+}
+
+/// Writes the `diagnostics` of the file at `path` to `writer` as JSON lines, then
+/// flushes `writer`.
+///
+/// Each diagnostic is written as a single line containing a JSON object with the fields
+/// `path`, `line`, `start_column`, `end_column`, `severity`, `code`, and `message`.
+/// As every line is a complete record, the diagnostics of a large batch of files may be
+/// written as each file is checked, rather than collected into one document.
+pub fn write_diagnostics_jsonl(
+    writer: &mut impl io::Write,
+    path: &str,
+    diagnostics: &[Diagnostic],
+) -> io::Result<()> {
+    for diagnostic in diagnostics {
+        serde_json::to_writer(&mut *writer, &DiagnosticRecord::new(path, diagnostic))?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+/// Returns the `diagnostics` of the file at `path` as a JSON array of objects with the
+/// same fields as the records of [`write_diagnostics_jsonl`].
+pub fn diagnostics_json(path: &str, diagnostics: &[Diagnostic]) -> String {
+    let records: Vec<DiagnosticRecord> = diagnostics
+        .iter()
+        .map(|diagnostic| DiagnosticRecord::new(path, diagnostic))
+        .collect();
+    serde_json::to_string(&records).expect("Diagnostics must serialize to JSON.")
+}
+
+/// Escapes `s` for the message of a GitHub Actions workflow command.
+fn escape_workflow_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes `s` for a property value of a GitHub Actions workflow command.
+fn escape_workflow_property(s: &str) -> String {
+    escape_workflow_data(s)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+/// Returns `diagnostic` of the file at `path` as a GitHub Actions workflow command, which
+/// shows the diagnostic inline on the file when printed by a workflow.
+///
+/// Errors, warnings, and info map to the `error`, `warning`, and `notice` commands.
+/// `path` should be relative to the root of the repository. The line and columns are
+/// 1-indexed, and the code of the diagnostic is its title.
+pub fn github_annotation(path: &str, diagnostic: &Diagnostic) -> String {
+    let command = match diagnostic.severity() {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "notice",
+    };
+    format!(
+        "::{command} file={},line={},col={},endColumn={},title={}::{}",
+        escape_workflow_property(path),
+        diagnostic.line_number(),
+        diagnostic.start_column(),
+        diagnostic.end_column(),
+        escape_workflow_property(diagnostic.code()),
+        escape_workflow_data(diagnostic.message()),
+    )
+}
+
+/// Returns the `diagnostics` of each file as a SARIF 2.1.0 log, the format ingested by
+/// code scanning tools such as GitHub code scanning. `files` pairs the path of each file,
+/// which is written as its uri, with its diagnostics.
+///
+/// The log has a single run, in which each file is an artifact, each code of the
+/// diagnostics is a rule, described by its explanation if it has one, and each
+/// diagnostic is a result. Errors, warnings, and info map to the `error`, `warning`,
+/// and `note` levels. Columns count unicode code points, and the end column of a region
+/// is exclusive.
+pub fn diagnostics_sarif(files: &[(&str, &[Diagnostic])]) -> String {
+    let mut codes: Vec<&str> = vec![];
+    for diagnostic in files.iter().flat_map(|(_, diagnostics)| diagnostics.iter()) {
+        if !codes.contains(&diagnostic.code()) {
+            codes.push(diagnostic.code());
+        }
+    }
+    let rules: Vec<serde_json::Value> = codes
+        .iter()
+        .map(|&code| match explain::explanation(code) {
+            Some(explanation) => json!({
+                "id": code,
+                "properties": { "id": explanation.id() },
+                "fullDescription": { "text": explanation.explanation() },
+            }),
+            None => json!({ "id": code }),
+        })
+        .collect();
+    let artifacts: Vec<serde_json::Value> = files
+        .iter()
+        .map(|(path, _)| json!({ "location": { "uri": path } }))
+        .collect();
+    let mut results = vec![];
+    for (index, (path, diagnostics)) in files.iter().enumerate() {
+        for diagnostic in diagnostics.iter() {
+            let level = match diagnostic.severity() {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Info => "note",
+            };
+            let span = diagnostic.span();
+            results.push(json!({
+                "ruleId": diagnostic.code(),
+                "ruleIndex": codes.iter().position(|&code| code == diagnostic.code()),
+                "level": level,
+                "message": { "text": diagnostic.message() },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": path, "index": index },
+                        "region": {
+                            "startLine": span.start_line(),
+                            "startColumn": span.start_column(),
+                            "endLine": span.end_line(),
+                            "endColumn": span.end_column() + 1,
+                        },
+                    },
+                }],
+            }));
+        }
+    }
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "aoe2-rms",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "columnKind": "unicodeCodePoints",
+            "artifacts": artifacts,
+            "results": results,
+        }],
+    });
+    serde_json::to_string_pretty(&log).expect("A SARIF log must serialize to JSON.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+
+    /// Tests extracting the text of several comments, including a decorated multi-line one.
+    #[test]
+    fn comments_only() {
+        let source = "/* Map by someone. */\n/*\n * Lands:\n *   - one\n *\n * See /* x */ .\n */\ncreate_land { } /* trailing */\n/* */ /* open";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        assert_eq!(
+            comment_text(&file),
+            "Map by someone.\n\nLands:\n- one\n\nSee /* x */ .\n\ntrailing\n\nopen"
+        );
+    }
+
+    /// Tests that each lexeme is dumped on its own line with control characters escaped.
+    #[test]
+    fn dump() {
+        let file = AnnotatedFile::annotate(&lexer::lex_str("/* x */\t\u{7}\r\n"));
+        let expected = concat!(
+            "1:1-2\ttext\tcomment\tcomment-0\t-\t\"/*\"\n",
+            "1:3-3\twhitespace\t-\t-\t-\t\" \"\n",
+            "1:4-4\ttext\tcomment\t-\t-\t\"x\"\n",
+            "1:5-5\twhitespace\t-\t-\t-\t\" \"\n",
+            "1:6-7\ttext\tcomment\tcomment-0\t-\t\"*/\"\n",
+            "1:8-8\twhitespace\t-\t-\t-\t\"\\t\"\n",
+            "1:9-9\ttext\t-\t-\tline-start\t\"\\u{7}\"\n",
+            "1:10-11\tline-break\t-\t-\t-\t\"\\r\\n\"\n",
+        );
+        assert_eq!(token_dump(&file), expected);
+    }
+
+    /// Tests that each diagnostic is written as an independent line of JSON.
+    #[test]
+    fn diagnostics_jsonl() {
+        let source = "if UNKNOWN \"x\"\nendif\nassign_to AT_NOTHING 0 0 0";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let diagnostics = crate::checker::check(&file, &Default::default());
+        let mut output = vec![];
+        write_diagnostics_jsonl(&mut output, "maps/a \"b\".rms", &diagnostics).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), diagnostics.len());
+        for (line, diagnostic) in output.lines().zip(&diagnostics) {
+            let record: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(record["path"], "maps/a \"b\".rms");
+            assert_eq!(record["line"], diagnostic.line_number());
+            assert_eq!(record["severity"], diagnostic.severity().to_string());
+            assert_eq!(record["code"], diagnostic.code());
+            assert_eq!(record["message"], diagnostic.message());
+        }
+    }
+
+    /// Tests the workflow commands of an error and a warning.
+    #[test]
+    fn github_annotations() {
+        let source = "<PLAYER_SETUP>\nif UNKNOWN\nendif\nassign_to AT_NOTHING 0 0 0";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let diagnostics = crate::checker::check(&file, &Default::default());
+        let annotations: Vec<String> = diagnostics
+            .iter()
+            .map(|d| github_annotation("maps/a,b.rms", d))
+            .collect();
+        assert_eq!(
+            annotations,
+            vec![
+                "::warning file=maps/a%2Cb.rms,line=2,col=4,endColumn=10,title=unknown-label::Unknown label `UNKNOWN`.",
+                "::error file=maps/a%2Cb.rms,line=4,col=11,endColumn=20,title=invalid-assign-type::`AT_NOTHING` is not an assign type, expected one of `AT_PLAYER`, `AT_COLOR`, `AT_TEAM`.",
+            ]
+        );
+        assert_eq!(escape_workflow_data("50%\r\n"), "50%25%0D%0A");
+    }
+
+    /// Tests that a SARIF log has the required fields and that its results refer to its
+    /// rules and artifacts.
+    #[test]
+    fn sarif() {
+        let check = |source| {
+            let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+            crate::checker::check(&file, &Default::default())
+        };
+        let (a, b) = (
+            check("<PLAYER_SETUP>\nif UNKNOWN\nendif"),
+            check("assign_to AT_NOTHING 0 0 0"),
+        );
+        let files = [("maps/a.rms", &a[..]), ("maps/b.rms", &b[..])];
+        let log: serde_json::Value = serde_json::from_str(&diagnostics_sarif(&files)).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        let runs = log["runs"].as_array().unwrap();
+        assert_eq!(runs.len(), 1);
+        let run = &runs[0];
+        assert_eq!(run["tool"]["driver"]["name"], "aoe2-rms");
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(run["artifacts"][1]["location"]["uri"], "maps/b.rms");
+        let results = run["results"].as_array().unwrap();
+        let num_diagnostics: usize = files.iter().map(|(_, d)| d.len()).sum();
+        assert_eq!(results.len(), num_diagnostics);
+        for result in results {
+            assert!(result["message"]["text"].is_string());
+            let rule = &rules[result["ruleIndex"].as_u64().unwrap() as usize];
+            assert_eq!(rule["id"], result["ruleId"]);
+            assert!(rule["fullDescription"]["text"].is_string());
+        }
+        let first = &results[0];
+        assert_eq!(first["ruleId"], "unknown-label");
+        assert_eq!(first["level"], "warning");
+        let location = &first["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "maps/a.rms");
+        assert_eq!(location["artifactLocation"]["index"], 0);
+        assert_eq!(
+            location["region"],
+            json!({ "startLine": 2, "startColumn": 4, "endLine": 2, "endColumn": 11 })
+        );
+        let last = results.last().unwrap();
+        assert_eq!(last["level"], "error");
+        assert_eq!(
+            last["locations"][0]["physicalLocation"]["artifactLocation"]["index"],
+            1
+        );
+    }
+
+    /// Tests that there is an entry for each line of the source, whose tokens rebuild the
+    /// line.
+    #[test]
+    fn lines_of_annotations() {
+        for source in ["", "a", "a\n", "/* a\n\n b */ rnd(1,2)\r\n\t\nx y", "\n\n"] {
+            let lines = annotations_by_line(&AnnotatedFile::annotate(&lexer::lex_str(source)));
+            assert_eq!(lines.len(), source.lines().count(), "{source:?}");
+            for (line, text) in lines.iter().zip(source.lines()) {
+                let tokens: String = line.tokens.iter().map(|t| &t.text[..]).collect();
+                assert_eq!(tokens, text);
+            }
+        }
+        let lines = annotations_by_line(&AnnotatedFile::annotate(&lexer::lex_str("a\n  rnd(1,2)")));
+        assert_eq!(lines[1].line, 2);
+        assert_eq!(
+            lines[1].tokens[1],
+            TokenAnnotation {
+                text: String::from("rnd(1,2)"),
+                kind: "function",
+                start_column: 3,
+                end_column: 10,
+                start_byte: 4,
+                end_byte: 12,
+            }
+        );
+        assert_eq!(lines[1].tokens[0].kind, "whitespace");
+    }
+
+    /// Tests the points and byte offsets of the nodes of a small file.
+    #[test]
+    fn node_points() {
+        let file = AnnotatedFile::annotate(&lexer::lex_str("é x\r\n  /* 語 */"));
+        let node = |kind, start_point, end_point, start_byte, end_byte| NodeRange {
+            kind,
+            start_point,
+            end_point,
+            start_byte,
+            end_byte,
+        };
+        assert_eq!(
+            node_ranges(&file),
+            vec![
+                node("text", (0, 0), (0, 2), 0, 2),
+                node("text", (0, 3), (0, 4), 3, 4),
+                node("comment", (1, 2), (1, 4), 8, 10),
+                node("comment", (1, 5), (1, 8), 11, 14),
+                node("comment", (1, 9), (1, 11), 15, 17),
+            ]
+        );
+    }
+
+    /// Tests the delta encoding of the highlighted tokens of a small file.
+    #[test]
+    fn delta_encoding() {
+        let source = "/* 🗺 */ a\n\n  b rnd(1,4) rnd ( 1 , 4 )";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        #[rustfmt::skip]
+        let expected = vec![
+            0, 0, 2, 0, 0, // `/*`
+            0, 3, 2, 0, 0, // `🗺`, two UTF-16 code units
+            0, 3, 2, 0, 0, // `*/`
+            2, 4, 8, 1, 0, // `rnd(1,4)`
+            0, 9, 3, 1, 0, // `rnd`
+            0, 4, 1, 1, 0, // `(`
+            0, 2, 1, 2, 0, // `1`
+            0, 2, 1, 1, 0, // `,`
+            0, 2, 1, 2, 0, // `4`
+            0, 2, 1, 1, 0, // `)`
+        ];
+        assert_eq!(semantic_tokens(&file), expected);
+    }
+}
@@ -0,0 +1,271 @@
+//! Formatting passes that tidy the layout of a map script without changing its meaning.
+
+use crate::{
+    annotater::{AnnotatedFile, AnnotatedToken},
+    lexer::{self, Lexeme},
+};
+
+/// Options selecting which formatting passes are run by [`format`].
+/// Every pass is opt-in, so formatting with the default options returns the source
+/// unchanged.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct FormatOptions {
+    /// Whether to sort each contiguous run of `#const` definitions by name.
+    pub sort_consts: bool,
+    /// Whether to align the values of each contiguous run of `#const` definitions into
+    /// a column.
+    pub align_consts: bool,
+    /// Whether to remove the whitespace at the end of each line, which also empties the
+    /// lines consisting only of whitespace.
+    pub trim_trailing_whitespace: bool,
+}
+
+/// A physical line of a file, split into its content and its line break.
+struct Line<'a> {
+    /// The tokens of the line, excluding the line break.
+    tokens: &'a [AnnotatedToken],
+    /// The line break ending the line, empty for a final line without one.
+    line_break: &'a str,
+}
+
+impl Line<'_> {
+    /// Returns the text of the line, excluding the line break.
+    fn text(&self) -> String {
+        self.tokens
+            .iter()
+            .map(|token| token.token().get_info().characters())
+            .collect()
+    }
+
+    /// Returns `true` if the line has text and all of its text is within comments.
+    fn is_comment_only(&self) -> bool {
+        let mut texts = self
+            .tokens
+            .iter()
+            .filter(|token| matches!(token.token(), Lexeme::Text(_)))
+            .peekable();
+        texts.peek().is_some() && texts.all(AnnotatedToken::is_comment)
+    }
+}
+
+/// A `#const` definition parsed from a line.
+struct ConstLine<'a> {
+    /// The original text of the line, excluding the line break.
+    text: String,
+    /// The whitespace preceding `#const`.
+    indentation: &'a str,
+    /// The name of the constant.
+    name: &'a str,
+    /// The value of the constant.
+    value: &'a str,
+    /// The text following the value, such as a trailing comment, including its
+    /// leading whitespace.
+    trailing: String,
+}
+
+/// Parses `line` as a `#const` definition of a name and a value, optionally followed by
+/// comments that end on the same line.
+/// Returns `None` if the line is not such a definition.
+fn parse_const<'a>(line: &Line<'a>) -> Option<ConstLine<'a>> {
+    let characters = |i: usize| line.tokens[i].token().get_info().characters();
+    let texts: Vec<usize> = (0..line.tokens.len())
+        .filter(|&i| matches!(line.tokens[i].token(), Lexeme::Text(_)))
+        .collect();
+    let [directive, name, value, ref comments @ ..] = texts[..] else {
+        return None;
+    };
+    if characters(directive) != "#const"
+        || [directive, name, value]
+            .iter()
+            .any(|&i| line.tokens[i].is_comment())
+        || !comments.iter().all(|&i| line.tokens[i].is_comment())
+    {
+        return None;
+    }
+    let depth = comments
+        .iter()
+        .fold(0isize, |depth, &i| match characters(i) {
+            "/*" => depth + 1,
+            "*/" => depth - 1,
+            _ => depth,
+        });
+    if depth != 0 {
+        return None;
+    }
+    Some(ConstLine {
+        text: line.text(),
+        indentation: if directive == 0 { "" } else { characters(0) },
+        name: characters(name),
+        value: characters(value),
+        trailing: (value + 1..line.tokens.len()).map(characters).collect(),
+    })
+}
+
+/// A `#const` definition together with the comment lines directly above it.
+struct ConstEntry<'a> {
+    /// The comment-only lines preceding the definition.
+    comments: Vec<String>,
+    /// The definition.
+    definition: ConstLine<'a>,
+}
+
+/// Returns the lines of `entries`, sorted by name if `options` sort constants and with
+/// their values aligned if `options` align constants.
+fn format_consts(mut entries: Vec<ConstEntry<'_>>, options: &FormatOptions) -> Vec<String> {
+    if options.sort_consts {
+        entries.sort_by(|a, b| a.definition.name.cmp(b.definition.name));
+    }
+    let width = entries
+        .iter()
+        .map(|entry| entry.definition.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut lines = vec![];
+    for entry in entries {
+        lines.extend(entry.comments);
+        let ConstLine {
+            text,
+            indentation,
+            name,
+            value,
+            trailing,
+        } = entry.definition;
+        lines.push(if options.align_consts {
+            format!("{indentation}#const {name:width$} {value}{trailing}")
+        } else {
+            text
+        });
+    }
+    lines
+}
+
+/// Formats `file` with the passes selected by `options`, returning the formatted source.
+///
+/// When sorting or aligning `#const` definitions, only contiguous runs of definitions are
+/// rewritten, so definitions are never moved across other lines, including blank lines.
+/// The comment-only lines directly above a definition move together with it, and a
+/// comment trailing a definition on its line is kept. Line breaks keep their positions.
+pub fn format(file: &AnnotatedFile, options: &FormatOptions) -> String {
+    let lines: Vec<Line> = file
+        .tokens()
+        .split_inclusive(|token| matches!(token.token(), Lexeme::LineBreak(_)))
+        .map(|tokens| match tokens.split_last() {
+            Some((last, rest)) if matches!(last.token(), Lexeme::LineBreak(_)) => Line {
+                tokens: rest,
+                line_break: last.token().get_info().characters(),
+            },
+            _ => Line {
+                tokens,
+                line_break: "",
+            },
+        })
+        .collect();
+    let rewrite_consts = options.sort_consts || options.align_consts;
+    let trim = |text: &str| -> String {
+        if options.trim_trailing_whitespace {
+            String::from(text.trim_end_matches(lexer::is_whitespace))
+        } else {
+            String::from(text)
+        }
+    };
+
+    let mut formatted = String::new();
+    let mut i = 0;
+    while i < lines.len() {
+        // Collects the run of definitions and their comments starting at line `i`.
+        let mut entries = vec![];
+        let mut end = i;
+        let mut comments = vec![];
+        let mut j = i;
+        while rewrite_consts && j < lines.len() {
+            if lines[j].is_comment_only() {
+                comments.push(lines[j].text());
+            } else if let Some(definition) = parse_const(&lines[j]) {
+                entries.push(ConstEntry {
+                    comments: std::mem::take(&mut comments),
+                    definition,
+                });
+                end = j + 1;
+            } else {
+                break;
+            }
+            j += 1;
+        }
+        if entries.is_empty() {
+            formatted.push_str(&trim(&lines[i].text()));
+            formatted.push_str(lines[i].line_break);
+            i += 1;
+            continue;
+        }
+        for (text, line) in format_consts(entries, options).iter().zip(&lines[i..end]) {
+            formatted.push_str(&trim(text));
+            formatted.push_str(line.line_break);
+        }
+        i = end;
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+
+    /// Returns `source` formatted with `options`.
+    fn format_str(source: &str, options: &FormatOptions) -> String {
+        format(&AnnotatedFile::annotate(&lexer::lex_str(source)), options)
+    }
+
+    /// Tests sorting and aligning a block of definitions with attached comments.
+    #[test]
+    fn sort_and_align_consts() {
+        let source = "#const ZEBRA 3 /* last */\r\n/* The fort. */\n#const  FORT 1\n#const BOAT 12\n\n#const ANT 4\n";
+        let options = FormatOptions {
+            sort_consts: true,
+            align_consts: true,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_str(source, &options),
+            "#const BOAT  12\r\n/* The fort. */\n#const FORT  1\n#const ZEBRA 3 /* last */\n\n#const ANT 4\n"
+        );
+        let options = FormatOptions {
+            sort_consts: true,
+            align_consts: false,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_str(source, &options),
+            "#const BOAT 12\r\n/* The fort. */\n#const  FORT 1\n#const ZEBRA 3 /* last */\n\n#const ANT 4\n"
+        );
+    }
+
+    /// Tests that the default options and other lines leave the source unchanged.
+    #[test]
+    fn unchanged() {
+        let source = "/* x */\n  #const B 2 /* open\n */ #const A 1\ncreate_land { }";
+        assert_eq!(format_str(source, &FormatOptions::default()), source);
+        let options = FormatOptions {
+            sort_consts: true,
+            align_consts: true,
+            ..FormatOptions::default()
+        };
+        assert_eq!(format_str(source, &options), source);
+    }
+
+    /// Tests that trailing whitespace is removed, emptying whitespace-only lines.
+    #[test]
+    fn trim_trailing_whitespace() {
+        let source = "#const A 1 \t\n  \r\n/* x */  \n\t base_size 7\n \t";
+        let options = FormatOptions {
+            trim_trailing_whitespace: true,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_str(source, &options),
+            "#const A 1\n\r\n/* x */\n\t base_size 7\n"
+        );
+        let file = lexer::lex_str(&format_str(source, &options));
+        assert!(file.whitespace_only_lines().is_empty());
+    }
+}
@@ -1,10 +1,18 @@
 //! Tools for writing a parsed RMS file to a debugging HTML file.
 
-use std::{fs::File, io::Write, path::Path};
+use std::{
+    borrow::Cow,
+    collections::BTreeSet,
+    fs::File,
+    io::Write,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use crate::{
-    annotater::{AnnotatedFile, AnnotatedToken},
-    lexer::{Lexeme, LexemeFile},
+    annotater::{AnnotatedFile, AnnotatedToken, HighlightKind},
+    lexer::{Lexeme, LexemeFile, LexemeInfo},
+    rms_data,
 };
 
 /// The `<head>` section of the html file.
@@ -15,15 +23,36 @@ const HTML_HEAD: &str = r#"  <head>
     <title>Code</title>
   </head>"#;
 
+/// The stylesheet of the rendered html, embedded for self-contained documents.
+pub const STYLESHEET: &str = include_str!("../style/style.css");
+
 /// Replaces characters in `s` so that they show up in html.
+/// Borrows `s` without allocating if nothing is replaced, as for most tokens.
 ///
 /// Performs the following replacements:
 ///
-/// - `<` to `&le;`
-/// - `>` to `&ge;`
-fn transform_text_to_html(s: &str) -> String {
+/// - `<` to `&lt;`
+/// - `>` to `&gt;`
+fn transform_text_to_html(s: &str) -> Cow<'_, str> {
     // TODO more replacements
-    s.replace('<', "&lt;").replace('>', "&gt;")
+    if !s.contains(['<', '>']) {
+        return Cow::Borrowed(s);
+    }
+    Cow::Owned(s.replace('<', "&lt;").replace('>', "&gt;"))
+}
+
+/// Escapes `s` for use as the value of a double-quoted html attribute.
+/// Borrows `s` without allocating if nothing is escaped.
+fn escape_attribute(s: &str) -> Cow<'_, str> {
+    if !s.contains(['&', '<', '>', '"']) {
+        return Cow::Borrowed(s);
+    }
+    Cow::Owned(
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;"),
+    )
 }
 
 /// Writes a debug file using just the lexemes, without tokenization or annotation.
@@ -47,7 +76,7 @@ pub fn write_debug_file(lexemes: &LexemeFile, output: &Path) -> std::io::Result<
         }
         match token {
             Lexeme::LineBreak(_token_info) => {
-                write!(f, "</code></pre>\n")?;
+                writeln!(f, "</code></pre>")?;
                 writeln!(f, "      </li>")?;
                 line_in_progress = false;
             }
@@ -63,6 +92,7 @@ pub fn write_debug_file(lexemes: &LexemeFile, output: &Path) -> std::io::Result<
                 } else {
                     format!("{start}&ndash;{end}")
                 };
+
                 let card = format!("<div>{range_display}</div>",);
                 write!(
                     f,
@@ -74,7 +104,7 @@ pub fn write_debug_file(lexemes: &LexemeFile, output: &Path) -> std::io::Result<
     }
     // Ends the final line in case the file does not end with a newline character.
     if line_in_progress {
-        write!(f, "</code></pre>\n")?;
+        writeln!(f, "</code></pre>")?;
         writeln!(f, "      </li>")?;
         // line_in_progress = false;  // Assignment would be unused.
     }
@@ -87,88 +117,911 @@ pub fn write_debug_file(lexemes: &LexemeFile, output: &Path) -> std::io::Result<
 
 // TODO tokenized debug file (step before annotation)
 
-/// TODO
-fn annotation_card(token: &AnnotatedToken) -> Option<String> {
-    match token.token() {
-        Lexeme::Text(token_info) => {
-            let html = transform_text_to_html(token_info.characters());
-            let highlight = if let Some(annotation) = token.annotation() {
-                if let Some(highlight) = annotation.highlight() {
-                    format!(" {highlight}")
-                } else {
-                    String::new()
-                }
-            } else {
-                String::new()
-            };
-            let comment_id = if let Some(annotation) = token.annotation() {
-                if let Some(comment_id) = annotation.comment_id() {
-                    format!(" comment-{comment_id}")
-                } else {
-                    String::new()
-                }
-            } else {
-                String::new()
-            };
+/// A built-in palette of highlight colors, whose rules follow the stylesheet and override
+/// its colors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HighlightTheme {
+    /// The dark colors of the bundled stylesheet.
+    #[default]
+    Default,
+    /// Colors approximating the palette of the in-game editor and the common Notepad++
+    /// RMS highlighting, dark text on a white background.
+    GameEditor,
+}
 
-            let start = token_info.start_column();
-            let end = token_info.end_column();
-            let range_display = if start == end {
-                format!("{start}")
-            } else {
-                format!("{start}&ndash;{end}")
-            };
+impl HighlightTheme {
+    /// Every built-in theme.
+    pub const ALL: [HighlightTheme; 2] = [HighlightTheme::Default, HighlightTheme::GameEditor];
+
+    /// Returns the name of this theme, as accepted by [`HighlightTheme::from_name`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::GameEditor => "game-editor",
+        }
+    }
+
+    /// Returns the theme named `name`, such as `game-editor`, or `None` if there is no
+    /// such theme.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|theme| theme.name() == name)
+    }
+
+    /// Returns the css rules of this theme, preceded by a blank line, to be appended to
+    /// the stylesheet. The default theme has no rules, so returns an empty string.
+    pub fn css(self) -> String {
+        let (background, text, colors) = match self {
+            Self::Default => return String::new(),
+            Self::GameEditor => (
+                "#ffffff",
+                "#000000",
+                [
+                    (HighlightKind::Comment, "#008000"),
+                    (HighlightKind::Function, "#0000ff"),
+                    (HighlightKind::Number, "#ff8000"),
+                    (HighlightKind::Constant, "#8000ff"),
+                    (HighlightKind::Resource, "#800080"),
+                    (HighlightKind::Section, "#804000"),
+                    (HighlightKind::Command, "#0000a0"),
+                    (HighlightKind::Directive, "#808080"),
+                ],
+            ),
+        };
+        let mut css =
+            format!("\nbody {{\n  background-color: {background};\n  color: {text};\n}}\n");
+        for (kind, color) in colors {
+            css.push_str(&format!(
+                "\n.{} {{\n  color: {color};\n}}\n",
+                kind.class_name()
+            ));
+        }
+        css
+    }
+}
+
+/// Options for writing an annotated debug file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HtmlOptions {
+    /// Whether to give each text span an id and embed a JSON source map from the ids
+    /// to the byte ranges of the spans in the source file.
+    pub source_map: bool,
+    /// If present, lines wrap after this many columns, and each card additionally shows
+    /// the 1-indexed visual row of its line on which the token starts.
+    pub wrap_columns: Option<usize>,
+    /// Whether to add accessibility attributes, labeling each text span with its
+    /// position and making its card focusable by keyboard.
+    pub accessible: bool,
+    /// If present, only this many lines are written, followed by a row stating how many
+    /// lines were omitted.
+    pub max_lines: Option<usize>,
+    /// Whether to mark each tab and space with a visible glyph.
+    /// The glyphs are drawn by css, so copying the text still yields the whitespace.
+    pub show_whitespace: bool,
+    /// Whether to show the information of each text span as a `title` tooltip instead of
+    /// a nested card, which greatly reduces the size of the document for large files.
+    pub compact_cards: bool,
+    /// Whether to mark each `/*` or `*/` within a comment other than the comment's
+    /// outermost delimiters, such as the delimiters of a nested comment or text glued
+    /// to a delimiter, so that authors see where the comment actually opens and closes.
+    pub mark_interior_delimiters: bool,
+    /// The number of the first written line, such as the line of the original file at
+    /// which a rendered snippet starts. Both the numbering of the list and the `L{n}`
+    /// anchors of the lines start at this number.
+    pub start_line: usize,
+    /// If present, the path of the source file, which is recorded together with the time
+    /// of generation and the version of this crate in a comment at the top of a
+    /// single-file document. Absent by default, so that the output is deterministic.
+    pub provenance: Option<String>,
+    /// The highlight colors of a single-file document with its stylesheet inlined.
+    /// A document linking to a stylesheet uses the colors of that stylesheet, to which
+    /// [`HighlightTheme::css`] may be appended.
+    pub highlight_theme: HighlightTheme,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self {
+            source_map: false,
+            wrap_columns: None,
+            accessible: false,
+            max_lines: None,
+            show_whitespace: false,
+            compact_cards: false,
+            mark_interior_delimiters: false,
+            start_line: 1,
+            provenance: None,
+            highlight_theme: HighlightTheme::Default,
+        }
+    }
+}
+
+/// Returns `time` as a UTC timestamp in the RFC 3339 format, such as
+/// `2023-11-14T22:13:20Z`. Times before the Unix epoch are written as the epoch.
+fn utc_timestamp(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, seconds) = ((seconds / 86_400) as i64, seconds % 86_400);
+    // Converts the days since the epoch to a civil date, counting in eras of 400 years
+    // whose years start in March, so that a leap day is the last day of its year.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
+/// Writes the provenance comment of `options` to `f`, if it has a source path.
+fn write_provenance(f: &mut impl Write, options: &HtmlOptions) -> std::io::Result<()> {
+    if let Some(source) = &options.provenance {
+        // A comment may not contain `--`.
+        writeln!(
+            f,
+            "<!-- Generated from `{}` at {} by aoe2-rms {}. -->",
+            source.replace("--", "- -"),
+            utc_timestamp(SystemTime::now()),
+            env!("CARGO_PKG_VERSION")
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the html of the whitespace `s`, wrapping each tab and space in a span that
+/// the stylesheet marks with a visible glyph.
+fn visible_whitespace(s: &str) -> String {
+    let mut html = String::new();
+    for c in s.chars() {
+        match c {
+            '\t' => html.push_str("<span class=\"ws-tab\">\t</span>"),
+            ' ' => html.push_str("<span class=\"ws-space\"> </span>"),
+            _ => html.push_str(&transform_text_to_html(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    html
+}
+
+/// Returns the indices of the text tokens within the comments of `annotated_tokens` that
+/// contain `/*` or `*/`, excluding the outermost delimiters of each comment.
+fn interior_delimiters(annotated_tokens: &AnnotatedFile) -> BTreeSet<usize> {
+    let tokens = annotated_tokens.tokens();
+    let mut interior = BTreeSet::new();
+    for region in annotated_tokens.comment_regions() {
+        let range = region.token_range();
+        let end = if region.is_terminated() {
+            range.end - 1
+        } else {
+            range.end
+        };
+        interior.extend((range.start + 1..end).filter(|&i| match tokens[i].token() {
+            Lexeme::Text(info) => {
+                info.characters().contains("/*") || info.characters().contains("*/")
+            }
+            _ => false,
+        }));
+    }
+    interior
+}
+
+/// Returns the number of lines of `annotated_tokens`.
+fn num_lines(annotated_tokens: &AnnotatedFile) -> usize {
+    let tokens = annotated_tokens.tokens();
+    let num_breaks = tokens
+        .iter()
+        .filter(|t| matches!(t.token(), Lexeme::LineBreak(_)))
+        .count();
+    match tokens.last() {
+        Some(t) if !matches!(t.token(), Lexeme::LineBreak(_)) => num_breaks + 1,
+        _ => num_breaks,
+    }
+}
+
+/// Returns the ids of the comments with a delimiter within the first `max_lines` lines
+/// of `annotated_tokens`, or within all lines if `max_lines` is `None`.
+fn written_comment_ids(
+    annotated_tokens: &AnnotatedFile,
+    max_lines: Option<usize>,
+) -> BTreeSet<usize> {
+    annotated_tokens
+        .tokens()
+        .iter()
+        .take_while(|t| max_lines.is_none_or(|max| t.token().get_info().line_number() <= max))
+        .filter_map(|t| t.annotation()?.comment_id())
+        .collect()
+}
+
+/// Returns the 1-indexed visual row on which the 1-indexed `column` is displayed
+/// when a line wraps after every `wrap_columns` columns.
+fn visual_row(column: usize, wrap_columns: usize) -> usize {
+    (column - 1) / wrap_columns.max(1) + 1
+}
+
+/// Returns the html of the span of the text token `token`, whose lexeme information
+/// is `token_info`, including the card displayed when hovering over the span.
+/// The comment classes of the span are prefixed by `prefix`.
+/// If `element_id` is present, the span is given the id `{prefix}lexeme-{element_id}`.
+/// If `interior_delimiter` is `true`, the span is marked as an interior delimiter.
+/// The card shows the visual row on which the token starts if `options` wrap lines,
+/// and the span has accessibility attributes if `options` are accessible.
+/// If `options` use compact cards, the lines of the card are instead written to the
+/// `title` attribute of the span.
+fn annotation_card(
+    token: &AnnotatedToken,
+    token_info: &LexemeInfo,
+    prefix: &str,
+    element_id: Option<usize>,
+    interior_delimiter: bool,
+    options: &HtmlOptions,
+) -> String {
+    let html = transform_text_to_html(token_info.characters());
+    let mut highlight = if let Some(annotation) = token.annotation() {
+        if let Some(highlight) = annotation.highlight() {
+            format!(" {highlight}")
+        } else {
+            String::new()
+        }
+    } else {
+        String::new()
+    };
+    if interior_delimiter {
+        highlight.push_str(" interior-delimiter");
+    }
+    let comment_id = if let Some(annotation) = token.annotation() {
+        if let Some(comment_id) = annotation.comment_id() {
+            format!(" {prefix}comment-{comment_id}")
+        } else {
+            String::new()
+        }
+    } else {
+        String::new()
+    };
 
-            let card = format!("<div>{range_display}</div>",);
-            Some(format!(
-                "<span class=\"code-item{highlight}{comment_id}\">{html}<div class=\"card\">{card}</div></span>",
-            ))
+    let start = token_info.start_column();
+    let end = token_info.end_column();
+    let range_display = if start == end {
+        format!("{start}")
+    } else {
+        format!("{start}&ndash;{end}")
+    };
+
+    let row = match options.wrap_columns {
+        Some(wrap_columns) => format!("<div>row {}</div>", visual_row(start, wrap_columns)),
+        None => String::new(),
+    };
+
+    let id = match element_id {
+        Some(i) => format!(" id=\"{prefix}lexeme-{i}\""),
+        None => String::new(),
+    };
+
+    let description = match rms_data::description(token_info.characters()) {
+        Some(description) if !token.is_comment() => format!("<div>{description}</div>"),
+        _ => String::new(),
+    };
+
+    let (span_aria, card_aria) = if options.accessible {
+        let columns = if start == end {
+            format!("column {start}")
+        } else {
+            format!("column {start} to {end}")
+        };
+        let label =
+            format!("{html}, line {}, {columns}", token_info.line_number()).replace('"', "&quot;");
+        (
+            format!(" tabindex=\"0\" role=\"group\" aria-label=\"{label}\""),
+            " role=\"tooltip\"",
+        )
+    } else {
+        (String::new(), "")
+    };
+
+    if options.compact_cards {
+        let mut title = vec![if start == end {
+            format!("{start}")
+        } else {
+            format!("{start}\u{2013}{end}")
+        }];
+        if let Some(wrap_columns) = options.wrap_columns {
+            title.push(format!("row {}", visual_row(start, wrap_columns)));
+        }
+        match rms_data::description(token_info.characters()) {
+            Some(description) if !token.is_comment() => title.push(String::from(description)),
+            _ => {}
         }
-        _ => None,
+        let title = escape_attribute(&title.join("\n")).replace('\n', "&#10;");
+        return format!(
+            "<span{id} class=\"code-item{highlight}{comment_id}\"{span_aria} title=\"{title}\">{html}</span>",
+        );
     }
+
+    let card = format!("<div>{range_display}</div>{row}{description}",);
+    format!(
+        "<span{id} class=\"code-item{highlight}{comment_id}\"{span_aria}>{html}<div class=\"card\"{card_aria}>{card}</div></span>",
+    )
 }
 
-/// TODO
+/// Writes a debug file of the annotated tokens using the default options.
+/// `output` is the path to which the output file is written. If a file already exists, it
+/// is overwritten.
+/// Returns an IO error if there is an error writing to the `output` file.
 pub fn write_annotated_debug_file(
     annotated_tokens: &AnnotatedFile,
     output: &Path,
+) -> std::io::Result<()> {
+    write_annotated_debug_file_with_options(annotated_tokens, output, &HtmlOptions::default())
+}
+
+/// Writes a debug file of the annotated tokens using the given `options`.
+/// `output` is the path to which the output file is written. If a file already exists, it
+/// is overwritten.
+/// Returns an IO error if there is an error writing to the `output` file.
+pub fn write_annotated_debug_file_with_options(
+    annotated_tokens: &AnnotatedFile,
+    output: &Path,
+    options: &HtmlOptions,
 ) -> std::io::Result<()> {
     let mut f = File::create(output)?;
+    write_annotated_html(&mut f, annotated_tokens, options)
+}
+
+/// Writes the html of the annotated tokens to `f` using the given `options`.
+/// Returns an IO error if there is an error writing to `f`.
+pub(crate) fn write_annotated_html(
+    f: &mut impl Write,
+    annotated_tokens: &AnnotatedFile,
+    options: &HtmlOptions,
+) -> std::io::Result<()> {
     writeln!(f, "<!DOCTYPE html>")?;
+    write_provenance(f, options)?;
     writeln!(f, "<html lang=\"en\">")?;
     writeln!(f, "{HTML_HEAD}")?;
     writeln!(f, "  <body>")?;
-    writeln!(f, "    <ol>")?;
+    write_annotated_body(f, annotated_tokens, options, "")?;
+    writeln!(f, "  </body>")?;
+    writeln!(f, "</html>")?;
+    Ok(())
+}
+
+/// Writes a self-contained html document of the annotated tokens to `f` using the given
+/// `options`, with the stylesheet and comment highlighting rules inlined in place of the
+/// linked `style.css`.
+/// Returns an IO error if there is an error writing to `f`.
+pub(crate) fn write_standalone_html(
+    f: &mut impl Write,
+    annotated_tokens: &AnnotatedFile,
+    options: &HtmlOptions,
+) -> std::io::Result<()> {
+    let comment_ids = written_comment_ids(annotated_tokens, options.max_lines);
+    let css = options.highlight_theme.css() + &comment_rules_css(comment_ids);
+    writeln!(f, "<!DOCTYPE html>")?;
+    write_provenance(f, options)?;
+    writeln!(f, "<html lang=\"en\">")?;
+    writeln!(
+        f,
+        "{}",
+        HTML_HEAD.replace(
+            "<link rel=\"stylesheet\" href=\"style.css\" />",
+            &format!("<style>\n{STYLESHEET}{css}</style>")
+        )
+    )?;
+    writeln!(f, "  <body>")?;
+    write_annotated_body(f, annotated_tokens, options, "")?;
+    writeln!(f, "  </body>")?;
+    writeln!(f, "</html>")?;
+    Ok(())
+}
+
+/// Writes the list of lines of the annotated tokens to `f` using the given `options`.
+/// The ids and comment classes of the lines' elements are prefixed by `prefix`,
+/// so that multiple files may be written to the same document.
+/// Returns an IO error if there is an error writing to `f`.
+fn write_annotated_body(
+    f: &mut impl Write,
+    annotated_tokens: &AnnotatedFile,
+    options: &HtmlOptions,
+    prefix: &str,
+) -> std::io::Result<()> {
+    let start = if options.start_line == 1 {
+        String::new()
+    } else {
+        format!(" start=\"{}\"", options.start_line)
+    };
+    match options.wrap_columns {
+        Some(wrap_columns) => writeln!(
+            f,
+            "    <ol{start} class=\"wrap\" style=\"--wrap-columns: {wrap_columns}\">"
+        )?,
+        None => writeln!(f, "    <ol{start}>")?,
+    }
+    let interior = if options.mark_interior_delimiters {
+        interior_delimiters(annotated_tokens)
+    } else {
+        BTreeSet::new()
+    };
     let mut line_in_progress = false;
-    for annotated_token in annotated_tokens.tokens() {
+    // Pairs of element ids and the byte ranges of their lexemes.
+    let mut source_map = vec![];
+    for (i, annotated_token) in annotated_tokens.tokens().iter().enumerate() {
+        let line_number = annotated_token.token().get_info().line_number();
+        if options.max_lines.is_some_and(|max| line_number > max) {
+            break;
+        }
         if !line_in_progress {
-            writeln!(f, "      <li>")?;
+            let anchor = options.start_line + line_number - 1;
+            writeln!(f, "      <li id=\"{prefix}L{anchor}\">")?;
             write!(f, "        <pre><code>")?;
             line_in_progress = true;
         }
         match annotated_token.token() {
             Lexeme::LineBreak(_token_info) => {
-                write!(f, "</code></pre>\n")?;
+                writeln!(f, "</code></pre>")?;
                 writeln!(f, "      </li>")?;
                 line_in_progress = false;
             }
+            Lexeme::Whitespace(token_info) if options.show_whitespace => {
+                write!(f, "{}", visible_whitespace(token_info.characters()))?;
+            }
             Lexeme::Whitespace(token_info) => {
                 write!(f, "{}", transform_text_to_html(token_info.characters()))?;
             }
-            Lexeme::Text(_token_info) => {
-                write!(f, "{}", annotation_card(annotated_token).unwrap())?;
+            Lexeme::Text(token_info) => {
+                let element_id = if options.source_map {
+                    source_map.push((i, token_info.byte_range()));
+                    Some(i)
+                } else {
+                    None
+                };
+                write!(
+                    f,
+                    "{}",
+                    annotation_card(
+                        annotated_token,
+                        token_info,
+                        prefix,
+                        element_id,
+                        interior.contains(&i),
+                        options
+                    )
+                )?;
             }
         }
     }
     // Ends the final line in case the file does not end with a newline character.
     if line_in_progress {
-        write!(f, "</code></pre>\n")?;
+        writeln!(f, "</code></pre>")?;
         writeln!(f, "      </li>")?;
         // line_in_progress = false;  // Assignment would be unused.
     }
 
+    if let Some(max_lines) = options.max_lines {
+        let num_omitted = num_lines(annotated_tokens).saturating_sub(max_lines);
+        if num_omitted > 0 {
+            let plural = if num_omitted == 1 { "" } else { "s" };
+            writeln!(
+                f,
+                "      <li class=\"truncated\">&hellip; ({num_omitted} more line{plural})</li>"
+            )?;
+        }
+    }
     writeln!(f, "    </ol>")?;
+    if options.source_map {
+        let entries: Vec<String> = source_map
+            .iter()
+            .map(|(i, range)| format!("\"{prefix}lexeme-{i}\":[{},{}]", range.start, range.end))
+            .collect();
+        writeln!(
+            f,
+            "    <script type=\"application/json\" id=\"{prefix}source-map\">{{{}}}</script>",
+            entries.join(",")
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the css rules highlighting the matching delimiters of each of the comments
+/// numbered below `num_comments` when one of them is hovered over.
+/// The rules are preceded by a blank line, to be appended to the copied stylesheet.
+/// Returns an empty string if there are no comments.
+pub fn comment_highlight_css(num_comments: usize) -> String {
+    comment_rules_css(0..num_comments)
+}
+
+/// Returns the css rules highlighting the matching delimiters of each of the comments
+/// numbered by `comment_ids`, preceded by a blank line.
+/// Returns an empty string if there are no comments.
+fn comment_rules_css(comment_ids: impl IntoIterator<Item = usize>) -> String {
+    let mut comment_ids = comment_ids.into_iter().peekable();
+    if comment_ids.peek().is_none() {
+        return String::new();
+    }
+    let mut css = String::from("\n");
+    for i in comment_ids {
+        css.push_str(&format!(
+            ":has(.comment-{i}:hover) .comment-{i} {{\n  background-color: #5f5f5f;\n}}\n\n"
+        ));
+    }
+    css
+}
+
+/// Writes a single debug file containing every map in `maps`, using the given `options`.
+/// Each map is a pair of its name and its annotated tokens.
+/// The maps are written in order, each in its own section, after a navigation list
+/// linking to each section.
+/// `output` is the path to which the output file is written. If a file already exists, it
+/// is overwritten.
+/// Returns an IO error if there is an error writing to the `output` file.
+pub fn write_combined_debug_file(
+    maps: &[(&str, &AnnotatedFile)],
+    output: &Path,
+    options: &HtmlOptions,
+) -> std::io::Result<()> {
+    let mut f = File::create(output)?;
+    write_combined_html(&mut f, maps, options)
+}
+
+/// Writes the html of every map in `maps` to `f` using the given `options`.
+/// The ids and comment classes of each map are prefixed by `map-{index}-`,
+/// so that hovering over a comment in one map does not highlight another's comments.
+/// Returns an IO error if there is an error writing to `f`.
+fn write_combined_html(
+    f: &mut impl Write,
+    maps: &[(&str, &AnnotatedFile)],
+    options: &HtmlOptions,
+) -> std::io::Result<()> {
+    writeln!(f, "<!DOCTYPE html>")?;
+    writeln!(f, "<html lang=\"en\">")?;
+    writeln!(f, "{HTML_HEAD}")?;
+    writeln!(f, "  <body>")?;
+    writeln!(f, "    <nav>")?;
+    writeln!(f, "      <ul>")?;
+    for (m, (name, _)) in maps.iter().enumerate() {
+        let name = transform_text_to_html(name);
+        writeln!(f, "        <li><a href=\"#map-{m}\">{name}</a></li>")?;
+    }
+    writeln!(f, "      </ul>")?;
+    writeln!(f, "    </nav>")?;
+    writeln!(f, "    <style>")?;
+    for (m, (_, annotated_tokens)) in maps.iter().enumerate() {
+        for i in written_comment_ids(annotated_tokens, options.max_lines) {
+            writeln!(
+                f,
+                "      :has(.map-{m}-comment-{i}:hover) .map-{m}-comment-{i} {{ background-color: #5f5f5f; }}"
+            )?;
+        }
+    }
+    writeln!(f, "    </style>")?;
+    for (m, (name, annotated_tokens)) in maps.iter().enumerate() {
+        writeln!(f, "    <hr />")?;
+        writeln!(f, "    <section id=\"map-{m}\">")?;
+        writeln!(f, "    <h2>{}</h2>", transform_text_to_html(name))?;
+        write_annotated_body(f, annotated_tokens, options, &format!("map-{m}-"))?;
+        writeln!(f, "    </section>")?;
+    }
     writeln!(f, "  </body>")?;
     writeln!(f, "</html>")?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+
+    /// Returns the html written for `source` using `options`.
+    fn render(source: &str, options: &HtmlOptions) -> String {
+        let annotated_file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let mut html = vec![];
+        write_annotated_html(&mut html, &annotated_file, options).unwrap();
+        String::from_utf8(html).unwrap()
+    }
+
+    /// Tests that the game editor theme inlines its color rules after the stylesheet, and
+    /// that the default theme adds none.
+    #[test]
+    fn game_editor_theme() {
+        let annotated_file = AnnotatedFile::annotate(&lexer::lex_str("/* a */ rnd(1,2)"));
+        let standalone = |highlight_theme| {
+            let options = HtmlOptions {
+                highlight_theme,
+                ..HtmlOptions::default()
+            };
+            let mut html = vec![];
+            write_standalone_html(&mut html, &annotated_file, &options).unwrap();
+            String::from_utf8(html).unwrap()
+        };
+        let css = HighlightTheme::GameEditor.css();
+        for rule in [
+            ".comment {\n  color: #008000;\n}",
+            ".function {\n  color: #0000ff;\n}",
+            ".number {\n  color: #ff8000;\n}",
+            ".constant {\n  color: #8000ff;\n}",
+            ".resource {\n  color: #800080;\n}",
+            ".section {\n  color: #804000;\n}",
+            ".command {\n  color: #0000a0;\n}",
+            ".directive {\n  color: #808080;\n}",
+            "body {\n  background-color: #ffffff;\n  color: #000000;\n}",
+        ] {
+            assert!(css.contains(rule), "{rule}");
+        }
+        assert!(standalone(HighlightTheme::GameEditor).contains(&format!("{STYLESHEET}{css}")));
+        assert!(!standalone(HighlightTheme::Default).contains("#008000"));
+        assert_eq!(
+            HighlightTheme::from_name("game-editor"),
+            Some(HighlightTheme::GameEditor)
+        );
+        assert_eq!(HighlightTheme::from_name("notepad"), None);
+    }
+
+    /// Tests that the provenance comment is written only when a source path is given.
+    #[test]
+    fn provenance() {
+        let html = render("base_terrain GRASS", &HtmlOptions::default());
+        assert!(!html.contains("<!--"));
+        let options = HtmlOptions {
+            provenance: Some(String::from("maps/a--b.rms")),
+            ..HtmlOptions::default()
+        };
+        let html = render("base_terrain GRASS", &options);
+        let comment = html.lines().nth(1).unwrap();
+        let prefix = "<!-- Generated from `maps/a- -b.rms` at ";
+        assert!(comment.starts_with(prefix), "{comment}");
+        let suffix = format!(" by aoe2-rms {}. -->", env!("CARGO_PKG_VERSION"));
+        assert!(comment.ends_with(&suffix), "{comment}");
+        let timestamp = &comment[prefix.len()..comment.len() - suffix.len()];
+        assert_eq!(timestamp.len(), "2023-11-14T22:13:20Z".len());
+        assert!(timestamp.ends_with('Z'));
+
+        let time = |seconds| UNIX_EPOCH + std::time::Duration::from_secs(seconds);
+        assert_eq!(utc_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(utc_timestamp(time(951_825_599)), "2000-02-29T11:59:59Z");
+        assert_eq!(utc_timestamp(time(1_700_000_000)), "2023-11-14T22:13:20Z");
+    }
+
+    /// Tests that the source map links span ids to the byte ranges of their lexemes.
+    #[test]
+    fn source_map() {
+        let options = HtmlOptions {
+            source_map: true,
+            ..HtmlOptions::default()
+        };
+        let html = render("é GRASS\n  x", &options);
+        assert!(html.contains("<span id=\"lexeme-0\" class=\"code-item\">é<div"));
+        assert!(html.contains("<span id=\"lexeme-5\" class=\"code-item\">x<div"));
+        assert!(html.contains(
+            "<script type=\"application/json\" id=\"source-map\">{\"lexeme-0\":[0,2],\"lexeme-2\":[3,8],\"lexeme-5\":[11,12]}</script>"
+        ));
+    }
+
+    /// Tests that compact cards are written as escaped titles instead of card elements.
+    #[test]
+    fn compact_cards() {
+        let options = HtmlOptions {
+            compact_cards: true,
+            wrap_columns: Some(4),
+            ..HtmlOptions::default()
+        };
+        let html = render("a \"b\"&<c>\nGRASS", &options);
+        assert!(!html.contains("class=\"card\""));
+        assert!(html.contains("<span class=\"code-item\" title=\"1&#10;row 1\">a</span>"));
+        assert!(html.contains(
+            "<span class=\"code-item\" title=\"3\u{2013}9&#10;row 1\">\"b\"&&lt;c&gt;</span>"
+        ));
+        let description = rms_data::terrain_from_name("GRASS", Default::default())
+            .unwrap()
+            .description();
+        assert!(html.contains(&format!(
+            "title=\"1\u{2013}5&#10;row 1&#10;{}\">GRASS</span>",
+            escape_attribute(description)
+        )));
+        assert_eq!(
+            escape_attribute("a < \"b\" & c"),
+            "a &lt; &quot;b&quot; &amp; c"
+        );
+    }
+
+    /// Tests that a snippet numbers its lines from its start line.
+    #[test]
+    fn start_line() {
+        let options = HtmlOptions {
+            start_line: 100,
+            ..HtmlOptions::default()
+        };
+        let html = render("a\nb", &options);
+        assert!(html.contains("<ol start=\"100\">\n      <li id=\"L100\">"));
+        assert!(html.contains("<li id=\"L101\">"));
+        let html = render("a\nb", &HtmlOptions::default());
+        assert!(html.contains("<ol>\n      <li id=\"L1\">"));
+    }
+
+    /// Tests that delimiters within a comment are marked, but not its outermost delimiters.
+    #[test]
+    fn interior_delimiters() {
+        let options = HtmlOptions {
+            mark_interior_delimiters: true,
+            ..HtmlOptions::default()
+        };
+        let html = render("/* a /* b */ c*/ */ x */", &options);
+        assert_eq!(html.matches("interior-delimiter").count(), 3);
+        assert!(
+            html.contains("<span class=\"code-item comment interior-delimiter comment-1\">/*<div")
+        );
+        assert!(html.contains("<span class=\"code-item comment interior-delimiter\">c*/<div"));
+        assert!(html.contains("<span class=\"code-item comment comment-0\">/*<div"));
+        assert!(!render("/* a /* b */ */", &HtmlOptions::default()).contains("interior-delimiter"));
+    }
+
+    /// Tests that the ids and comment classes of each map in a combined file are namespaced.
+    #[test]
+    fn combined_namespaces() {
+        let first = AnnotatedFile::annotate(&lexer::lex_str("/* a */"));
+        let second = AnnotatedFile::annotate(&lexer::lex_str("/* b */"));
+        let mut html = vec![];
+        let options = HtmlOptions {
+            source_map: true,
+            ..HtmlOptions::default()
+        };
+        write_combined_html(
+            &mut html,
+            &[("first", &first), ("second", &second)],
+            &options,
+        )
+        .unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains("<a href=\"#map-0\">first</a>"));
+        assert!(html.contains("<a href=\"#map-1\">second</a>"));
+        assert!(html.contains(":has(.map-0-comment-0:hover) .map-0-comment-0"));
+        assert!(html.contains(":has(.map-1-comment-0:hover) .map-1-comment-0"));
+        assert!(html.contains(
+            "<span id=\"map-0-lexeme-0\" class=\"code-item comment map-0-comment-0\">/*"
+        ));
+        assert!(html.contains(
+            "<span id=\"map-1-lexeme-0\" class=\"code-item comment map-1-comment-0\">/*"
+        ));
+        assert!(!html.contains(" comment-0"));
+        assert!(html.contains("id=\"map-1-source-map\""));
+    }
+
+    /// Tests that built-in labels and constants are described in their cards,
+    /// unless they are within a comment.
+    #[test]
+    fn card_description() {
+        let html = render("base_terrain GRASS /* GRASS */", &HtmlOptions::default());
+        assert_eq!(html.matches("<div>Grass.</div>").count(), 1);
+    }
+
+    /// Tests that a text token without an annotation is written as a plain span.
+    #[test]
+    fn unannotated_text_token() {
+        let annotated_file = AnnotatedFile::annotate(&lexer::lex_str("a <b>"));
+        let token = &annotated_file.tokens()[2];
+        assert!(token.annotation().is_none());
+        let Lexeme::Text(token_info) = token.token() else {
+            panic!("Lexeme must be text.");
+        };
+        assert_eq!(
+            annotation_card(token, token_info, "", None, false, &HtmlOptions::default()),
+            "<span class=\"code-item\">&lt;b&gt;<div class=\"card\"><div>3&ndash;5</div></div></span>"
+        );
+    }
+
+    /// Tests the visual rows of the tokens of a wrapped line.
+    #[test]
+    fn wrapped_rows() {
+        assert_eq!(visual_row(1, 10), 1);
+        assert_eq!(visual_row(10, 10), 1);
+        assert_eq!(visual_row(11, 10), 2);
+        assert_eq!(visual_row(25, 10), 3);
+        let options = HtmlOptions {
+            wrap_columns: Some(10),
+            ..HtmlOptions::default()
+        };
+        let html = render("base_terrain GRASS", &options);
+        assert!(html.contains("<ol class=\"wrap\" style=\"--wrap-columns: 10\">"));
+        assert!(html.contains("<div>1&ndash;12</div><div>row 1</div>"));
+        assert!(html.contains("<div>14&ndash;18</div><div>row 2</div>"));
+        assert!(!render("x", &HtmlOptions::default()).contains("row"));
+    }
+
+    /// Tests that accessibility attributes are added only when requested.
+    #[test]
+    fn accessible() {
+        let options = HtmlOptions {
+            accessible: true,
+            ..HtmlOptions::default()
+        };
+        let html = render("x\n  GRASS", &options);
+        assert!(html.contains(
+            "<span class=\"code-item\" tabindex=\"0\" role=\"group\" aria-label=\"GRASS, line 2, column 3 to 7\">GRASS<div class=\"card\" role=\"tooltip\">"
+        ));
+        assert!(html.contains("aria-label=\"x, line 1, column 1\""));
+        let html = render("x\n  GRASS", &HtmlOptions::default());
+        assert!(!html.contains("aria-") && !html.contains("tabindex") && !html.contains("role"));
+    }
+
+    /// Tests truncating a file after a given number of lines.
+    #[test]
+    fn truncated() {
+        let options = |max_lines| HtmlOptions {
+            max_lines: Some(max_lines),
+            ..HtmlOptions::default()
+        };
+        let source = "a\nb\nc\n";
+        let html = render(source, &options(2));
+        assert_eq!(html.matches("<li id=").count(), 2);
+        assert!(html.contains(">b<") && !html.contains(">c<"));
+        assert!(html.contains("<li class=\"truncated\">&hellip; (1 more line)</li>"));
+        assert!(!render(source, &options(3)).contains("truncated"));
+        assert!(render("a\nb\nc\nd", &options(1)).contains("(3 more lines)"));
+    }
+
+    /// Tests that the styles of truncated combined and standalone files only reference
+    /// written comments.
+    #[test]
+    fn truncated_comments() {
+        let file = AnnotatedFile::annotate(&lexer::lex_str("/* a */\n/* b\n */\n/* c */"));
+        let options = HtmlOptions {
+            max_lines: Some(2),
+            ..HtmlOptions::default()
+        };
+        let mut html = vec![];
+        write_combined_html(&mut html, &[("map", &file)], &options).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains(":has(.map-0-comment-1:hover)"));
+        assert!(!html.contains(":has(.map-0-comment-2:hover)"));
+        assert!(html.contains("&hellip; (2 more lines)"));
+        assert_eq!(written_comment_ids(&file, None).len(), 3);
+        let mut html = vec![];
+        write_standalone_html(&mut html, &file, &options).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains(":has(.comment-1:hover)"));
+        assert!(!html.contains(":has(.comment-2:hover)"));
+    }
+
+    /// Tests that whitespace glyph spans are written only when enabled, and that the text
+    /// of the spans is still the original whitespace.
+    #[test]
+    fn show_whitespace() {
+        let options = HtmlOptions {
+            show_whitespace: true,
+            ..HtmlOptions::default()
+        };
+        let html = render("\t a\u{b}b", &options);
+        assert!(html.contains(
+            "<code><span class=\"ws-tab\">\t</span><span class=\"ws-space\"> </span><span class=\"code-item\">a"
+        ));
+        let code =
+            &html[html.find("<code>").unwrap()..html.find("<span class=\"code-item").unwrap()];
+        let mut text = String::new();
+        let mut in_tag = false;
+        for c in code.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' => in_tag = false,
+                c if !in_tag => text.push(c),
+                _ => (),
+            }
+        }
+        assert_eq!(text, "\t ");
+        assert!(!render("\t a", &HtmlOptions::default()).contains("ws-"));
+    }
+
+    /// Tests that no ids or source map are written by default.
+    #[test]
+    fn no_source_map() {
+        let html = render("é GRASS\n  x", &HtmlOptions::default());
+        assert!(!html.contains("lexeme-"));
+        assert!(!html.contains("source-map"));
+    }
+}
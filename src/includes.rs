@@ -0,0 +1,209 @@
+//! Resolves the graph of map scripts included by `#include` directives, for scripts
+//! split across multiple files.
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    annotater::AnnotatedFile,
+    diagnostics::{Diagnostic, Severity},
+    source::SourceFile,
+    tokenizer,
+};
+
+/// The files reachable from a root map script through its `#include` directives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludeGraph {
+    /// The resolved files, starting with the root, in the order they are first included.
+    files: Vec<PathBuf>,
+    /// The resolved files included directly by each file, in the order of its directives.
+    includes: HashMap<PathBuf, Vec<PathBuf>>,
+    /// The path of the including file of each diagnostic, paired with the diagnostic.
+    diagnostics: Vec<(PathBuf, Diagnostic)>,
+}
+
+impl IncludeGraph {
+    /// Returns the resolved files, starting with the root, in the order they are first
+    /// included. Each file is listed once, even if it is included several times.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Returns the resolved files included directly by the file at `path`, in the order of
+    /// its directives. Missing files and the includes that complete a cycle are omitted.
+    pub fn includes(&self, path: &Path) -> &[PathBuf] {
+        self.includes.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the diagnostics of the missing includes and include cycles, each paired
+    /// with the path of the file containing its directive.
+    pub fn diagnostics(&self) -> &[(PathBuf, Diagnostic)] {
+        &self.diagnostics
+    }
+}
+
+/// Returns `path` with its `.` components removed and each `..` component removed
+/// together with the preceding normal component, without reading the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Builds the graph of the files included by the map script at `root`, following each
+/// `#include "path"` directive. An included path is relative to the directory of the
+/// file that includes it.
+///
+/// An included file that does not exist or cannot be read is reported as a
+/// `missing-include` diagnostic, and an include leading back to a file that is still
+/// being resolved, such as a file including itself, as an `include-cycle` diagnostic.
+/// Returns an IO error if the root file cannot be read.
+pub fn resolve(root: &Path) -> io::Result<IncludeGraph> {
+    let root = normalize(root);
+    let source = SourceFile::from_path(&root)?;
+    let mut graph = IncludeGraph {
+        files: vec![root.clone()],
+        includes: HashMap::new(),
+        diagnostics: vec![],
+    };
+    let mut stack = vec![root];
+    visit(source, &mut stack, &mut graph);
+    Ok(graph)
+}
+
+/// Adds the includes of `source`, the file at the top of `stack`, to `graph`, then visits
+/// each included file not yet in `graph`. The `stack` holds the chain of files being
+/// resolved, from the root.
+fn visit(source: SourceFile, stack: &mut Vec<PathBuf>, graph: &mut IncludeGraph) {
+    let path = source.path().to_path_buf();
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let file = AnnotatedFile::annotate_owned(source.into_lexemes());
+    let info = |i: usize| file.tokens()[i].token().get_info();
+    let mut includes = vec![];
+    for line in tokenizer::logical_lines(&file) {
+        let indices = line.token_indices();
+        if info(indices[0]).characters() != "#include" || indices.len() < 2 {
+            continue;
+        }
+        let argument = info(indices[1]);
+        let name = argument.characters().trim_matches('"');
+        let included = normalize(&dir.join(name));
+        if stack.contains(&included) {
+            let chain: Vec<String> = stack
+                .iter()
+                .skip_while(|file| **file != included)
+                .chain([&included])
+                .map(|file| format!("`{}`", file.display()))
+                .collect();
+            let message = format!("Including `{name}` forms a cycle: {}.", chain.join(" -> "));
+            let diagnostic = Diagnostic::new(Severity::Error, "include-cycle", message, argument);
+            graph.diagnostics.push((path.clone(), diagnostic));
+            continue;
+        }
+        if graph.files.contains(&included) {
+            includes.push(included);
+            continue;
+        }
+        let source = match SourceFile::from_path(&included) {
+            Ok(source) => source,
+            Err(e) => {
+                let message = if e.kind() == io::ErrorKind::NotFound {
+                    format!("Included file `{}` does not exist.", included.display())
+                } else {
+                    format!(
+                        "Included file `{}` could not be read: {e}",
+                        included.display()
+                    )
+                };
+                let diagnostic =
+                    Diagnostic::new(Severity::Error, "missing-include", message, argument);
+                graph.diagnostics.push((path.clone(), diagnostic));
+                continue;
+            }
+        };
+        graph.files.push(included.clone());
+        includes.push(included.clone());
+        stack.push(included);
+        visit(source, stack, graph);
+        stack.pop();
+    }
+    graph.includes.insert(path, includes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Tests resolving a chain of two includes across directories, a missing include,
+    /// and a cycle back to the root.
+    #[test]
+    fn include_chain() {
+        let dir = std::env::temp_dir().join("aoe2-rms-include-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("parts")).unwrap();
+        fs::write(
+            dir.join("main.rms"),
+            "#include \"parts/land.inc\"\n<PLAYER_SETUP>\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("parts/land.inc"),
+            "#include \"objects.inc\"\n#include \"./missing.inc\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("parts/objects.inc"),
+            "#include \"../main.rms\"\ncreate_object GOLD\n",
+        )
+        .unwrap();
+
+        let graph = resolve(&dir.join("./main.rms")).unwrap();
+        let (main, land, objects) = (
+            dir.join("main.rms"),
+            dir.join("parts/land.inc"),
+            dir.join("parts/objects.inc"),
+        );
+        assert_eq!(graph.files(), [main.clone(), land.clone(), objects.clone()]);
+        assert_eq!(graph.includes(&main), std::slice::from_ref(&land));
+        assert_eq!(graph.includes(&land), std::slice::from_ref(&objects));
+        assert!(graph.includes(&objects).is_empty());
+
+        let found: Vec<(&Path, &str, usize, usize)> = graph
+            .diagnostics()
+            .iter()
+            .map(|(path, d)| (path.as_path(), d.code(), d.line_number(), d.start_column()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (objects.as_path(), "include-cycle", 1, 10),
+                (land.as_path(), "missing-include", 2, 10),
+            ]
+        );
+        assert_eq!(
+            graph.diagnostics()[1].1.message(),
+            format!(
+                "Included file `{}` does not exist.",
+                dir.join("parts/missing.inc").display()
+            )
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
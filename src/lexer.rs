@@ -1,13 +1,18 @@
 //! Lexes a RMS file into tokens.
 
 use std::{
+    borrow::Cow,
+    fmt::Display,
     fs::File,
     io::{BufRead, BufReader, Write},
     iter::Peekable,
-    path::Path,
+    ops::Range,
+    path::{Path, PathBuf},
     str::Chars,
 };
 
+use crate::span::Span;
+
 /// Information for a lexeme.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct LexemeInfo {
@@ -17,6 +22,8 @@ pub struct LexemeInfo {
     start_column: usize,
     /// The 1-indexed column number of the final character of hte lexeme.
     end_column: usize,
+    /// The 0-indexed offset of the lexeme's first byte within the file.
+    start_byte: usize,
     /// The sequence of characters comprising the lexeme.
     characters: String,
 }
@@ -41,6 +48,48 @@ impl LexemeInfo {
     pub fn characters(&self) -> &str {
         &self.characters
     }
+
+    /// Returns this token's characters normalized for matching against names.
+    ///
+    /// Zero-width characters are removed: the zero-width space (U+200B),
+    /// zero-width non-joiner (U+200C), zero-width joiner (U+200D), word joiner (U+2060),
+    /// and zero-width no-break space or byte order mark (U+FEFF).
+    /// Then leading and trailing unicode whitespace, such as the no-break space, is trimmed.
+    /// Returns the characters unchanged, without allocating, if nothing is normalized.
+    ///
+    /// The raw `characters` are left untouched for reconstructing the file.
+    /// Note that the game does not normalize text, so, for example, a comment delimiter
+    /// followed by a zero-width space is not treated as a delimiter by the game.
+    pub fn normalized_text(&self) -> Cow<'_, str> {
+        let is_zero_width =
+            |c: char| matches!(c, '\u{200b}'..='\u{200d}' | '\u{2060}' | '\u{feff}');
+        let text = if self.characters.contains(is_zero_width) {
+            Cow::Owned(self.characters.replace(is_zero_width, ""))
+        } else {
+            Cow::Borrowed(&self.characters[..])
+        };
+        match text {
+            Cow::Borrowed(s) => Cow::Borrowed(s.trim()),
+            Cow::Owned(s) if s.trim().len() == s.len() => Cow::Owned(s),
+            Cow::Owned(s) => Cow::Owned(String::from(s.trim())),
+        }
+    }
+
+    /// Returns the range of 0-indexed byte offsets of this token within the file.
+    pub fn byte_range(&self) -> Range<usize> {
+        self.start_byte..self.start_byte + self.characters.len()
+    }
+
+    /// Returns the span of this token.
+    pub fn span(&self) -> Span {
+        Span::new(
+            self.line_number,
+            self.start_column,
+            self.line_number,
+            self.end_column,
+            self.byte_range(),
+        )
+    }
 }
 
 /// A lexeme parsed from an RMS file.
@@ -49,6 +98,8 @@ pub enum Lexeme {
     /// A line break: `\r\n` or `\n`.
     LineBreak(LexemeInfo),
     /// A consecutive sequence of whitespace characters that is not a linebreak.
+    /// Also the zero-length marker of an empty final line, see
+    /// [`LexOptions::explicit_final_line`].
     Whitespace(LexemeInfo),
     /// A lexeme of non-whitespace characters.
     Text(LexemeInfo),
@@ -63,7 +114,125 @@ impl Lexeme {
             Self::Text(t) => t,
         }
     }
+
+    /// Returns a mutable reference to the information associated with `self`.
+    fn get_info_mut(&mut self) -> &mut LexemeInfo {
+        match self {
+            Self::LineBreak(t) => t,
+            Self::Whitespace(t) => t,
+            Self::Text(t) => t,
+        }
+    }
+
+    /// Splits this lexeme into two lexemes before the character at the 0-indexed
+    /// `char_index`, recomputing the columns and byte offsets of each half.
+    ///
+    /// Text and whitespace halves keep their kind. A line break only ends with its
+    /// final `\n`, so the first half of a split line break, such as the `\r` of `\r\n`,
+    /// is whitespace.
+    ///
+    /// Panics if `char_index` is 0 or not less than the number of characters, as each
+    /// half must be nonempty.
+    pub fn split_at(&self, char_index: usize) -> (Lexeme, Lexeme) {
+        let info = self.get_info();
+        let byte_index = info
+            .characters
+            .char_indices()
+            .nth(char_index)
+            .map(|(i, _)| i)
+            .filter(|&i| i > 0)
+            .expect("Both halves of a split lexeme must be nonempty.");
+        let (first, second) = info.characters.split_at(byte_index);
+        let first = LexemeInfo {
+            line_number: info.line_number,
+            start_column: info.start_column,
+            end_column: info.start_column + char_index - 1,
+            start_byte: info.start_byte,
+            characters: String::from(first),
+        };
+        let second = LexemeInfo {
+            line_number: info.line_number,
+            start_column: info.start_column + char_index,
+            end_column: info.end_column,
+            start_byte: info.start_byte + byte_index,
+            characters: String::from(second),
+        };
+        match self {
+            Self::LineBreak(_) => (Self::Whitespace(first), Self::LineBreak(second)),
+            Self::Whitespace(_) => (Self::Whitespace(first), Self::Whitespace(second)),
+            Self::Text(_) => (Self::Text(first), Self::Text(second)),
+        }
+    }
+
+    /// Moves this lexeme to the 1-indexed line `line_number`, keeping its columns.
+    pub(crate) fn set_line_number(&mut self, line_number: usize) {
+        debug_assert!(line_number >= 1);
+        self.get_info_mut().line_number = line_number;
+    }
+
+    /// Moves this lexeme to start at the 0-indexed byte offset `start_byte`.
+    pub(crate) fn set_start_byte(&mut self, start_byte: usize) {
+        self.get_info_mut().start_byte = start_byte;
+    }
+}
+
+/// Displays the lexeme compactly as its kind, its escaped characters, and its line and
+/// columns, such as `Text("GRASS")@1:15-19` or `LineBreak("\r\n")@2:8-9`.
+impl Display for Lexeme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            Self::LineBreak(_) => "LineBreak",
+            Self::Whitespace(_) => "Whitespace",
+            Self::Text(_) => "Text",
+        };
+        let info = self.get_info();
+        write!(
+            f,
+            "{kind}({:?})@{}:{}-{}",
+            info.characters, info.line_number, info.start_column, info.end_column
+        )
+    }
 }
+
+/// A pass over the lexemes of a file, driven by [`LexemeFile::accept`].
+/// Each method is called once per lexeme of its kind, in the order of the file,
+/// and does nothing unless overridden.
+pub trait LexemeVisitor {
+    /// Visits a text lexeme.
+    fn visit_text(&mut self, _info: &LexemeInfo) {}
+
+    /// Visits a whitespace lexeme.
+    fn visit_whitespace(&mut self, _info: &LexemeInfo) {}
+
+    /// Visits a line break lexeme.
+    fn visit_line_break(&mut self, _info: &LexemeInfo) {}
+}
+
+/// The number of lexemes of each kind in a file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LexemeCounts {
+    /// The number of text lexemes.
+    pub text: usize,
+    /// The number of whitespace lexemes.
+    pub whitespace: usize,
+    /// The number of line break lexemes.
+    pub line_breaks: usize,
+}
+
+impl LexemeVisitor for LexemeCounts {
+    fn visit_text(&mut self, _info: &LexemeInfo) {
+        self.text += 1;
+    }
+
+    fn visit_whitespace(&mut self, _info: &LexemeInfo) {
+        self.whitespace += 1;
+    }
+
+    fn visit_line_break(&mut self, _info: &LexemeInfo) {
+        self.line_breaks += 1;
+    }
+}
+
 /// A sequence of lexemes comprising a file.
 /// Using the information stored in each lexeme, the file may be reconstructed
 /// exactly as it was before it was parsed.
@@ -84,12 +253,303 @@ impl LexemeFile {
         Ok(())
     }
 
+    /// Returns the source text of this file, reconstructed from its lexemes.
+    pub fn to_source_string(&self) -> String {
+        self.lexemes
+            .iter()
+            .map(|lexeme| lexeme.get_info().characters())
+            .collect()
+    }
+
+    /// Returns an iterator over the lines of this file, each a slice of lexemes ending
+    /// with its line break, if it has one.
+    /// A file ending with a line break has no empty final line unless it was lexed with
+    /// [`LexOptions::explicit_final_line`].
+    pub fn lines(&self) -> impl Iterator<Item = &[Lexeme]> {
+        self.lexemes
+            .split_inclusive(|lexeme| matches!(lexeme, Lexeme::LineBreak(_)))
+    }
+
+    /// Returns the 1-indexed numbers of the lines consisting only of whitespace, such as
+    /// spaces or tabs, before their line breaks.
+    /// Truly empty lines, including the empty marker of an explicit final line, are
+    /// not included. Such lines are emptied by [`FormatOptions::trim_trailing_whitespace`].
+    ///
+    /// [`FormatOptions::trim_trailing_whitespace`]:
+    /// crate::formatter::FormatOptions::trim_trailing_whitespace
+    pub fn whitespace_only_lines(&self) -> Vec<usize> {
+        self.lines()
+            .filter_map(|line| match line {
+                [Lexeme::Whitespace(info)] | [Lexeme::Whitespace(info), Lexeme::LineBreak(_)]
+                    if !info.characters.is_empty() =>
+                {
+                    Some(info.line_number)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Renames the identifier `from` to `to`, replacing only the whole text lexemes equal
+    /// to `from` that are not within a comment.
+    /// The columns of the following lexemes on each edited line and the byte offsets of
+    /// all following lexemes are shifted accordingly.
+    /// Returns the number of lexemes replaced.
+    ///
+    /// Panics if `to` is empty or contains whitespace, as it must remain a single lexeme.
+    pub fn rename_identifier(&mut self, from: &str, to: &str) -> usize {
+        assert!(
+            !to.is_empty() && !to.chars().any(is_whitespace),
+            "The new name must be a single text lexeme."
+        );
+        let column_change = to.chars().count() as isize - from.chars().count() as isize;
+        let byte_change = to.len() as isize - from.len() as isize;
+        let mut num_replaced = 0;
+        let mut comment_depth = 0usize;
+        // The shifts accumulated from the replacements on the current line and in the file.
+        let mut column_shift = 0isize;
+        let mut byte_shift = 0isize;
+        for lexeme in self.lexemes.iter_mut() {
+            let is_code_match = match lexeme {
+                Lexeme::Text(info) => match &info.characters[..] {
+                    "/*" => {
+                        comment_depth += 1;
+                        false
+                    }
+                    "*/" => {
+                        comment_depth = comment_depth.saturating_sub(1);
+                        false
+                    }
+                    characters => comment_depth == 0 && characters == from,
+                },
+                _ => false,
+            };
+            let info = lexeme.get_info_mut();
+            info.start_column = info.start_column.wrapping_add_signed(column_shift);
+            info.end_column = info.end_column.wrapping_add_signed(column_shift);
+            info.start_byte = info.start_byte.wrapping_add_signed(byte_shift);
+            if is_code_match {
+                info.characters = String::from(to);
+                info.end_column = info.end_column.wrapping_add_signed(column_change);
+                column_shift += column_change;
+                byte_shift += byte_change;
+                num_replaced += 1;
+            }
+            if let Lexeme::LineBreak(_) = lexeme {
+                column_shift = 0;
+            }
+        }
+        num_replaced
+    }
+
+    /// Returns a copy of this file with `f` applied to the characters of each text lexeme.
+    /// Whitespace and line breaks are unchanged.
+    /// The columns and byte offsets of all lexemes are recomputed.
+    ///
+    /// Panics if `f` returns an empty string or a string containing whitespace,
+    /// as each text lexeme must remain a single lexeme.
+    pub fn map_text<F: Fn(&str) -> String>(&self, f: F) -> LexemeFile {
+        self.map_lexemes(f, str::to_owned)
+    }
+
+    /// Returns a copy of this file with `text` applied to the characters of each text
+    /// lexeme and `whitespace` applied to the characters of each whitespace lexeme.
+    /// Line breaks are unchanged, so every lexeme keeps its line number.
+    /// The columns and byte offsets of all lexemes are recomputed.
+    ///
+    /// Panics if `text` returns an empty string or a string containing whitespace,
+    /// or if `whitespace` returns a string containing a line feed or a character that is
+    /// not whitespace, or returns an empty string for a nonempty lexeme.
+    pub fn map_lexemes<T, W>(&self, text: T, whitespace: W) -> LexemeFile
+    where
+        T: Fn(&str) -> String,
+        W: Fn(&str) -> String,
+    {
+        let mut start_column = 1;
+        let mut start_byte = 0;
+        let lexemes = self
+            .lexemes
+            .iter()
+            .map(|lexeme| {
+                let info = lexeme.get_info();
+                let characters = match lexeme {
+                    Lexeme::Text(_) => {
+                        let mapped = text(&info.characters);
+                        assert!(
+                            !mapped.is_empty() && !mapped.chars().any(is_whitespace),
+                            "A mapped text lexeme must be a single text lexeme."
+                        );
+                        mapped
+                    }
+                    Lexeme::Whitespace(_) => {
+                        let mapped = whitespace(&info.characters);
+                        assert!(
+                            mapped.chars().all(|c| is_whitespace(c) && c != '\n')
+                                && mapped.is_empty() == info.characters.is_empty(),
+                            "A mapped whitespace lexeme must be a single whitespace lexeme."
+                        );
+                        mapped
+                    }
+                    Lexeme::LineBreak(_) => info.characters.clone(),
+                };
+                let num_chars = characters.chars().count();
+                let mapped_info = LexemeInfo {
+                    line_number: info.line_number,
+                    start_column,
+                    end_column: start_column + num_chars - 1,
+                    start_byte,
+                    characters,
+                };
+                start_byte += mapped_info.characters.len();
+                let mapped = match lexeme {
+                    Lexeme::Text(_) => Lexeme::Text(mapped_info),
+                    Lexeme::Whitespace(_) => Lexeme::Whitespace(mapped_info),
+                    Lexeme::LineBreak(_) => {
+                        // The following lexeme starts the next line.
+                        start_column = 1;
+                        return Lexeme::LineBreak(mapped_info);
+                    }
+                };
+                start_column += num_chars;
+                mapped
+            })
+            .collect();
+        LexemeFile { lexemes }
+    }
+
+    /// Merges each run of consecutive text lexemes, and each run of consecutive whitespace
+    /// lexemes, on the same line into a single lexeme spanning the run, such as the
+    /// halves of a lexeme split by [`Lexeme::split_at`].
+    /// A carriage return ending the whitespace before a `\n` line break is merged into
+    /// the line break, as the `\r\n` is a single line break.
+    /// Afterwards the lexemes are those of lexing the file's source text.
+    pub fn coalesce(&mut self) {
+        let mut coalesced: Vec<Lexeme> = Vec::with_capacity(self.lexemes.len());
+        for lexeme in std::mem::take(&mut self.lexemes) {
+            let Some(previous) = coalesced.last_mut() else {
+                coalesced.push(lexeme);
+                continue;
+            };
+            match (&mut *previous, lexeme) {
+                (Lexeme::Text(first), Lexeme::Text(second))
+                | (Lexeme::Whitespace(first), Lexeme::Whitespace(second))
+                    if first.line_number == second.line_number =>
+                {
+                    first.end_column = second.end_column;
+                    first.characters.push_str(&second.characters);
+                }
+                (Lexeme::Whitespace(first), Lexeme::LineBreak(mut second))
+                    if first.characters.ends_with('\r') && second.characters == "\n" =>
+                {
+                    first.characters.pop();
+                    second.characters.insert(0, '\r');
+                    second.start_column -= 1;
+                    second.start_byte -= 1;
+                    if first.characters.is_empty() {
+                        coalesced.pop();
+                    } else {
+                        first.end_column -= 1;
+                    }
+                    coalesced.push(Lexeme::LineBreak(second));
+                }
+                (_, lexeme) => coalesced.push(lexeme),
+            }
+        }
+        self.lexemes = coalesced;
+    }
+
+    /// Calls the method of `visitor` matching the kind of each lexeme of this file, in order.
+    pub fn accept(&self, visitor: &mut impl LexemeVisitor) {
+        for lexeme in self.lexemes.iter() {
+            match lexeme {
+                Lexeme::Text(info) => visitor.visit_text(info),
+                Lexeme::Whitespace(info) => visitor.visit_whitespace(info),
+                Lexeme::LineBreak(info) => visitor.visit_line_break(info),
+            }
+        }
+    }
+
+    /// Returns the number of lexemes of each kind in this file.
+    pub fn counts(&self) -> LexemeCounts {
+        let mut counts = LexemeCounts::default();
+        self.accept(&mut counts);
+        counts
+    }
+
+    /// Returns the lexemes of this file, consuming the file.
+    pub fn into_lexemes(self) -> Vec<Lexeme> {
+        self.lexemes
+    }
+
     /// Returns a reference to the vector of lexemes in this file.
     pub fn lexemes(&self) -> &Vec<Lexeme> {
         &self.lexemes
     }
 }
 
+impl From<Vec<Lexeme>> for LexemeFile {
+    fn from(lexemes: Vec<Lexeme>) -> Self {
+        Self { lexemes }
+    }
+}
+
+/// A line of lexemes split into its indentation, its body, and what trails the body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LineParts<'a> {
+    /// The whitespace lexemes before the first text lexeme.
+    pub indent: &'a [Lexeme],
+    /// The lexemes from the first text lexeme through the final text lexeme that is not
+    /// part of a trailing comment.
+    pub body: &'a [Lexeme],
+    /// The whitespace, comment following the body, and line break ending the line.
+    pub trailing: &'a [Lexeme],
+}
+
+/// Splits `line`, such as a line returned by [`LexemeFile::lines`], into its parts.
+///
+/// A comment at the end of the line is trailing only if it begins on the line after
+/// some code, so the body of a line containing only a comment is the comment.
+/// A line without text has an empty body, and its whitespace is all indentation.
+pub fn line_parts(line: &[Lexeme]) -> LineParts<'_> {
+    let is_text = |lexeme: &Lexeme| matches!(lexeme, Lexeme::Text(_));
+    let Some(body_start) = line.iter().position(is_text) else {
+        let indent_end = line
+            .iter()
+            .position(|lexeme| matches!(lexeme, Lexeme::LineBreak(_)))
+            .unwrap_or(line.len());
+        return LineParts {
+            indent: &line[..indent_end],
+            body: &[],
+            trailing: &line[indent_end..],
+        };
+    };
+    let mut body_end = line.iter().rposition(is_text).map_or(line.len(), |i| i + 1);
+    // Finds the start of a comment ending the line, matching nested delimiters backward.
+    if line[body_end - 1].get_info().characters() == "*/" {
+        let mut depth = 0usize;
+        for i in (body_start..body_end).rev() {
+            match line[i].get_info().characters() {
+                "*/" => depth += 1,
+                "/*" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        if let Some(code_end) = line[body_start..i].iter().rposition(is_text) {
+                            body_end = body_start + code_end + 1;
+                        }
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    LineParts {
+        indent: &line[..body_start],
+        body: &line[body_start..body_end],
+        trailing: &line[body_end..],
+    }
+}
+
 /// Returns `true` if `c` is considered a whitespace character in RMS scripts.
 /// Returns `false` if not.
 ///
@@ -140,6 +600,8 @@ fn lex_one_lexeme(
         line_number,
         start_column,
         end_column: start_column + num_chars - 1,
+        // The byte offset is assigned once the offset of the line is known.
+        start_byte: 0,
         characters,
     };
     Some(if whitespace_lexeme {
@@ -153,56 +615,111 @@ fn lex_one_lexeme(
 /// If `line` ends with a line break sequence, either `\r\n`, or `\n`,
 /// then that sequence is extracted into the information for a `LineBreak` lexeme,
 /// and the returned `line_content` references the `line` without the ending break.
+/// Only a break at the end of `line` is extracted. Any other characters, including
+/// carriage returns that are not followed by a line feed, remain in `line_content`.
 ///
-/// Requires that, if `line` contains a linebreak, then the break is at the end.
 /// Requires `line_number >= 1`.
 fn extract_line_break(line: &str, line_number: usize) -> (&str, Option<LexemeInfo>) {
     debug_assert!(line_number >= 1);
-    // The debug assertions enforce the precondition of containing the linebreak
-    // only at the end. The `line`s are collected from the `lines` of a buffered reader,
-    // which should not produce "internal" line breaks.
-    if line.ends_with("\r\n") {
-        debug_assert!(line.chars().filter(|c| *c == '\n').count() == 1);
+    if let Some(content) = line.strip_suffix("\r\n") {
         // Note `col` is 0-indexed, whereas the start and end columns are 1-indexed.
-        let col = line.len() - 2;
+        // Columns count characters, not bytes.
+        let col = content.chars().count();
         (
-            &line[..col],
+            content,
             Some(LexemeInfo {
                 line_number,
                 start_column: col + 1,
                 end_column: col + 2,
+                start_byte: 0,
                 characters: String::from("\r\n"),
             }),
         )
-    } else if line.ends_with('\n') {
-        debug_assert!(line.chars().filter(|c| *c == '\n').count() == 1);
+    } else if let Some(content) = line.strip_suffix('\n') {
         // Note `col` is 0-indexed, whereas the start and end columns are 1-indexed.
-        let col = line.len() - 1;
+        let col = content.chars().count();
         (
-            &line[..col],
+            content,
             Some(LexemeInfo {
                 line_number,
                 start_column: col + 1,
                 end_column: col + 1,
+                start_byte: 0,
                 characters: String::from("\n"),
             }),
         )
     } else {
-        debug_assert!(line.chars().filter(|c| *c == '\n').count() == 0);
         (line, None)
     }
 }
 
-/// Turns the rms script in the file located at `path` into a sequence of lexemes.
+/// Options for lexing a map script.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct LexOptions {
+    /// Whether a file ending with a line break ends with a zero-length whitespace
+    /// lexeme marking its empty final line.
+    pub explicit_final_line: bool,
+}
+
+/// Turns the rms script in the file located at `path` into a sequence of lexemes
+/// using the default options.
 /// Returns the lexemes.
 /// Returns an error if there is an io error in processing the file at `path`.
 pub fn lex(path: &Path) -> std::io::Result<LexemeFile> {
+    lex_with_options(path, &LexOptions::default())
+}
+
+/// Lexes each of the files at `paths`, returning each path paired with the result of
+/// lexing its file, in the order of `paths`.
+/// An error reading one file does not prevent the others from being lexed.
+/// With the `rayon` feature, the files are lexed in parallel.
+pub fn lex_all(paths: &[PathBuf]) -> Vec<(PathBuf, std::io::Result<LexemeFile>)> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        paths
+            .par_iter()
+            .map(|path| (path.clone(), lex(path)))
+            .collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        paths.iter().map(|path| (path.clone(), lex(path))).collect()
+    }
+}
+
+/// Turns the rms script in the file located at `path` into a sequence of lexemes
+/// using the given `options`.
+/// Returns the lexemes.
+/// Returns an error if there is an io error in processing the file at `path`.
+pub fn lex_with_options(path: &Path, options: &LexOptions) -> std::io::Result<LexemeFile> {
     let f = File::open(path)?;
-    let mut br = BufReader::new(f);
+    lex_buffer(BufReader::new(f), options)
+}
+
+/// Turns the rms script `source` into a sequence of lexemes using the default options.
+/// Returns the lexemes.
+pub fn lex_str(source: &str) -> LexemeFile {
+    lex_str_with_options(source, &LexOptions::default())
+}
+
+/// Turns the rms script `source` into a sequence of lexemes using the given `options`.
+/// Returns the lexemes.
+pub fn lex_str_with_options(source: &str, options: &LexOptions) -> LexemeFile {
+    // Reading from a byte slice of valid UTF-8 cannot fail.
+    lex_buffer(source.as_bytes(), options).expect("Lexing a string cannot fail.")
+}
+
+/// Turns the rms script read from `br` into a sequence of lexemes using `options`.
+/// Returns the lexemes.
+/// Returns an error if there is an io error in reading from `br`.
+fn lex_buffer(mut br: impl BufRead, options: &LexOptions) -> std::io::Result<LexemeFile> {
     let mut lexemes = vec![];
     let mut line_number = 1;
+    let mut line_start_byte = 0;
     let mut line = String::new();
     while br.read_line(&mut line)? > 0 {
+        let first_lexeme = lexemes.len();
         let (line_content, line_break) = extract_line_break(&line, line_number);
         let mut start_column = 1;
         let mut chars = line_content.chars().peekable();
@@ -213,9 +730,24 @@ pub fn lex(path: &Path) -> std::io::Result<LexemeFile> {
         if let Some(break_info) = line_break {
             lexemes.push(Lexeme::LineBreak(break_info));
         }
+        let mut start_byte = line_start_byte;
+        for lexeme in lexemes[first_lexeme..].iter_mut() {
+            lexeme.set_start_byte(start_byte);
+            start_byte += lexeme.get_info().characters.len();
+        }
+        line_start_byte += line.len();
         line_number += 1;
         line.clear();
     }
+    if options.explicit_final_line && matches!(lexemes.last(), Some(Lexeme::LineBreak(_))) {
+        lexemes.push(Lexeme::Whitespace(LexemeInfo {
+            line_number,
+            start_column: 1,
+            end_column: 0,
+            start_byte: line_start_byte,
+            characters: String::new(),
+        }));
+    }
     Ok(LexemeFile { lexemes })
 }
 
@@ -223,6 +755,216 @@ pub fn lex(path: &Path) -> std::io::Result<LexemeFile> {
 mod tests {
     use super::*;
 
+    /// Tests the displayed form of each kind of lexeme, with escaped characters.
+    #[test]
+    fn display() {
+        let file = lex_str("base_terrain\t\"GRASS\"\r\n");
+        let displayed: Vec<String> = file.lexemes().iter().map(Lexeme::to_string).collect();
+        assert_eq!(
+            displayed,
+            vec![
+                "Text(\"base_terrain\")@1:1-12",
+                "Whitespace(\"\\t\")@1:13-13",
+                "Text(\"\\\"GRASS\\\"\")@1:14-20",
+                "LineBreak(\"\\r\\n\")@1:21-22",
+            ]
+        );
+    }
+
+    /// Tests splitting lines into their indentation, body, and trailing lexemes.
+    #[test]
+    fn parts_of_lines() {
+        let file = lex_str("  base_size 7 /* a /* b */ */ \r\n/* only */\n\t\nx */ y");
+        let lines: Vec<&[Lexeme]> = file.lines().collect();
+        let text = |lexemes: &[Lexeme]| -> String {
+            lexemes.iter().map(|l| l.get_info().characters()).collect()
+        };
+        let parts: Vec<(String, String, String)> = lines
+            .iter()
+            .map(|line| {
+                let parts = line_parts(line);
+                (text(parts.indent), text(parts.body), text(parts.trailing))
+            })
+            .collect();
+        let expected = [
+            ("  ", "base_size 7", " /* a /* b */ */ \r\n"),
+            ("", "/* only */", "\n"),
+            ("\t", "", "\n"),
+            ("", "x */ y", ""),
+        ];
+        let expected: Vec<(String, String, String)> = expected
+            .iter()
+            .map(|&(a, b, c)| (a.into(), b.into(), c.into()))
+            .collect();
+        assert_eq!(parts, expected);
+    }
+
+    /// Tests that a custom visitor sees the same text lexemes as the file's counts.
+    #[test]
+    fn visitor() {
+        /// Counts the text lexemes and collects the uppercase ones.
+        #[derive(Default)]
+        struct Constants {
+            num_text: usize,
+            constants: Vec<String>,
+        }
+        impl LexemeVisitor for Constants {
+            fn visit_text(&mut self, info: &LexemeInfo) {
+                self.num_text += 1;
+                if info.characters().chars().all(|c| c.is_ascii_uppercase()) {
+                    self.constants.push(String::from(info.characters()));
+                }
+            }
+        }
+        let file = lex_str("base_terrain GRASS\r\n  /* DESERT */\n");
+        let mut visitor = Constants::default();
+        file.accept(&mut visitor);
+        let counts = file.counts();
+        assert_eq!(visitor.num_text, counts.text);
+        assert_eq!(visitor.constants, vec!["GRASS", "DESERT"]);
+        assert_eq!(
+            counts,
+            LexemeCounts {
+                text: 5,
+                whitespace: 4,
+                line_breaks: 2
+            }
+        );
+    }
+
+    /// Tests the lines of a file ending with a line break, with and without an explicit
+    /// empty final line.
+    #[test]
+    fn explicit_final_line() {
+        let source = "a\r\nb\n";
+        let implicit = lex_str(source);
+        assert_eq!(implicit.lines().count(), 2);
+        assert!(matches!(
+            implicit.lexemes().last(),
+            Some(Lexeme::LineBreak(_))
+        ));
+
+        let options = LexOptions {
+            explicit_final_line: true,
+        };
+        let explicit = lex_str_with_options(source, &options);
+        let lines: Vec<&[Lexeme]> = explicit.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let [Lexeme::Whitespace(marker)] = lines[2] else {
+            panic!("The final line must be a single marker.");
+        };
+        assert_eq!(marker.line_number(), 3);
+        assert_eq!(marker.characters(), "");
+        assert_eq!(marker.byte_range(), 5..5);
+        assert_eq!(explicit.to_source_string(), source);
+        assert_eq!(lex_str_with_options("a", &options).lines().count(), 1);
+    }
+
+    /// Tests that lines of spaces and tabs are distinguished from truly empty lines.
+    #[test]
+    fn whitespace_only_lines() {
+        let source = "a\n\n  \r\n\t \nb \n  ";
+        assert_eq!(lex_str(source).whitespace_only_lines(), vec![3, 4, 6]);
+        let options = LexOptions {
+            explicit_final_line: true,
+        };
+        let file = lex_str_with_options("a\n\n", &options);
+        assert!(file.whitespace_only_lines().is_empty());
+    }
+
+    /// Tests that coalescing a file of split lexemes matches relexing its source.
+    #[test]
+    fn coalesce() {
+        let source = "  base_terrain GRASS\t \r\nx /*comment*/\r\n";
+        let mut lexemes = vec![];
+        for lexeme in lex_str(source).into_lexemes() {
+            let num_chars = lexeme.get_info().characters().chars().count();
+            if num_chars < 2 {
+                lexemes.push(lexeme);
+                continue;
+            }
+            let (first, second) = lexeme.split_at(1);
+            lexemes.push(first);
+            lexemes.push(second);
+        }
+        let mut file = LexemeFile::from(lexemes);
+        assert_ne!(file, lex_str(source));
+        file.coalesce();
+        assert_eq!(file, lex_str(&file.to_source_string()));
+        assert_eq!(file.to_source_string(), source);
+    }
+
+    /// Tests that renaming replaces whole code lexemes, skipping comments and substrings.
+    #[test]
+    fn rename_identifier() {
+        let mut file = lex_str("#const ROCK 1 /* ROCK */\nROCKS ROCK é\nROCK");
+        assert_eq!(file.rename_identifier("ROCK", "STONE_X"), 3);
+        assert_eq!(
+            file.to_source_string(),
+            "#const STONE_X 1 /* ROCK */\nROCKS STONE_X é\nSTONE_X"
+        );
+        assert_eq!(file, lex_str(&file.to_source_string()));
+    }
+
+    /// Tests that mapping lexemes recomputes their positions to match relexing the result.
+    #[test]
+    fn map_text() {
+        let file = lex_str("#const rock é\r\n\tbase_size 7\n");
+        let upper = file.map_text(str::to_uppercase);
+        assert_eq!(upper.to_source_string(), "#CONST ROCK É\r\n\tBASE_SIZE 7\n");
+        assert_eq!(upper, lex_str(&upper.to_source_string()));
+        let spaced = file.map_lexemes(|text| format!("{text}é"), |_| String::from("  "));
+        assert_eq!(
+            spaced.to_source_string(),
+            "#consté  rocké  éé\r\n  base_sizeé  7é\n"
+        );
+        assert_eq!(spaced, lex_str(&spaced.to_source_string()));
+        assert_eq!(spaced.lexemes()[2].get_info().start_column(), 10);
+    }
+
+    /// Tests splitting a glued comment into its delimiters and contents.
+    #[test]
+    fn split_glued_comment() {
+        let file = lex_str("x /*comment*/");
+        let glued = &file.lexemes()[2];
+        let (open, rest) = glued.split_at(2);
+        let (contents, close) = rest.split_at(7);
+        let parts = [&open, &contents, &close].map(|l| {
+            let info = l.get_info();
+            (
+                info.characters(),
+                info.start_column(),
+                info.end_column(),
+                info.byte_range(),
+            )
+        });
+        assert_eq!(
+            parts,
+            [
+                ("/*", 3, 4, 2..4),
+                ("comment", 5, 11, 4..11),
+                ("*/", 12, 13, 11..13)
+            ]
+        );
+        assert!(matches!(close, Lexeme::Text(_)));
+    }
+
+    /// Tests that the carriage return of a split line break becomes whitespace.
+    #[test]
+    fn split_line_break() {
+        let file = lex_str("é\r\n");
+        let (cr, lf) = file.lexemes()[1].split_at(1);
+        assert!(matches!(cr, Lexeme::Whitespace(ref info) if info.byte_range() == (2..3)));
+        assert!(matches!(lf, Lexeme::LineBreak(ref info) if info.byte_range() == (3..4)));
+    }
+
+    /// Tests that a lexeme cannot be split into an empty half.
+    #[test]
+    #[should_panic]
+    fn split_empty_half() {
+        lex_str("abc").lexemes()[0].split_at(3);
+    }
+
     /// Tests a horizontal tab is considered whitespace.
     #[test]
     fn is_whitespace_t() {
@@ -553,6 +1295,18 @@ mod tests {
         assert_eq!(info.characters, "  \t \t\t ");
     }
 
+    /// Tests the byte ranges of lexemes following multi-byte characters and line breaks.
+    #[test]
+    fn lex_str_byte_ranges() {
+        let lexemes = lex_str("é GRASS\r\n  語");
+        let ranges: Vec<Range<usize>> = lexemes
+            .lexemes()
+            .iter()
+            .map(|lexeme| lexeme.get_info().byte_range())
+            .collect();
+        assert_eq!(ranges, vec![0..2, 2..3, 3..8, 8..10, 10..12, 12..15]);
+    }
+
     /// Tests that no line break is extracted from an empty string.
     #[test]
     fn extract_line_break_empty() {
@@ -600,4 +1354,76 @@ mod tests {
         assert_eq!(info.end_column, 20);
         assert_eq!(info.characters, "\r\n");
     }
+
+    /// Tests that the columns of a line break follow multi-byte characters by character.
+    #[test]
+    fn extract_line_break_multi_byte() {
+        let (content, info) = extract_line_break("terrain_type 語é🗺\n", 1);
+        assert_eq!(content, "terrain_type 語é🗺");
+        let info = info.unwrap();
+        assert_eq!(info.start_column, 17);
+        assert_eq!(info.end_column, 17);
+        let (_, info) = extract_line_break("é\r\n", 1);
+        let info = info.unwrap();
+        assert_eq!((info.start_column, info.end_column), (2, 3));
+    }
+
+    /// Tests that an interior carriage return is kept in the line content.
+    #[test]
+    fn extract_line_break_interior_carriage_return() {
+        let (content, info) = extract_line_break("base_terrain\rGRASS\r\r\n", 1);
+        assert_eq!(content, "base_terrain\rGRASS\r");
+        let info = info.unwrap();
+        assert_eq!(info.start_column, 20);
+        assert_eq!(info.end_column, 21);
+        assert_eq!(info.characters, "\r\n");
+    }
+
+    /// Tests that interior carriage returns are lexed as whitespace with correct columns.
+    #[test]
+    fn lex_str_interior_carriage_return() {
+        let lexemes = lex_str("a\rb\r\r\nc");
+        let lexemes: Vec<(usize, usize, usize, &str)> = lexemes
+            .lexemes()
+            .iter()
+            .map(|lexeme| {
+                let info = lexeme.get_info();
+                let characters = info.characters();
+                (
+                    info.line_number,
+                    info.start_column,
+                    info.end_column,
+                    characters,
+                )
+            })
+            .collect();
+        assert_eq!(
+            lexemes,
+            vec![
+                (1, 1, 1, "a"),
+                (1, 2, 2, "\r"),
+                (1, 3, 3, "b"),
+                (1, 4, 4, "\r"),
+                (1, 5, 6, "\r\n"),
+                (2, 1, 1, "c"),
+            ]
+        );
+    }
+
+    /// Tests that normalization removes zero-width characters and trims whitespace,
+    /// without affecting the reconstruction of the file.
+    #[test]
+    fn normalized_text() {
+        let source = "\u{a0}GRASS\u{200b} \u{feff}REGI\u{200d}CIDE DESERT";
+        let lexemes = lex_str(source);
+        let texts: Vec<Cow<str>> = lexemes
+            .lexemes()
+            .iter()
+            .filter(|lexeme| matches!(lexeme, Lexeme::Text(_)))
+            .map(|lexeme| lexeme.get_info().normalized_text())
+            .collect();
+        assert_eq!(texts, vec!["GRASS", "REGICIDE", "DESERT"]);
+        assert!(matches!(texts[2], Cow::Borrowed(_)));
+        assert_eq!(lexemes.to_source_string(), source);
+    }
 }
@@ -1,7 +1,29 @@
 //! Collection of modules for working with RMS files.
 
 pub mod annotater;
+#[cfg(feature = "zip")]
+pub mod archive;
+pub mod checker;
+pub mod config;
+pub mod definitions;
+pub mod diagnostics;
+pub mod explain;
+pub mod export;
+pub mod formatter;
 pub mod html_writer;
+pub mod includes;
 pub mod lexer;
-mod rms_data;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod manifest;
+pub mod pipeline;
+pub mod rms_data;
+pub mod source;
+pub mod span;
+mod suggest;
 pub mod tokenizer;
+pub mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "notify")]
+pub mod watch;
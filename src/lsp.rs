@@ -0,0 +1,479 @@
+//! A minimal synchronous Language Server Protocol server for map scripts, speaking
+//! JSON-RPC over a reader and a writer such as stdin and stdout.
+//!
+//! The server keeps the full text of each open document, which clients send in full on
+//! every change, and supports publishing diagnostics, `textDocument/hover`,
+//! `textDocument/semanticTokens/full`, and `textDocument/completion`.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    annotater::AnnotatedFile,
+    checker::{self, CheckOptions},
+    diagnostics::{Diagnostic, Severity},
+    export, lexer,
+    lexer::Lexeme,
+    rms_data::{self, CompletionKind},
+};
+
+/// The JSON-RPC error code of a request for a method the server does not support.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Reads one message from `reader`, framed by a `Content-Length` header.
+/// Returns `None` if `reader` ends before the start of a message.
+/// Returns an IO error if the header or body is malformed or if reading fails.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    let mut header = String::new();
+    loop {
+        header.clear();
+        if reader.read_line(&mut header)? == 0 {
+            return match content_length {
+                None => Ok(None),
+                Some(_) => Err(invalid_data("The message ended within its header.")),
+            };
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let length = value.trim().parse().map_err(invalid_data)?;
+                content_length = Some(length);
+            }
+        }
+    }
+    let length = content_length.ok_or_else(|| invalid_data("Missing Content-Length header."))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(invalid_data)
+}
+
+/// Writes `message` to `writer`, framed by a `Content-Length` header.
+/// Returns an IO error if writing fails.
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
+
+/// Returns an IO error of kind `InvalidData` wrapping `error`.
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// An open document, consisting of its text and its annotated tokens.
+struct Document {
+    /// The full text of the document.
+    text: String,
+    /// The annotated tokens of the text.
+    file: AnnotatedFile,
+}
+
+impl Document {
+    /// Lexes and annotates `text`.
+    fn new(text: String) -> Self {
+        let file = AnnotatedFile::annotate_owned(lexer::lex_str(&text));
+        Self { text, file }
+    }
+
+    /// Returns the text of the 1-indexed `line`, excluding its line break,
+    /// or an empty string if there is no such line.
+    fn line(&self, line: usize) -> &str {
+        self.text.lines().nth(line - 1).unwrap_or("")
+    }
+
+    /// Returns the protocol position of the 1-indexed `line` and `column`, counting
+    /// characters in UTF-16 code units.
+    fn position(&self, line: usize, column: usize) -> Value {
+        let character: usize = self
+            .line(line)
+            .chars()
+            .take(column - 1)
+            .map(char::len_utf16)
+            .sum();
+        json!({ "line": line - 1, "character": character })
+    }
+
+    /// Returns the 1-indexed line and column of the protocol `position`,
+    /// or `None` if `position` is malformed.
+    fn line_column(&self, position: &Value) -> Option<(usize, usize)> {
+        let line = usize::try_from(position["line"].as_u64()?)
+            .ok()?
+            .checked_add(1)?;
+        let character = position["character"].as_u64()? as usize;
+        // The column is after every character ending at or before `character`.
+        let mut end = 0;
+        let column = 1 + self
+            .line(line)
+            .chars()
+            .take_while(|c| {
+                end += c.len_utf16();
+                end <= character
+            })
+            .count();
+        Some((line, column))
+    }
+
+    /// Returns the protocol range of the characters of `diagnostic`.
+    fn range(&self, diagnostic: &Diagnostic) -> Value {
+        let span = diagnostic.span();
+        json!({
+            "start": self.position(span.start_line(), span.start_column()),
+            "end": self.position(span.end_line(), span.end_column() + 1),
+        })
+    }
+}
+
+/// The state of a language server: its open documents and whether it was shut down.
+#[derive(Default)]
+pub struct Server {
+    /// The open documents, keyed by uri.
+    documents: HashMap<String, Document>,
+    /// Whether the client requested a shutdown.
+    shut_down: bool,
+    /// Whether the client sent the `exit` notification.
+    exited: bool,
+}
+
+impl Server {
+    /// Returns a new server without open documents.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if the client sent the `exit` notification, `false` otherwise.
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Returns `true` if the client requested a shutdown before exiting, `false` otherwise.
+    pub fn shut_down(&self) -> bool {
+        self.shut_down
+    }
+
+    /// Handles the request or notification `message`.
+    /// Returns the messages to send to the client in response, in order.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let id = message.get("id");
+        let result = match method {
+            "initialize" => Some(initialize_result()),
+            "shutdown" => {
+                self.shut_down = true;
+                Some(Value::Null)
+            }
+            "exit" => {
+                self.exited = true;
+                None
+            }
+            "textDocument/didOpen" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                return self.update(uri, String::from(text));
+            }
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                // Documents are synchronized in full, so the final change is the whole text.
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                return match text {
+                    Some(text) => self.update(uri, String::from(text)),
+                    None => vec![],
+                };
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                self.documents.remove(uri);
+                None
+            }
+            "textDocument/hover" => Some(self.hover(params)),
+            "textDocument/semanticTokens/full" => Some(self.semantic_tokens(params)),
+            "textDocument/completion" => Some(completions()),
+            _ => {
+                return match id {
+                    Some(id) => vec![json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {
+                            "code": METHOD_NOT_FOUND,
+                            "message": format!("Unsupported method `{method}`."),
+                        },
+                    })],
+                    None => vec![],
+                }
+            }
+        };
+        match (id, result) {
+            (Some(id), Some(result)) => {
+                vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Replaces the text of the document at `uri`, returning the notification publishing
+    /// its diagnostics.
+    fn update(&mut self, uri: &str, text: String) -> Vec<Value> {
+        let document = Document::new(text);
+        let diagnostics: Vec<Value> = checker::check(&document.file, &CheckOptions::default())
+            .iter()
+            .map(|diagnostic| {
+                json!({
+                    "range": document.range(diagnostic),
+                    "severity": match diagnostic.severity() {
+                        Severity::Error => 1,
+                        Severity::Warning => 2,
+                        Severity::Info => 3,
+                    },
+                    "code": diagnostic.code(),
+                    "source": "aoe2-rms",
+                    "message": diagnostic.message(),
+                })
+            })
+            .collect();
+        self.documents.insert(String::from(uri), document);
+        vec![json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        })]
+    }
+
+    /// Returns the hover of the token at the position of `params`, describing a built-in
+    /// name, or null if there is no such token.
+    fn hover(&self, params: &Value) -> Value {
+        let hover = || {
+            let document = self
+                .documents
+                .get(params["textDocument"]["uri"].as_str()?)?;
+            let (line, column) = document.line_column(&params["position"])?;
+            let token = &document.file.tokens()[document.file.token_index_at(line, column)?];
+            let Lexeme::Text(info) = token.token() else {
+                return None;
+            };
+            if token.is_comment() {
+                return None;
+            }
+            let description = rms_data::description(info.characters())?;
+            Some(json!({
+                "contents": {
+                    "kind": "markdown",
+                    "value": format!("`{}`\n\n{description}", info.characters()),
+                },
+                "range": {
+                    "start": document.position(line, info.start_column()),
+                    "end": document.position(line, info.end_column() + 1),
+                },
+            }))
+        };
+        hover().unwrap_or(Value::Null)
+    }
+
+    /// Returns the semantic tokens of the document of `params`, or null if the document
+    /// is not open.
+    fn semantic_tokens(&self, params: &Value) -> Value {
+        params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| self.documents.get(uri))
+            .map_or(
+                Value::Null,
+                |document| json!({ "data": export::semantic_tokens(&document.file) }),
+            )
+    }
+}
+
+/// Returns the result of the `initialize` request, advertising the server's capabilities.
+fn initialize_result() -> Value {
+    json!({
+        "capabilities": {
+            // Documents are synchronized by sending their full text.
+            "textDocumentSync": 1,
+            "hoverProvider": true,
+            "completionProvider": {},
+            "semanticTokensProvider": {
+                "legend": {
+                    "tokenTypes": export::SEMANTIC_TOKEN_TYPES,
+                    "tokenModifiers": [],
+                },
+                "full": true,
+            },
+        },
+        "serverInfo": { "name": "aoe2-rms", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+/// Returns the completion items of every built-in name.
+fn completions() -> Value {
+    let items: Vec<Value> = rms_data::all_completions()
+        .iter()
+        .map(|completion| {
+            // The protocol's `CompletionItemKind` of each kind of name.
+            let kind = match completion.kind() {
+                CompletionKind::Command => 14,
+                CompletionKind::Section => 9,
+                CompletionKind::Label => 21,
+                CompletionKind::Terrain | CompletionKind::AssignType => 20,
+                CompletionKind::Resource => 6,
+            };
+            json!({
+                "label": completion.name(),
+                "kind": kind,
+                "detail": completion.description(),
+            })
+        })
+        .collect();
+    Value::from(items)
+}
+
+/// Runs a server that reads messages from `reader` and writes messages to `writer` until
+/// the client sends the `exit` notification or `reader` ends.
+/// Returns `true` if the client requested a shutdown before exiting, `false` otherwise.
+/// Returns an IO error if reading or writing a message fails.
+pub fn run(mut reader: impl BufRead, mut writer: impl Write) -> io::Result<bool> {
+    let mut server = Server::new();
+    while let Some(message) = read_message(&mut reader)? {
+        for response in server.handle(&message) {
+            write_message(&mut writer, &response)?;
+        }
+        if server.exited() {
+            break;
+        }
+    }
+    Ok(server.shut_down())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::Span;
+
+    /// Returns `message` framed by its header.
+    fn frame(message: &Value) -> Vec<u8> {
+        let mut framed = vec![];
+        write_message(&mut framed, message).unwrap();
+        framed
+    }
+
+    /// Tests writing and reading back framed messages.
+    #[test]
+    fn framing() {
+        let first = json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" });
+        let second = json!({ "jsonrpc": "2.0", "method": "exit", "params": { "é": "ü" } });
+        let mut framed = frame(&first);
+        assert!(framed.starts_with(b"Content-Length: 46\r\n\r\n{"));
+        framed.extend(frame(&second));
+        let mut reader = &framed[..];
+        assert_eq!(read_message(&mut reader).unwrap(), Some(first));
+        assert_eq!(read_message(&mut reader).unwrap(), Some(second));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+        let mut truncated = &b"Content-Length: 10\r\n\r\n{}"[..];
+        assert!(read_message(&mut truncated).is_err());
+    }
+
+    /// Tests that opening a document with an invalid assign type publishes an error,
+    /// and that hovering describes a constant.
+    #[test]
+    fn did_open_publishes_diagnostics() {
+        let uri = "file:///maps/test.rms";
+        let open = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {
+                "textDocument": {
+                    "uri": uri,
+                    "languageId": "rms",
+                    "version": 1,
+                    "text": "<PLAYER_SETUP>\n/* 𝄞 */ assign_to AT_NOTHING 0 0 0\n",
+                },
+            },
+        });
+        let exit = json!({ "jsonrpc": "2.0", "method": "exit" });
+        let mut input = frame(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" }));
+        input.extend(frame(&open));
+        input.extend(frame(&exit));
+        let mut output = vec![];
+        assert!(!run(&input[..], &mut output).unwrap());
+
+        let mut reader = &output[..];
+        let initialized = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(initialized["id"], 1);
+        assert_eq!(
+            initialized["result"]["capabilities"]["semanticTokensProvider"]["legend"]["tokenTypes"]
+                [3],
+            "enumMember"
+        );
+        let published = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(published["method"], "textDocument/publishDiagnostics");
+        assert_eq!(published["params"]["uri"], uri);
+        let diagnostics = published["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["code"], "invalid-assign-type");
+        assert_eq!(diagnostics[0]["severity"], 1);
+        assert_eq!(
+            diagnostics[0]["range"],
+            json!({
+                "start": { "line": 1, "character": 19 },
+                "end": { "line": 1, "character": 29 },
+            })
+        );
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    /// Tests that the range of a diagnostic spanning several lines ends on its final line.
+    #[test]
+    fn multi_line_range() {
+        let document = Document::new(String::from("create_land {\n  base_size 5\n}"));
+        let span = Span::new(1, 1, 3, 1, 0..28);
+        let diagnostic = Diagnostic::with_span(Severity::Warning, "test", String::new(), span);
+        assert_eq!(
+            document.range(&diagnostic),
+            json!({
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 2, "character": 1 },
+            })
+        );
+    }
+
+    /// Tests that a position whose line overflows is rejected as malformed.
+    #[test]
+    fn overflowing_line() {
+        let document = Document::new(String::from("x"));
+        let position = json!({ "line": u64::MAX, "character": 0 });
+        assert_eq!(document.line_column(&position), None);
+    }
+
+    /// Tests hovering over an assign type and over whitespace.
+    #[test]
+    fn hover() {
+        let mut server = Server::new();
+        server.update("a.rms", String::from("assign_to AT_TEAM 1 0 0"));
+        let hover = |character| {
+            server.hover(&json!({
+                "textDocument": { "uri": "a.rms" },
+                "position": { "line": 0, "character": character },
+            }))
+        };
+        let result = hover(12);
+        assert!(result["contents"]["value"]
+            .as_str()
+            .unwrap()
+            .starts_with("`AT_TEAM`\n\n"));
+        assert_eq!(result["range"]["start"]["character"], 10);
+        assert_eq!(hover(9), Value::Null);
+    }
+}
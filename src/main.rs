@@ -5,9 +5,269 @@
 //! matures.
 
 use std::io::Write;
-use std::{fs::OpenOptions, path::PathBuf, process};
+#[cfg(feature = "notify")]
+use std::time::Instant;
+use std::{
+    fs::OpenOptions,
+    path::{Path, PathBuf},
+    process,
+};
 
-use aoe2_rms::{annotater::AnnotatedFile, html_writer, lexer};
+use aoe2_rms::{
+    annotater::{AnnotateOptions, AnnotatedFile},
+    checker::{self, CheckOptions},
+    config::{self, Config},
+    diagnostics::{self, Severity},
+    explain, export,
+    html_writer::{self, HighlightTheme},
+    lexer,
+    manifest::Manifest,
+    pipeline::Pipeline,
+    rms_data::LabelType,
+    source::SourceFile,
+    validate,
+};
+
+/// How much output the application prints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Verbosity {
+    /// Prints only errors.
+    Quiet,
+    /// Prints errors and a one-line summary per file.
+    Normal,
+    /// Prints errors, summaries, and per-file timings and counts.
+    Verbose,
+}
+
+/// The format in which diagnostics are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// One human-readable line per diagnostic.
+    Text,
+    /// One JSON object per diagnostic, each on its own line.
+    Jsonl,
+    /// One GitHub Actions workflow command per diagnostic.
+    Github,
+    /// A snippet of the source line with a caret beneath each diagnostic.
+    Pretty,
+    /// A single SARIF log of the diagnostics of every map.
+    Sarif,
+}
+
+/// Prints messages to standard error according to the selected verbosity.
+#[derive(Debug, Clone, Copy)]
+struct Logger {
+    verbosity: Verbosity,
+}
+
+impl Logger {
+    /// Prints an error message, which is printed at every verbosity.
+    fn error(&self, message: impl std::fmt::Display) {
+        eprintln!("{message}");
+    }
+
+    /// Prints a summary message, unless the verbosity is quiet.
+    fn info(&self, message: impl std::fmt::Display) {
+        if self.verbosity >= Verbosity::Normal {
+            eprintln!("{message}");
+        }
+    }
+
+    /// Prints a detailed message, only if the verbosity is verbose.
+    fn verbose(&self, message: impl std::fmt::Display) {
+        if self.verbosity >= Verbosity::Verbose {
+            eprintln!("{message}");
+        }
+    }
+}
+
+/// Returns the least severe diagnostic that causes a nonzero exit named by `name`, as
+/// accepted by `--fail-on`, or `None` to never fail.
+/// Returns an error message if `name` is not `error`, `warning`, `info`, or `never`.
+fn parse_fail_on(name: &str) -> Result<Option<Severity>, String> {
+    match name {
+        "error" => Ok(Some(Severity::Error)),
+        "warning" => Ok(Some(Severity::Warning)),
+        "info" => Ok(Some(Severity::Info)),
+        "never" => Ok(None),
+        other => Err(format!(
+            "Unknown severity `{other}`, expected `error`, `warning`, `info`, or `never`."
+        )),
+    }
+}
+
+/// Reads the configuration file discovered in the working directory or one of its
+/// parents, or returns the default configuration if there is none.
+/// Exits the process if the configuration file cannot be read.
+fn read_config() -> Config {
+    let Ok(cwd) = std::env::current_dir() else {
+        return Config::default();
+    };
+    let Some(path) = config::discover(&cwd) else {
+        return Config::default();
+    };
+    // Keeps the configured paths relative when the file is in the working directory.
+    let path = path
+        .strip_prefix(&cwd)
+        .map_or(path.clone(), Path::to_path_buf);
+    match Config::read(&path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("`{}` {e}", path.display());
+            process::exit(1);
+        }
+    }
+}
+
+/// Writes the html of the maps at `files` and the stylesheet at `theme`, followed by the
+/// rules of `highlight_theme`, to the zip archive `output`.
+/// Exits the process if the stylesheet cannot be read or the archive cannot be written.
+#[cfg(feature = "zip")]
+fn write_archive(
+    log: Logger,
+    files: Vec<PathBuf>,
+    theme: &Path,
+    highlight_theme: HighlightTheme,
+    output: &Path,
+) {
+    let stylesheet = match std::fs::read_to_string(theme) {
+        Ok(stylesheet) => stylesheet,
+        Err(e) => {
+            log.error(format!("Could not read `{}`.\n{e}", theme.display()));
+            process::exit(1);
+        }
+    };
+    let mut maps = vec![];
+    for path in files {
+        match lexer::lex(&path) {
+            Ok(tokens) => {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                log.info(format!("`{}` -> `{}`", path.display(), output.display()));
+                maps.push((name, AnnotatedFile::annotate_owned(tokens)));
+            }
+            Err(e) => log.error(format!("Could not read `{}`.\n{e}", path.display())),
+        }
+    }
+    let maps: Vec<(&str, &AnnotatedFile)> = maps
+        .iter()
+        .map(|(name, annotated_file)| (&name[..], annotated_file))
+        .collect();
+    let options = html_writer::HtmlOptions {
+        highlight_theme,
+        ..html_writer::HtmlOptions::default()
+    };
+    if let Err(e) = aoe2_rms::archive::write_zip_archive(&maps, &stylesheet, output, &options) {
+        log.error(format!("Could not write `{}`.\n{e}", output.display()));
+        process::exit(1);
+    }
+}
+
+/// Reports that writing a zip archive requires the `zip` feature, and exits the process.
+#[cfg(not(feature = "zip"))]
+fn write_archive(
+    log: Logger,
+    _files: Vec<PathBuf>,
+    _theme: &Path,
+    _highlight_theme: HighlightTheme,
+    _output: &Path,
+) {
+    log.error("`--zip` requires building with the `zip` feature.");
+    process::exit(1);
+}
+
+/// How long a map must go unchanged before `--watch` re-renders it, so that the rapid
+/// successive events of a single save render the map once.
+#[cfg(feature = "notify")]
+const WATCH_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Writes the stylesheet at `theme` to `css_path`, followed by the rules of
+/// `highlight_theme` and the rules highlighting the comments numbered below
+/// `num_comments`.
+#[cfg(feature = "notify")]
+fn write_stylesheet(
+    theme: &Path,
+    highlight_theme: HighlightTheme,
+    css_path: &Path,
+    num_comments: usize,
+) -> std::io::Result<()> {
+    std::fs::copy(theme, css_path)?;
+    let mut css_file = OpenOptions::new().append(true).open(css_path)?;
+    write!(
+        css_file,
+        "{}{}",
+        highlight_theme.css(),
+        html_writer::comment_highlight_css(num_comments)
+    )
+}
+
+/// Watches `maps_dir`, rendering each changed map to `out_dir` and printing how long it
+/// took. The stylesheet at `theme`, followed by the rules of `highlight_theme`, is
+/// rewritten when a map has more comments than the `max_comments` of the maps already
+/// rendered.
+/// Runs until the watch fails, then exits the process.
+#[cfg(feature = "notify")]
+fn watch_maps(
+    log: Logger,
+    maps_dir: &Path,
+    out_dir: &Path,
+    theme: &Path,
+    highlight_theme: HighlightTheme,
+    max_comments: usize,
+) {
+    let mut max_comments = max_comments;
+    log.info(format!("Watching `{}` for changes.", maps_dir.display()));
+    let result = aoe2_rms::watch::watch(maps_dir, WATCH_DELAY, |path| {
+        if !path.is_file() {
+            return;
+        }
+        let start = Instant::now();
+        let tokens = match lexer::lex(path) {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                log.error(format!("Could not read `{}`.\n{e}", path.display()));
+                return;
+            }
+        };
+        let annotated_file = AnnotatedFile::annotate_owned(tokens);
+        let mut output = out_dir.join(path.file_name().unwrap());
+        output.set_extension("html");
+        if let Err(e) = html_writer::write_annotated_debug_file(&annotated_file, &output) {
+            log.error(format!("Could not write `{}`.\n{e}", output.display()));
+            return;
+        }
+        if annotated_file.num_comments() > max_comments {
+            max_comments = annotated_file.num_comments();
+            let css_path = out_dir.join("style.css");
+            if let Err(e) = write_stylesheet(theme, highlight_theme, &css_path, max_comments) {
+                log.error(format!("Could not write `{}`.\n{e}", css_path.display()));
+            }
+        }
+        // The watcher reports absolute paths, so the path is shown within `maps_dir`.
+        log.info(format!(
+            "rebuilt `{}` in {}ms",
+            maps_dir.join(path.file_name().unwrap()).display(),
+            start.elapsed().as_millis()
+        ));
+    });
+    if let Err(e) = result {
+        log.error(format!("Could not watch `{}`.\n{e}", maps_dir.display()));
+    }
+    process::exit(1);
+}
+
+/// Reports that watching requires the `notify` feature, and exits the process.
+#[cfg(not(feature = "notify"))]
+fn watch_maps(
+    log: Logger,
+    _maps_dir: &Path,
+    _out_dir: &Path,
+    _theme: &Path,
+    _highlight_theme: HighlightTheme,
+    _max_comments: usize,
+) {
+    log.error("`--watch` requires building with the `notify` feature.");
+    process::exit(1);
+}
 
 /// Runs the application to transform a map script to a html file.
 /// Accepts as input the names of the files in the `maps` folder to transform.
@@ -21,90 +281,557 @@ use aoe2_rms::{annotater::AnnotatedFile, html_writer, lexer};
 /// All maps must be directly in the `maps` folder, nesting in subdirectories
 /// is not supported.
 ///
+/// The `out` folder is created if it does not exist.
 /// Copies the `style/style.css` file to `out`.
 /// If the `style/style.css` folder is missing, an error message is printed to
 /// standard error and no files are transformed.
+///
+/// The following flags control how much is printed to standard error:
+///
+/// - `--quiet`: prints only errors.
+/// - `--verbose`: additionally prints the timings and counts of each file.
+///
+/// By default, errors and a one-line summary of each file are printed.
+///
+/// With the `--combined` flag, all of the maps are written to the single file
+/// `out/index.html` instead of to one file per map.
+///
+/// With the `--incremental` flag, maps that have not been modified since they were
+/// last rendered are skipped. The rendered maps are recorded in `out/manifest.tsv`.
+/// The flag has no effect when combined with `--combined`.
+///
+/// With the `--highlight-theme <name>` option, the rules of a built-in highlight theme
+/// are appended to the copied stylesheet, overriding its colors. The `game-editor` theme
+/// approximates the colors of the in-game editor. The default is `default`, which
+/// appends no rules.
+///
+/// With the `--zip <path>` option, the maps and the stylesheet are written to a single
+/// zip archive at `path` instead of to the `out` folder. Each map is written to the entry
+/// of its name with an `.html` extension, beside the `style.css` entry. The option
+/// requires the `zip` feature.
+///
+/// With the `--profile` flag, the time taken to lex, annotate, and write each map is
+/// printed to standard output, with each stage's share of the total, at every verbosity.
+/// The flag cannot be combined with `--combined` or `--zip`, which write the maps
+/// together.
+///
+/// With the `--watch` flag, after the maps are transformed, the `maps` folder is watched
+/// and each map that changes is transformed again, printing a line with the time taken.
+/// The flag requires the `notify` feature and cannot be combined with `--combined`.
+///
+/// With the `--tokens` flag, no html is written. Instead, every lexeme of each map is
+/// printed to standard output, one per line, prefixed by the path of its map.
+///
+/// With the `--comments` flag, no html is written. Instead, the text of the comments
+/// of each map is printed to standard output, preceded by a Markdown heading of the
+/// map's path.
+///
+/// With the `--stats` flag, no html is written. Instead, a summary of the highlighting
+/// and comment matching of each map is printed to standard output.
+///
+/// With the `--check` flag, no html is written. Instead, the checks are run on each map
+/// and the diagnostics are printed to standard output, each prefixed by the path of its
+/// map. With `--format jsonl`, each diagnostic is instead printed as a JSON object on its
+/// own line, and the output is flushed after each map. With `--format github`, each
+/// diagnostic is printed as a GitHub Actions workflow command, so that it is shown inline
+/// on the map in a pull request. With `--format pretty`, each diagnostic is followed by
+/// its source line with a caret underline beneath the problem. With `--format sarif`,
+/// the diagnostics of every map are printed as a single SARIF 2.1.0 log, for code
+/// scanning tools, once every map has been checked. The default is `--format text`.
+/// With `--fail-on <severity>`, where the severity is `error`, `warning`, `info`, or
+/// `never`, the application exits with status 1 if any diagnostic is at least as severe.
+/// The default is `--fail-on error`. With the `--strict-comments` flag, an unmatched
+/// comment delimiter is reported as an error rather than a warning, failing the check.
+///
+/// With the `--coverage <label type>` option, no html is written. Instead, for each map,
+/// the built-in labels of the type that are handled and missing from its conditions are
+/// printed to standard output. The label type is named as in the data file, for example
+/// `--coverage MapSizeModern`.
+///
+/// With the `--validate` flag, no html is written. Instead, every file in the `maps`
+/// folder is checked to be text that can be lexed, and a report of the valid and
+/// invalid files is printed to standard output. Exits with status 1 if any file is
+/// invalid.
+///
+/// Defaults may be set in an `.rms-tools.toml` file in the working directory or one of
+/// its parents: the `maps_dir` and `out_dir` to use in place of the `maps` and `out`
+/// folders, the `theme` stylesheet to use in place of `style/style.css`, the `lints`
+/// whose codes are reported by `--check`, and the `fail_on` severity.
+/// Flags given on the command line override the file.
 fn main() {
+    let config = read_config();
+    let maps_dir = config.maps_dir().unwrap_or_else(|| PathBuf::from("maps"));
+    let out_dir = config.out_dir().unwrap_or_else(|| PathBuf::from("out"));
+    let theme = config
+        .theme()
+        .unwrap_or_else(|| PathBuf::from("style/style.css"));
     // Skips the first argument, which is always present.
-    let args = std::env::args().skip(1);
+    let mut arguments = std::env::args().skip(1);
+    let mut args = vec![];
+    let mut verbosity = Verbosity::Normal;
+    let mut combined = false;
+    let mut incremental = false;
+    let mut tokens_dump = false;
+    let mut comments_only = false;
+    let mut stats = false;
+    let mut validate = false;
+    let mut check = false;
+    let mut format = OutputFormat::Text;
+    let mut coverage: Option<LabelType> = None;
+    // The least severe diagnostic that causes a nonzero exit, or `None` to never fail.
+    let mut fail_on = match config.fail_on().map(parse_fail_on) {
+        Some(Ok(fail_on)) => fail_on,
+        Some(Err(e)) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+        None => Some(Severity::Error),
+    };
+    let mut zip_output: Option<PathBuf> = None;
+    let mut watch = false;
+    let mut profile = false;
+    let mut strict_comments = false;
+    let mut highlight_theme = HighlightTheme::Default;
+    while let Some(flag) = arguments.next() {
+        match &flag[..] {
+            _ if !flag.starts_with("--") => args.push(flag),
+            "--check" => check = true,
+            "--combined" => combined = true,
+            "--comments" => comments_only = true,
+            "--coverage" => match arguments.next().as_deref().map(str::parse) {
+                Some(Ok(label_type)) => coverage = Some(label_type),
+                Some(Err(e)) => {
+                    eprintln!("{e}");
+                    process::exit(1);
+                }
+                None => {
+                    eprintln!("`--coverage` requires a label type, such as `MapSizeModern`.");
+                    process::exit(1);
+                }
+            },
+            "--zip" => match arguments.next() {
+                Some(path) => zip_output = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("`--zip` requires the path of the archive to write.");
+                    process::exit(1);
+                }
+            },
+            "--fail-on" => match arguments.next().as_deref().map(parse_fail_on) {
+                Some(Ok(severity)) => fail_on = severity,
+                Some(Err(e)) => {
+                    eprintln!("{e}");
+                    process::exit(1);
+                }
+                None => {
+                    eprintln!("`--fail-on` requires a severity, such as `warning`.");
+                    process::exit(1);
+                }
+            },
+            "--format" => match arguments.next().as_deref() {
+                Some("text") => format = OutputFormat::Text,
+                Some("jsonl") => format = OutputFormat::Jsonl,
+                Some("github") => format = OutputFormat::Github,
+                Some("pretty") => format = OutputFormat::Pretty,
+                Some("sarif") => format = OutputFormat::Sarif,
+                Some(other) => {
+                    eprintln!(
+                        "Unknown format `{other}`, expected `text`, `jsonl`, `github`, `pretty`, or `sarif`."
+                    );
+                    process::exit(1);
+                }
+                None => {
+                    eprintln!(
+                        "`--format` requires a format, `text`, `jsonl`, `github`, `pretty`, or `sarif`."
+                    );
+                    process::exit(1);
+                }
+            },
+            "--explain" => match arguments.next() {
+                Some(name) => match explain::explanation(&name) {
+                    Some(explanation) => {
+                        print!("{explanation}");
+                        process::exit(0);
+                    }
+                    None => {
+                        eprintln!(
+                            "Unknown diagnostic code `{name}`, expected an id such as `RMS001` or a code such as `unknown-label`."
+                        );
+                        process::exit(1);
+                    }
+                },
+                None => {
+                    eprintln!("`--explain` requires a diagnostic code, such as `RMS001`.");
+                    process::exit(1);
+                }
+            },
+            "--highlight-theme" => match arguments.next().as_deref() {
+                Some(name) => match HighlightTheme::from_name(name) {
+                    Some(theme) => highlight_theme = theme,
+                    None => {
+                        eprintln!(
+                            "Unknown highlight theme `{name}`, expected `default` or `game-editor`."
+                        );
+                        process::exit(1);
+                    }
+                },
+                None => {
+                    eprintln!("`--highlight-theme` requires a theme, such as `game-editor`.");
+                    process::exit(1);
+                }
+            },
+            "--incremental" => incremental = true,
+            "--profile" => profile = true,
+            "--quiet" => verbosity = Verbosity::Quiet,
+            "--stats" => stats = true,
+            "--strict-comments" => strict_comments = true,
+            "--tokens" => tokens_dump = true,
+            "--validate" => validate = true,
+            "--verbose" => verbosity = Verbosity::Verbose,
+            "--watch" => watch = true,
+            _ => {
+                eprintln!("Unknown flag `{flag}`.");
+                process::exit(1);
+            }
+        }
+    }
+    let log = Logger { verbosity };
+    if watch && combined {
+        log.error("`--watch` cannot be combined with `--combined`.");
+        process::exit(1);
+    }
+    if profile && (combined || zip_output.is_some()) {
+        log.error("`--profile` cannot be combined with `--combined` or `--zip`.");
+        process::exit(1);
+    }
+
+    if validate {
+        let report = match validate::validate_dir(&maps_dir) {
+            Ok(report) => report,
+            Err(e) => {
+                log.error(format!(
+                    "Could not read the `{}` directory.\n{e}",
+                    maps_dir.display()
+                ));
+                process::exit(1);
+            }
+        };
+        let mut all_valid = true;
+        for (path, result) in report {
+            match result {
+                Ok(()) => println!("valid: `{}`", path.display()),
+                Err(reason) => {
+                    all_valid = false;
+                    println!("invalid: `{}` {reason}", path.display());
+                }
+            }
+        }
+        process::exit(if all_valid { 0 } else { 1 });
+    }
+
     let mut files = vec![];
-    if args.len() == 0 {
-        for result in std::fs::read_dir("maps/").unwrap() {
+    if args.is_empty() {
+        let entries = match std::fs::read_dir(&maps_dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log.error(format!("Could not read `{}`.\n{e}", maps_dir.display()));
+                process::exit(1);
+            }
+        };
+        for result in entries {
             match result {
                 Ok(entry) => {
                     if entry.path().is_file() {
                         files.push(entry.path())
                     }
                 }
-                Err(e) => eprintln!("{e}"),
+                Err(e) => log.error(e),
             }
         }
     } else {
         for arg in args {
-            let mut path = PathBuf::with_capacity(2);
-            path.push("maps");
-            path.push(arg);
+            let mut path = maps_dir.join(arg);
             if path.is_file() {
                 files.push(path);
             } else {
-                eprintln!("`{}` is not an existing file.", path.display());
+                log.error(format!("`{}` is not an existing file.", path.display()));
                 path.set_extension("rms");
                 if path.is_file() {
-                    eprintln!("Did you mean `{}`?", path.display());
+                    log.error(format!("Did you mean `{}`?", path.display()));
+                }
+            }
+        }
+    }
+
+    if comments_only {
+        for path in files {
+            match lexer::lex(&path) {
+                Ok(tokens) => {
+                    let text = export::comment_text(&AnnotatedFile::annotate_owned(tokens));
+                    println!("# {}\n\n{text}\n", path.display());
+                }
+                Err(e) => log.error(format!("Could not read `{}`.\n{e}", path.display())),
+            }
+        }
+        return;
+    }
+
+    if check {
+        let check_options = CheckOptions {
+            annotate_options: AnnotateOptions { strict_comments },
+            ..CheckOptions::default()
+        };
+        let mut stdout = std::io::stdout().lock();
+        let mut failed = false;
+        // The paths and diagnostics of the maps, written together as a SARIF log.
+        let mut sarif_files = vec![];
+        for path in files {
+            let source = match SourceFile::from_path(&path) {
+                Ok(source) => source,
+                Err(e) => {
+                    log.error(format!("Could not read `{}`.\n{e}", path.display()));
+                    continue;
+                }
+            };
+            let mut diagnostics =
+                checker::check(&AnnotatedFile::annotate(source.lexemes()), &check_options);
+            diagnostics.retain(|d| config.lint_enabled(d.code()));
+            failed |= diagnostics
+                .iter()
+                .any(|d| fail_on.is_some_and(|severity| d.severity() >= severity));
+            let result = match format {
+                OutputFormat::Text => diagnostics
+                    .iter()
+                    .try_for_each(|d| writeln!(stdout, "{}:{d}", path.display())),
+                OutputFormat::Github => diagnostics.iter().try_for_each(|d| {
+                    // Workflow commands expect forward slashes on every platform.
+                    let path = path.to_string_lossy().replace('\\', "/");
+                    writeln!(stdout, "{}", export::github_annotation(&path, d))
+                }),
+                OutputFormat::Pretty => diagnostics.iter().try_for_each(|d| {
+                    writeln!(stdout, "{}", diagnostics::render_snippet(&source, d))
+                }),
+                OutputFormat::Jsonl => export::write_diagnostics_jsonl(
+                    &mut stdout,
+                    &path.to_string_lossy(),
+                    &diagnostics,
+                ),
+                OutputFormat::Sarif => {
+                    // Artifact uris use forward slashes on every platform.
+                    sarif_files.push((path.to_string_lossy().replace('\\', "/"), diagnostics));
+                    Ok(())
+                }
+            };
+            if let Err(e) = result {
+                log.error(format!("Could not write diagnostics.\n{e}"));
+                process::exit(1);
+            }
+        }
+        if format == OutputFormat::Sarif {
+            let sarif_files: Vec<(&str, &[_])> = sarif_files
+                .iter()
+                .map(|(path, diagnostics)| (&path[..], &diagnostics[..]))
+                .collect();
+            if let Err(e) = writeln!(stdout, "{}", export::diagnostics_sarif(&sarif_files)) {
+                log.error(format!("Could not write diagnostics.\n{e}"));
+                process::exit(1);
+            }
+        }
+        process::exit(if failed { 1 } else { 0 });
+    }
+
+    if let Some(label_type) = coverage {
+        for path in files {
+            match lexer::lex(&path) {
+                Ok(tokens) => {
+                    let coverage = checker::label_coverage(
+                        &AnnotatedFile::annotate_owned(tokens),
+                        &label_type,
+                    );
+                    println!("{}: {coverage}", path.display());
                 }
+                Err(e) => log.error(format!("Could not read `{}`.\n{e}", path.display())),
             }
         }
+        return;
+    }
+
+    if stats {
+        for path in files {
+            match lexer::lex(&path) {
+                Ok(tokens) => {
+                    let stats = AnnotatedFile::annotate_owned(tokens).stats();
+                    println!("{}: {stats}", path.display());
+                }
+                Err(e) => log.error(format!("Could not read `{}`.\n{e}", path.display())),
+            }
+        }
+        return;
+    }
+
+    if tokens_dump {
+        for path in files {
+            match lexer::lex(&path) {
+                Ok(tokens) => {
+                    let dump = export::token_dump(&AnnotatedFile::annotate_owned(tokens));
+                    for line in dump.lines() {
+                        println!("{}:{line}", path.display());
+                    }
+                }
+                Err(e) => log.error(format!("Could not read `{}`.\n{e}", path.display())),
+            }
+        }
+        return;
+    }
+
+    if let Some(output) = zip_output {
+        write_archive(log, files, &theme, highlight_theme, &output);
+        return;
+    }
+
+    // Creates the output directory, which does not exist in a fresh clone.
+    if let Err(e) = std::fs::create_dir_all(&out_dir) {
+        log.error(format!(
+            "Could not create the `{}` directory.\n{e}",
+            out_dir.display()
+        ));
+        process::exit(1);
     }
 
     // Copies the style CSS file.
-    if let Err(e) = std::fs::copy("style/style.css", "out/style.css") {
-        eprintln!("Could not copy `style/style.css` to `out`.\n{e}");
+    let css_path = out_dir.join("style.css");
+    if let Err(e) = std::fs::copy(&theme, &css_path) {
+        log.error(format!(
+            "Could not copy `{}` to `{}`.\n{e}",
+            theme.display(),
+            out_dir.display()
+        ));
         process::exit(1);
     }
 
     // Transforms the map files.
     let mut max_comments = 0;
+    // The names and annotations of the maps written to the combined file.
+    let mut combined_maps = vec![];
+    let combined_path = out_dir.join("index.html");
+    let manifest_path = out_dir.join("manifest.tsv");
+    let mut manifest = if incremental && !combined {
+        Manifest::read(&manifest_path)
+    } else {
+        Manifest::default()
+    };
     for path in files {
-        let tokens = match lexer::lex(&path) {
-            Ok(ts) => ts,
-            Err(e) => {
-                eprintln!("{e}");
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let mut pb = out_dir.join(&name);
+        pb.set_extension("html");
+        if incremental && !combined && pb.is_file() {
+            if let Some(num_comments) = manifest.unchanged(&name, &path) {
+                max_comments = max_comments.max(num_comments);
+                log.verbose(format!("`{}` is unchanged, skipping.", path.display()));
                 continue;
             }
+        }
+        let annotated_file = if profile {
+            match Pipeline::new().render_to_timed(&path, &pb) {
+                Ok((annotated_file, timings)) => {
+                    println!("profile `{}`: {timings}", path.display());
+                    annotated_file
+                }
+                Err(e) => {
+                    log.error(format!(
+                        "Could not render `{}` to `{}`.\n{e}",
+                        path.display(),
+                        pb.display()
+                    ));
+                    continue;
+                }
+            }
+        } else {
+            let tokens = match lexer::lex(&path) {
+                Ok(ts) => ts,
+                Err(e) => {
+                    log.error(format!("Could not read `{}`.\n{e}", path.display()));
+                    continue;
+                }
+            };
+            let annotated_file = AnnotatedFile::annotate_owned(tokens);
+            if !combined {
+                if let Err(e) = html_writer::write_annotated_debug_file(&annotated_file, &pb) {
+                    log.error(format!("Could not write `{}`.\n{e}", pb.display()));
+                    continue;
+                }
+            }
+            annotated_file
         };
-        let mut pb = PathBuf::from("out");
-        pb.push(path.file_name().unwrap());
-        pb.set_extension("html");
-        let annotated_file = AnnotatedFile::annotate(&tokens);
         max_comments = max_comments.max(annotated_file.num_comments());
-        if let Err(e) = html_writer::write_annotated_debug_file(&annotated_file, &pb) {
-            println!("{e}");
+        if combined {
+            log.info(format!(
+                "`{}` -> `{}`",
+                path.display(),
+                combined_path.display()
+            ));
+            combined_maps.push((name, annotated_file));
+            continue;
         }
+        manifest.record(&name, &path, annotated_file.num_comments());
+        log.info(format!("`{}` -> `{}`", path.display(), pb.display()));
+        log.verbose(format!(
+            "  {} lexemes, {} matched comments",
+            annotated_file.tokens().len(),
+            annotated_file.num_comments(),
+        ));
     }
 
-    // Writes comment match highlight classes to the copied css file.
-    let mut css_file = match OpenOptions::new().append(true).open("out/style.css") {
-        Ok(file) => file,
-        Err(e) => {
-            eprintln!("Could not open output css file.\n{e}");
-            process::exit(1);
+    if incremental && !combined {
+        if let Err(e) = manifest.write(&manifest_path) {
+            log.error(format!(
+                "Could not write `{}`.\n{e}",
+                manifest_path.display()
+            ));
         }
-    };
-    if max_comments > 0 {
-        // Writes a blank line before the comments.
-        if let Err(e) = writeln!(css_file, "") {
-            eprintln!("Could not write to output css file.\n{e}");
+    }
+
+    if combined {
+        let maps: Vec<(&str, &AnnotatedFile)> = combined_maps
+            .iter()
+            .map(|(name, annotated_file)| (&name[..], annotated_file))
+            .collect();
+        let options = html_writer::HtmlOptions::default();
+        if let Err(e) = html_writer::write_combined_debug_file(&maps, &combined_path, &options) {
+            log.error(format!(
+                "Could not write `{}`.\n{e}",
+                combined_path.display()
+            ));
             process::exit(1);
         }
     }
-    for i in 0..max_comments {
-        if let Err(e) = writeln!(
-            css_file,
-            ":has(.comment-{i}:hover) .comment-{i} {{\n  background-color: #5f5f5f;\n}}\n"
-        ) {
-            eprintln!("Could not write to output css file.\n{e}");
+
+    // Writes the highlight theme and comment match highlight classes to the copied css file.
+    let mut css_file = match OpenOptions::new().append(true).open(&css_path) {
+        Ok(file) => file,
+        Err(e) => {
+            log.error(format!("Could not open output css file.\n{e}"));
             process::exit(1);
         }
+    };
+    if let Err(e) = write!(
+        css_file,
+        "{}{}",
+        highlight_theme.css(),
+        html_writer::comment_highlight_css(max_comments)
+    ) {
+        log.error(format!("Could not write to output css file.\n{e}"));
+        process::exit(1);
+    }
+
+    if watch {
+        watch_maps(
+            log,
+            &maps_dir,
+            &out_dir,
+            &theme,
+            highlight_theme,
+            max_comments,
+        );
     }
 
     // TODO write css classes for matching curly braces, if statements, and random blocks.
@@ -0,0 +1,144 @@
+//! A manifest of previously rendered map files, used to skip rendering unchanged files.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+/// The information recorded about a map file when it was rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ManifestEntry {
+    /// The modification time of the source file, in nanoseconds since the Unix epoch.
+    modified: u128,
+    /// The number of matched comments in the file.
+    num_comments: usize,
+}
+
+/// The files rendered by a previous run, keyed by their file names.
+///
+/// The manifest is stored as a text file with one line per map file,
+/// consisting of the file name, modification time, and number of comments,
+/// separated by tabs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    entries: HashMap<String, ManifestEntry>,
+}
+
+/// Returns the modification time of the file at `path` in nanoseconds since the Unix
+/// epoch, or `None` if the time cannot be read.
+fn modified_nanos(path: &Path) -> Option<u128> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos())
+}
+
+impl Manifest {
+    /// Reads the manifest at `path`.
+    /// Returns an empty manifest if the file does not exist. Malformed lines are ignored.
+    pub fn read(path: &Path) -> Self {
+        let mut entries = HashMap::new();
+        let Ok(text) = fs::read_to_string(path) else {
+            return Self { entries };
+        };
+        for line in text.lines() {
+            let mut fields = line.split('\t');
+            let (Some(name), Some(modified), Some(num_comments), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            if let (Ok(modified), Ok(num_comments)) = (modified.parse(), num_comments.parse()) {
+                let entry = ManifestEntry {
+                    modified,
+                    num_comments,
+                };
+                entries.insert(String::from(name), entry);
+            }
+        }
+        Self { entries }
+    }
+
+    /// Writes the manifest to `path`, overwriting the file if it exists.
+    /// Returns an io error if the writing fails.
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let mut names: Vec<&String> = self.entries.keys().collect();
+        names.sort();
+        let mut f = File::create(path)?;
+        for name in names {
+            let entry = self.entries[name];
+            writeln!(f, "{name}\t{}\t{}", entry.modified, entry.num_comments)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the recorded number of comments of the map file `name` if the file at
+    /// `source` has not been modified since it was recorded.
+    /// Returns `None` if the file was modified or was never recorded.
+    pub fn unchanged(&self, name: &str, source: &Path) -> Option<usize> {
+        let entry = self.entries.get(name)?;
+        (modified_nanos(source)? == entry.modified).then_some(entry.num_comments)
+    }
+
+    /// Records that the map file `name`, located at `source`, has been rendered
+    /// with `num_comments` matched comments.
+    /// Nothing is recorded if the modification time of `source` cannot be read.
+    pub fn record(&mut self, name: &str, source: &Path, num_comments: usize) {
+        if let Some(modified) = modified_nanos(source) {
+            let entry = ManifestEntry {
+                modified,
+                num_comments,
+            };
+            self.entries.insert(String::from(name), entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        time::{Duration, SystemTime},
+    };
+
+    use super::*;
+
+    /// Tests that touching one source file marks only that file as changed,
+    /// including after the manifest is written and read again.
+    #[test]
+    fn touch_one_file() {
+        let dir = std::env::temp_dir().join("aoe2-rms-manifest-test");
+        fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("first.rms");
+        let second = dir.join("second.rms");
+        fs::write(&first, "<PLAYER_SETUP>").unwrap();
+        fs::write(&second, "<LAND_GENERATION>").unwrap();
+        let past = SystemTime::now() - Duration::from_secs(60);
+        for path in [&first, &second] {
+            File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(past)
+                .unwrap();
+        }
+
+        let mut manifest = Manifest::default();
+        manifest.record("first.rms", &first, 1);
+        manifest.record("second.rms", &second, 2);
+        let manifest_path: PathBuf = dir.join("manifest.tsv");
+        manifest.write(&manifest_path).unwrap();
+
+        File::options()
+            .write(true)
+            .open(&second)
+            .unwrap()
+            .set_modified(SystemTime::now())
+            .unwrap();
+        let manifest = Manifest::read(&manifest_path);
+        assert_eq!(manifest.unchanged("first.rms", &first), Some(1));
+        assert_eq!(manifest.unchanged("second.rms", &second), None);
+        assert_eq!(manifest.unchanged("third.rms", &first), None);
+    }
+}
@@ -0,0 +1,253 @@
+//! A single entry point for lexing, annotating, and rendering a map script,
+//! configured with a builder.
+//!
+//! ```no_run
+//! use aoe2_rms::pipeline::Pipeline;
+//!
+//! let html = Pipeline::new()
+//!     .source_map(true)
+//!     .render_file("maps/example.rms".as_ref())
+//!     .unwrap();
+//! ```
+
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    annotater::AnnotatedFile,
+    html_writer::{self, HighlightTheme, HtmlOptions},
+    lexer::{self, LexOptions},
+};
+
+/// The time taken by each stage of rendering a map script, for finding which stage
+/// dominates when a large file renders slowly.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StageTimings {
+    /// The time taken to lex the map script.
+    pub lex: Duration,
+    /// The time taken to annotate the lexemes.
+    pub annotate: Duration,
+    /// The time taken to write the html.
+    pub write: Duration,
+}
+
+impl StageTimings {
+    /// Returns the total time taken by every stage.
+    pub fn total(&self) -> Duration {
+        self.lex + self.annotate + self.write
+    }
+}
+
+impl Display for StageTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total = self.total();
+        let percent = |stage: Duration| {
+            if total.is_zero() {
+                0.0
+            } else {
+                100.0 * stage.as_secs_f64() / total.as_secs_f64()
+            }
+        };
+        write!(
+            f,
+            "lex {:?} ({:.0}%), annotate {:?} ({:.0}%), write {:?} ({:.0}%), total {total:?}",
+            self.lex,
+            percent(self.lex),
+            self.annotate,
+            percent(self.annotate),
+            self.write,
+            percent(self.write),
+        )
+    }
+}
+
+/// Configuration of the steps that transform a map script into a html file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Pipeline {
+    /// The options used to lex the map script.
+    lex_options: LexOptions,
+    /// The options used to render the annotated file.
+    html_options: HtmlOptions,
+    /// Whether to record the path of each rendered file in its html.
+    provenance: bool,
+}
+
+impl Pipeline {
+    /// Returns a new pipeline using the default options of each step.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets all of the options used to lex the map script.
+    pub fn lex_options(mut self, lex_options: LexOptions) -> Self {
+        self.lex_options = lex_options;
+        self
+    }
+
+    /// Sets whether a file ending with a line break has an explicit empty final line.
+    pub fn explicit_final_line(mut self, explicit_final_line: bool) -> Self {
+        self.lex_options.explicit_final_line = explicit_final_line;
+        self
+    }
+
+    /// Sets all of the options used to render the annotated file.
+    pub fn html_options(mut self, html_options: HtmlOptions) -> Self {
+        self.html_options = html_options;
+        self
+    }
+
+    /// Sets whether to embed a source map in the rendered html.
+    pub fn source_map(mut self, source_map: bool) -> Self {
+        self.html_options.source_map = source_map;
+        self
+    }
+
+    /// Sets the number of columns after which rendered lines wrap,
+    /// or `None` to not wrap lines.
+    pub fn wrap_columns(mut self, wrap_columns: Option<usize>) -> Self {
+        self.html_options.wrap_columns = wrap_columns;
+        self
+    }
+
+    /// Sets whether to add accessibility attributes to the rendered html.
+    pub fn accessible(mut self, accessible: bool) -> Self {
+        self.html_options.accessible = accessible;
+        self
+    }
+
+    /// Sets the maximum number of lines to render, or `None` to render every line.
+    pub fn max_lines(mut self, max_lines: Option<usize>) -> Self {
+        self.html_options.max_lines = max_lines;
+        self
+    }
+
+    /// Sets the highlight colors of the html rendered from a string, whose stylesheet is
+    /// inlined.
+    pub fn highlight_theme(mut self, highlight_theme: HighlightTheme) -> Self {
+        self.html_options.highlight_theme = highlight_theme;
+        self
+    }
+
+    /// Sets whether to mark tabs and spaces with visible glyphs in the rendered html.
+    pub fn show_whitespace(mut self, show_whitespace: bool) -> Self {
+        self.html_options.show_whitespace = show_whitespace;
+        self
+    }
+
+    /// Sets whether to show token information as compact `title` tooltips in the rendered
+    /// html instead of cards.
+    pub fn compact_cards(mut self, compact_cards: bool) -> Self {
+        self.html_options.compact_cards = compact_cards;
+        self
+    }
+
+    /// Sets whether to mark the `/*` and `*/` within comments other than their outermost
+    /// delimiters in the rendered html.
+    pub fn mark_interior_delimiters(mut self, mark_interior_delimiters: bool) -> Self {
+        self.html_options.mark_interior_delimiters = mark_interior_delimiters;
+        self
+    }
+
+    /// Sets the number of the first rendered line, such as the line of the original file
+    /// at which a snippet starts.
+    pub fn start_line(mut self, start_line: usize) -> Self {
+        self.html_options.start_line = start_line;
+        self
+    }
+
+    /// Sets whether to record the path of each rendered file, the time of generation, and
+    /// the version of this crate in a comment at the top of the rendered html.
+    /// Rendering a string records no path.
+    pub fn provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Returns the options for rendering the map script at `path`, recording its path
+    /// if provenance is enabled.
+    fn html_options_for(&self, path: &Path) -> Cow<'_, HtmlOptions> {
+        if !self.provenance {
+            return Cow::Borrowed(&self.html_options);
+        }
+        Cow::Owned(HtmlOptions {
+            provenance: Some(path.display().to_string()),
+            ..self.html_options.clone()
+        })
+    }
+
+    /// Lexes and annotates the map script at `path`.
+    /// Returns an IO error if there is an error reading the file.
+    pub fn annotate_file(&self, path: &Path) -> io::Result<AnnotatedFile> {
+        let lexemes = lexer::lex_with_options(path, &self.lex_options)?;
+        Ok(AnnotatedFile::annotate_owned(lexemes))
+    }
+
+    /// Lexes, annotates, and renders the map script at `path`, returning the html.
+    /// Returns an IO error if there is an error reading the file.
+    pub fn render_file(&self, path: &Path) -> io::Result<String> {
+        let mut html = vec![];
+        self.write_html(path, &mut html)?;
+        // The html writer only writes valid UTF-8.
+        Ok(String::from_utf8(html).expect("Rendered html must be valid UTF-8."))
+    }
+
+    /// Lexes, annotates, and renders the map script `source`, returning a self-contained
+    /// html document with the stylesheet inlined. No files are read or written.
+    pub fn render_string(&self, source: &str) -> String {
+        let lexemes = lexer::lex_str_with_options(source, &self.lex_options);
+        let annotated_file = AnnotatedFile::annotate_owned(lexemes);
+        let mut html = vec![];
+        html_writer::write_standalone_html(&mut html, &annotated_file, &self.html_options)
+            .expect("Writing to a vector cannot fail.");
+        // The html writer only writes valid UTF-8.
+        String::from_utf8(html).expect("Rendered html must be valid UTF-8.")
+    }
+
+    /// Lexes, annotates, and renders the map script at `path`, writing the html to `output`.
+    /// If a file already exists at `output`, it is overwritten.
+    /// Returns an IO error if there is an error reading `path` or writing `output`.
+    pub fn render_to(&self, path: &Path, output: &Path) -> io::Result<()> {
+        let mut f = File::create(output)?;
+        self.write_html(path, &mut f)
+    }
+
+    /// Lexes, annotates, and renders the map script at `path`, writing the html to `output`,
+    /// and returns the annotated file with the time taken by each stage.
+    /// If a file already exists at `output`, it is overwritten.
+    /// Returns an IO error if there is an error reading `path` or writing `output`.
+    pub fn render_to_timed(
+        &self,
+        path: &Path,
+        output: &Path,
+    ) -> io::Result<(AnnotatedFile, StageTimings)> {
+        let start = Instant::now();
+        let lexemes = lexer::lex_with_options(path, &self.lex_options)?;
+        let lex = start.elapsed();
+        let annotated_file = AnnotatedFile::annotate_owned(lexemes);
+        let annotate = start.elapsed() - lex;
+        html_writer::write_annotated_debug_file_with_options(
+            &annotated_file,
+            output,
+            &self.html_options_for(path),
+        )?;
+        let write = start.elapsed() - lex - annotate;
+        let timings = StageTimings {
+            lex,
+            annotate,
+            write,
+        };
+        Ok((annotated_file, timings))
+    }
+
+    /// Lexes, annotates, and renders the map script at `path`, writing the html to `f`.
+    fn write_html(&self, path: &Path, f: &mut impl Write) -> io::Result<()> {
+        let annotated_file = self.annotate_file(path)?;
+        html_writer::write_annotated_html(f, &annotated_file, &self.html_options_for(path))
+    }
+}
@@ -18,13 +18,100 @@
 //! - Player Data Constants
 //! - Civilization Constants
 
-use std::fmt::Display;
+use std::{collections::HashSet, fmt::Display, str::FromStr, sync::OnceLock};
 
-use crate::lexer;
+use serde::Deserialize;
+
+use crate::{lexer, suggest::did_you_mean};
+
+/// The error of parsing a name that does not name a known constant.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnknownNameError {
+    /// The kind of constant that was expected, such as `terrain`.
+    kind: &'static str,
+    /// The unknown name.
+    name: String,
+    /// The known name most similar to the unknown name, if one is similar enough.
+    suggestion: Option<String>,
+}
+
+impl UnknownNameError {
+    /// Constructs an error for the unknown `name` of a `kind` of constant, suggesting the
+    /// most similar of the `known` names.
+    fn new<'a>(kind: &'static str, name: &str, known: impl Iterator<Item = &'a str>) -> Self {
+        Self {
+            kind,
+            name: String::from(name),
+            suggestion: did_you_mean(name, known).map(String::from),
+        }
+    }
+
+    /// Returns the known name most similar to the unknown name, if one is similar enough.
+    pub fn suggestion(&self) -> Option<&str> {
+        self.suggestion.as_deref()
+    }
+}
+
+impl Display for UnknownNameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown {} `{}`", self.kind, self.name)?;
+        match &self.suggestion {
+            Some(suggestion) => write!(f, ", did you mean `{suggestion}`?"),
+            None => write!(f, "."),
+        }
+    }
+}
+
+impl std::error::Error for UnknownNameError {}
+
+/// A version of the game for which a map script is generated.
+/// Some constants are available in only some versions.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy, Deserialize)]
+pub enum GameVersion {
+    /// The HD Edition, including its expansions.
+    Hd,
+    /// The Definitive Edition.
+    #[default]
+    De,
+}
+
+impl Display for GameVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use GameVersion::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Hd => "HD",
+                De => "DE",
+            }
+        )
+    }
+}
+
+impl FromStr for GameVersion {
+    type Err = UnknownNameError;
+
+    /// Parses a game version from its displayed name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [GameVersion::Hd, GameVersion::De]
+            .into_iter()
+            .find(|version| version.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownNameError::new("game version", s, ["HD", "DE"].into_iter()))
+    }
+}
+
+impl TryFrom<&str> for GameVersion {
+    type Error = UnknownNameError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
 
 /// The type of label, indicating how it's intended to be used in a map script.
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
-enum LabelType {
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Deserialize)]
+pub enum LabelType {
     /// The game mode selected in the lobby dropdown menu.
     GameMode,
     /// The size of the map, including the original sizes and HD' Ludicrous.
@@ -74,9 +161,121 @@ impl Display for LabelType {
     }
 }
 
+impl LabelType {
+    /// All label types, in the order of their declaration.
+    pub const ALL: [LabelType; 11] = [
+        LabelType::GameMode,
+        LabelType::MapSizeLegacy,
+        LabelType::MapSizeModern,
+        LabelType::StartingResources,
+        LabelType::StartingAge,
+        LabelType::AdditionalLobbySettings,
+        LabelType::PlayerCount,
+        LabelType::TeamCount,
+        LabelType::TeamSize,
+        LabelType::PlayerInTeam,
+        LabelType::GameVersions,
+    ];
+}
+
+impl FromStr for LabelType {
+    type Err = UnknownNameError;
+
+    /// Parses a label type from the name of its variant, such as `MapSizeModern`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let names: Vec<String> = LabelType::ALL.iter().map(|t| format!("{t:?}")).collect();
+        match names.iter().position(|name| name == s) {
+            Some(i) => Ok(LabelType::ALL[i].clone()),
+            None => Err(UnknownNameError::new(
+                "label type",
+                s,
+                names.iter().map(String::as_str),
+            )),
+        }
+    }
+}
+
+/// A section header, which begins a section of a map script.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum Section {
+    /// `<PLAYER_SETUP>`
+    PlayerSetup,
+    /// `<LAND_GENERATION>`
+    LandGeneration,
+    /// `<ELEVATION_GENERATION>`
+    ElevationGeneration,
+    /// `<CLIFF_GENERATION>`
+    CliffGeneration,
+    /// `<TERRAIN_GENERATION>`
+    TerrainGeneration,
+    /// `<CONNECTION_GENERATION>`
+    ConnectionGeneration,
+    /// `<OBJECTS_GENERATION>`
+    ObjectsGeneration,
+}
+
+impl Section {
+    /// All sections, in the order in which the game generates them.
+    pub const ALL: [Section; 7] = [
+        Section::PlayerSetup,
+        Section::LandGeneration,
+        Section::ElevationGeneration,
+        Section::CliffGeneration,
+        Section::TerrainGeneration,
+        Section::ConnectionGeneration,
+        Section::ObjectsGeneration,
+    ];
+
+    /// Returns the header of this section, including the angle brackets.
+    pub fn header(self) -> &'static str {
+        match self {
+            Section::PlayerSetup => "<PLAYER_SETUP>",
+            Section::LandGeneration => "<LAND_GENERATION>",
+            Section::ElevationGeneration => "<ELEVATION_GENERATION>",
+            Section::CliffGeneration => "<CLIFF_GENERATION>",
+            Section::TerrainGeneration => "<TERRAIN_GENERATION>",
+            Section::ConnectionGeneration => "<CONNECTION_GENERATION>",
+            Section::ObjectsGeneration => "<OBJECTS_GENERATION>",
+        }
+    }
+
+    /// Returns the section whose header is `header`, or `None` if there is no such section.
+    pub fn from_header(header: &str) -> Option<Section> {
+        Section::ALL
+            .into_iter()
+            .find(|section| section.header() == header)
+    }
+}
+
+impl Display for Section {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.header())
+    }
+}
+
+impl FromStr for Section {
+    type Err = UnknownNameError;
+
+    /// Parses a section from its header, including the angle brackets.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Section::from_header(s).ok_or_else(|| {
+            let headers = Section::ALL.into_iter().map(Section::header);
+            UnknownNameError::new("section", s, headers)
+        })
+    }
+}
+
+impl TryFrom<&str> for Section {
+    type Error = UnknownNameError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
 /// A label for if statements.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
-struct Label {
+pub struct Label {
     /// The name of the label. Consists of only non-whitespace tokens and must be nonempty.
     name: String,
     /// The description of the label, if the label is built-in.
@@ -88,7 +287,7 @@ struct Label {
 impl Label {
     /// Constructs a new label using `name` with the given `description`, and `label_type`.
     /// The `name` must consist of only non-whitespace tokens and must be nonempty.
-    /// If the label is built-in, then it
+    /// If the label is built-in, then it has both a `description` and a `label_type`.
     pub fn new(name: &str, description: Option<&str>, label_type: Option<LabelType>) -> Self {
         debug_assert!(!name.is_empty() && !name.chars().any(lexer::is_whitespace));
         Self {
@@ -97,4 +296,693 @@ impl Label {
             label_type,
         }
     }
+
+    /// Returns the name of the label.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the description of the label, if the label is built-in.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Returns the type of the label, if the label is built-in.
+    pub fn label_type(&self) -> Option<&LabelType> {
+        self.label_type.as_ref()
+    }
+}
+
+/// The data file containing the descriptions of the labels and constants.
+const DATA_FILE: &str = include_str!("../data/rms_data.toml");
+
+/// A built-in label with a fixed name, as listed in the data file.
+#[derive(Debug, Deserialize)]
+struct LabelEntry {
+    /// The name of the label.
+    name: String,
+    /// The type of the label.
+    label_type: LabelType,
+    /// The description of the label.
+    description: String,
+}
+
+/// The contents of the data file.
+#[derive(Debug, Deserialize)]
+struct RmsData {
+    /// The built-in labels with fixed names.
+    labels: Vec<LabelEntry>,
+    /// The terrain constants.
+    terrains: Vec<Terrain>,
+    /// The assign type constants used by `assign_to`.
+    assign_types: Vec<Constant>,
+    /// The constants renamed by later patches.
+    deprecated_constants: Vec<DeprecatedConstant>,
+    /// The commands, including directives.
+    commands: Vec<Command>,
+    /// The resource constants.
+    resources: Vec<Constant>,
+}
+
+/// Returns the contents of the data file, parsing the file when first called.
+fn data() -> &'static RmsData {
+    static DATA: OnceLock<RmsData> = OnceLock::new();
+    DATA.get_or_init(|| toml::from_str(DATA_FILE).expect("The data file must be valid."))
+}
+
+/// Returns the built-in labels, including the numbered player and team labels.
+pub fn built_in_labels() -> &'static [Label] {
+    static LABELS: OnceLock<Vec<Label>> = OnceLock::new();
+    LABELS.get_or_init(|| {
+        let mut labels: Vec<Label> = data()
+            .labels
+            .iter()
+            .map(|entry| {
+                Label::new(
+                    &entry.name,
+                    Some(&entry.description),
+                    Some(entry.label_type.clone()),
+                )
+            })
+            .collect();
+        for p in 1..=8 {
+            let description = format!("The game has {p} players.");
+            let name = format!("{p}_PLAYER_GAME");
+            labels.push(Label::new(
+                &name,
+                Some(&description),
+                Some(LabelType::PlayerCount),
+            ));
+        }
+        for t in 0..=4 {
+            let description = format!("The game has {t} teams.");
+            let name = format!("{t}_TEAM_GAME");
+            labels.push(Label::new(
+                &name,
+                Some(&description),
+                Some(LabelType::TeamCount),
+            ));
+        }
+        for t in 0..=4 {
+            for n in 0..=8 {
+                let description = format!("Team {t} has {n} players.");
+                let name = format!("TEAM{t}_SIZE{n}");
+                labels.push(Label::new(
+                    &name,
+                    Some(&description),
+                    Some(LabelType::TeamSize),
+                ));
+            }
+        }
+        for p in 1..=8 {
+            for t in 0..=4 {
+                let description = format!("Player {p} is on team {t}.");
+                let name = format!("PLAYER{p}_TEAM{t}");
+                labels.push(Label::new(
+                    &name,
+                    Some(&description),
+                    Some(LabelType::PlayerInTeam),
+                ));
+            }
+        }
+        labels
+    })
+}
+
+/// Returns the built-in label named `name`, or `None` if there is no such label.
+pub fn built_in_label(name: &str) -> Option<&'static Label> {
+    built_in_labels().iter().find(|label| label.name == name)
+}
+
+/// A terrain constant.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Deserialize)]
+pub struct Terrain {
+    /// The name of the constant.
+    name: String,
+    /// The game versions in which the constant is available.
+    versions: Vec<GameVersion>,
+    /// The description of the terrain.
+    description: String,
+}
+
+impl Terrain {
+    /// Returns the name of the terrain constant.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the description of the terrain.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns `true` if the terrain is available in `version`, `false` otherwise.
+    pub fn is_available(&self, version: GameVersion) -> bool {
+        self.versions.contains(&version)
+    }
+}
+
+impl FromStr for Terrain {
+    type Err = UnknownNameError;
+
+    /// Parses a terrain constant from its name, in any game version.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        any_terrain_from_name(s).cloned().ok_or_else(|| {
+            let names = data().terrains.iter().map(Terrain::name);
+            UnknownNameError::new("terrain", s, names)
+        })
+    }
+}
+
+impl TryFrom<&str> for Terrain {
+    type Error = UnknownNameError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Returns the terrain constant named `name` if it is available in `version`.
+/// Returns `None` if there is no such terrain or it is not available in `version`.
+pub fn terrain_from_name(name: &str, version: GameVersion) -> Option<&'static Terrain> {
+    any_terrain_from_name(name).filter(|terrain| terrain.is_available(version))
+}
+
+/// Returns the terrain constant named `name` in any version,
+/// or `None` if there is no such terrain.
+pub(crate) fn any_terrain_from_name(name: &str) -> Option<&'static Terrain> {
+    data().terrains.iter().find(|terrain| terrain.name == name)
+}
+
+/// A constant that is available in every game version that supports its command.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Deserialize)]
+pub struct Constant {
+    /// The name of the constant.
+    name: String,
+    /// The description of the constant.
+    description: String,
+}
+
+impl Constant {
+    /// Returns the name of the constant.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the description of the constant.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+/// Returns the assign type constant named `name`, or an error suggesting a similar
+/// assign type if there is no such constant.
+pub fn parse_assign_type(name: &str) -> Result<&'static Constant, UnknownNameError> {
+    assign_type(name).ok_or_else(|| {
+        let names = assign_types().iter().map(Constant::name);
+        UnknownNameError::new("assign type", name, names)
+    })
+}
+
+/// Returns the assign type constants accepted by `assign_to`.
+pub fn assign_types() -> &'static [Constant] {
+    &data().assign_types
+}
+
+/// Returns the assign type constant named `name`, or `None` if there is no such constant.
+pub fn assign_type(name: &str) -> Option<&'static Constant> {
+    assign_types().iter().find(|constant| constant.name == name)
+}
+
+/// Returns the resource constants, such as `AMOUNT_STARTING_FOOD`.
+pub fn resources() -> &'static [Constant] {
+    &data().resources
+}
+
+/// Returns the resource constant named `name`, or `None` if there is no such constant.
+pub fn resource(name: &str) -> Option<&'static Constant> {
+    resources().iter().find(|constant| constant.name == name)
+}
+
+/// The commands taking a resource constant as their second argument when their first
+/// argument is the paired constant, such as `effect_amount MOD_RESOURCE AMOUNT_FOOD`.
+pub const RESOURCE_COMMANDS: [(&str, &str); 2] = [
+    ("effect_amount", "MOD_RESOURCE"),
+    ("effect_percent", "MOD_RESOURCE"),
+];
+
+/// A constant that was renamed by a later patch.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Deserialize)]
+pub struct DeprecatedConstant {
+    /// The old name of the constant.
+    name: String,
+    /// The name that replaces the old name.
+    replacement: String,
+}
+
+impl DeprecatedConstant {
+    /// Returns the old name of the constant.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name that replaces the old name.
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+}
+
+/// Returns the deprecated constants, in the order of the data file.
+pub fn deprecated_constants() -> &'static [DeprecatedConstant] {
+    &data().deprecated_constants
+}
+
+/// Returns the deprecated constant named `name`, or `None` if `name` is not deprecated.
+pub fn deprecated_constant(name: &str) -> Option<&'static DeprecatedConstant> {
+    deprecated_constants()
+        .iter()
+        .find(|constant| constant.name == name)
+}
+
+/// Returns the description of the built-in label or constant named `name`,
+/// or `None` if there is no such label or constant.
+pub fn description(name: &str) -> Option<&'static str> {
+    match built_in_label(name) {
+        Some(label) => label.description(),
+        None => any_terrain_from_name(name)
+            .map(Terrain::description)
+            .or_else(|| assign_type(name).map(Constant::description))
+            .or_else(|| resource(name).map(Constant::description)),
+    }
+}
+
+/// A command, including directives such as `#const`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Deserialize)]
+pub struct Command {
+    /// The name of the command.
+    name: String,
+    /// The description of the command.
+    description: String,
+    /// The number of numeric arguments the command takes, if it takes a fixed number of
+    /// leading numbers, such as the x and y pair of `land_position`.
+    #[serde(default)]
+    numeric_arguments: Option<usize>,
+    /// Whether the command takes no arguments, such as `random_placement`.
+    #[serde(default)]
+    no_arguments: bool,
+    /// Whether the first argument of the command is a named constant, such as the terrain
+    /// of `base_terrain`, rather than a number.
+    #[serde(default)]
+    constant_argument: bool,
+}
+
+impl Command {
+    /// Returns the name of the command.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the description of the command.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns the number of leading numeric arguments the command takes,
+    /// or `None` if the command does not take a fixed number of numbers.
+    pub fn numeric_arguments(&self) -> Option<usize> {
+        self.numeric_arguments
+    }
+
+    /// Returns `true` if the command takes no arguments, so that the token following it
+    /// begins a new statement.
+    pub fn takes_no_arguments(&self) -> bool {
+        self.no_arguments
+    }
+
+    /// Returns `true` if the first argument of the command is a named constant, such as
+    /// the terrain of `base_terrain`, rather than a number.
+    pub fn takes_constant_argument(&self) -> bool {
+        self.constant_argument
+    }
+}
+
+/// Returns `true` if `name` is a constant built into the game in any version, such as a
+/// terrain, an assign type, or a resource, `false` otherwise.
+/// Constants defined with `#const` are not built in.
+pub fn is_built_in_constant(name: &str) -> bool {
+    any_terrain_from_name(name).is_some()
+        || assign_type(name).is_some()
+        || resource(name).is_some()
+        || deprecated_constant(name).is_some()
+}
+
+/// Returns the commands, in the order of the data file.
+pub fn commands() -> &'static [Command] {
+    &data().commands
+}
+
+/// Returns the command named `name`, or `None` if there is no such command.
+pub fn command(name: &str) -> Option<&'static Command> {
+    commands().iter().find(|command| command.name == name)
+}
+
+/// The domain of a completion.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum CompletionKind {
+    /// A command or directive.
+    Command,
+    /// A section header.
+    Section,
+    /// A built-in label.
+    Label,
+    /// A terrain constant.
+    Terrain,
+    /// An assign type constant.
+    AssignType,
+    /// A resource constant.
+    Resource,
+}
+
+/// A known identifier that an editor may offer to complete.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Completion {
+    /// The identifier.
+    name: String,
+    /// The domain of the identifier.
+    kind: CompletionKind,
+    /// The description of the identifier.
+    description: Option<String>,
+}
+
+impl Completion {
+    /// Returns the identifier.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the domain of the identifier.
+    pub fn kind(&self) -> CompletionKind {
+        self.kind
+    }
+
+    /// Returns the description of the identifier, if it has one.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
+
+/// Returns every known command, section header, built-in label, and constant, in that
+/// order, for an editor to offer as completions.
+/// Each name occurs once, with the first domain in which it is found.
+/// The list is built when first called.
+pub fn all_completions() -> &'static [Completion] {
+    static COMPLETIONS: OnceLock<Vec<Completion>> = OnceLock::new();
+    COMPLETIONS.get_or_init(|| {
+        let completion = |name: &str, kind, description: Option<&str>| Completion {
+            name: String::from(name),
+            kind,
+            description: description.map(String::from),
+        };
+        let candidates =
+            commands()
+                .iter()
+                .map(|c| completion(c.name(), CompletionKind::Command, Some(c.description())))
+                .chain(Section::ALL.into_iter().map(|section| {
+                    let description = format!("The {section} section.");
+                    completion(
+                        section.header(),
+                        CompletionKind::Section,
+                        Some(&description),
+                    )
+                }))
+                .chain(built_in_labels().iter().map(|label| {
+                    completion(label.name(), CompletionKind::Label, label.description())
+                }))
+                .chain(data().terrains.iter().map(|terrain| {
+                    completion(
+                        terrain.name(),
+                        CompletionKind::Terrain,
+                        Some(terrain.description()),
+                    )
+                }))
+                .chain(assign_types().iter().map(|constant| {
+                    completion(
+                        constant.name(),
+                        CompletionKind::AssignType,
+                        Some(constant.description()),
+                    )
+                }))
+                .chain(resources().iter().map(|constant| {
+                    completion(
+                        constant.name(),
+                        CompletionKind::Resource,
+                        Some(constant.description()),
+                    )
+                }));
+        let mut seen = HashSet::new();
+        candidates
+            .filter(|completion| seen.insert(completion.name.clone()))
+            .collect()
+    })
+}
+
+/// The property commands that are usually present in a `create_object` block.
+/// Omitting `number_of_objects` places a single object, which is rarely intended.
+pub const EXPECTED_OBJECT_PROPERTIES: [&str; 1] = ["number_of_objects"];
+
+/// Pairs of `create_object` property commands that contradict each other when used in
+/// the same block.
+pub const CONTRADICTORY_OBJECT_PROPERTIES: [(&str, &str); 2] = [
+    ("set_loose_grouping", "set_tight_grouping"),
+    ("set_scaling_to_map_size", "set_scaling_to_player_number"),
+];
+
+/// The numbers embedded in the name of a player or team label.
+/// Team numbers refer to lobby order, and team 0 contains the players without a team.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum PlayerSetup {
+    /// A `PlayerCount` label: the game has this many players.
+    PlayerCount(usize),
+    /// A `TeamCount` label: the game has this many teams.
+    TeamCount(usize),
+    /// A `TeamSize` label: the team has `size` players.
+    TeamSize { team: usize, size: usize },
+    /// A `PlayerInTeam` label: the player is on the team.
+    PlayerInTeam { player: usize, team: usize },
+}
+
+impl PlayerSetup {
+    /// Returns the maximum number of players allowed by this label, if it limits them.
+    fn max_players(self) -> Option<usize> {
+        match self {
+            Self::PlayerCount(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Returns the minimum number of players required by this label.
+    fn min_players(self) -> usize {
+        match self {
+            Self::PlayerCount(n) => n,
+            Self::TeamCount(_) => 0,
+            Self::TeamSize { size, .. } => size,
+            Self::PlayerInTeam { player, .. } => player,
+        }
+    }
+
+    /// Returns the maximum number of teams allowed by this label, if it limits them.
+    fn max_teams(self) -> Option<usize> {
+        match self {
+            Self::TeamCount(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Returns the highest team number referenced by this label, or 0 if there is none.
+    /// Team 0 contains the players without a team, so it does not count towards teams.
+    fn team(self) -> usize {
+        match self {
+            Self::TeamSize { team, size } if size > 0 => team,
+            Self::PlayerInTeam { team, .. } => team,
+            _ => 0,
+        }
+    }
+
+    /// Returns `true` if this label and `other` can both hold in the same game,
+    /// `false` otherwise.
+    pub fn is_consistent_with(self, other: &PlayerSetup) -> bool {
+        let other = *other;
+        let fits = |a: PlayerSetup, b: PlayerSetup| {
+            a.max_players().is_none_or(|max| b.min_players() <= max)
+                && a.max_teams().is_none_or(|max| b.team() <= max)
+        };
+        let same_kind_conflict = match (self, other) {
+            (Self::PlayerCount(a), Self::PlayerCount(b)) => a != b,
+            (Self::TeamCount(a), Self::TeamCount(b)) => a != b,
+            (Self::TeamSize { team: a, size: m }, Self::TeamSize { team: b, size: n }) => {
+                a == b && m != n
+            }
+            (
+                Self::PlayerInTeam { player: a, team: s },
+                Self::PlayerInTeam { player: b, team: t },
+            ) => a == b && s != t,
+            _ => false,
+        };
+        !same_kind_conflict && fits(self, other) && fits(other, self)
+    }
+}
+
+/// Returns the player setup of the built-in label named `name`,
+/// or `None` if it is not a player count, team count, team size, or player-in-team label.
+pub fn player_setup(name: &str) -> Option<PlayerSetup> {
+    let label_type = built_in_label(name)?.label_type()?;
+    let numbers: Vec<usize> = name
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().unwrap())
+        .collect();
+    match (label_type, &numbers[..]) {
+        (LabelType::PlayerCount, [n]) => Some(PlayerSetup::PlayerCount(*n)),
+        (LabelType::TeamCount, [n]) => Some(PlayerSetup::TeamCount(*n)),
+        (LabelType::TeamSize, [team, size]) => Some(PlayerSetup::TeamSize {
+            team: *team,
+            size: *size,
+        }),
+        (LabelType::PlayerInTeam, [player, team]) => Some(PlayerSetup::PlayerInTeam {
+            player: *player,
+            team: *team,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that a terrain available in all versions is found in each version.
+    #[test]
+    fn terrain_all_versions() {
+        assert!(terrain_from_name("GRASS", GameVersion::Hd).is_some());
+        assert!(terrain_from_name("GRASS", GameVersion::De).is_some());
+    }
+
+    /// Tests that a terrain added in the Definitive Edition is not found in HD.
+    #[test]
+    fn terrain_de_only() {
+        assert!(terrain_from_name("BEACH_WHITE", GameVersion::Hd).is_none());
+        assert!(terrain_from_name("BEACH_WHITE", GameVersion::default()).is_some());
+    }
+
+    /// Tests that every entry of the data file has a unique, valid name and a description.
+    #[test]
+    fn data_file_integrity() {
+        let data: RmsData = toml::from_str(DATA_FILE).unwrap();
+        let names: Vec<&str> = data
+            .labels
+            .iter()
+            .map(|label| &label.name[..])
+            .chain(data.terrains.iter().map(|terrain| terrain.name()))
+            .chain(data.assign_types.iter().map(|constant| constant.name()))
+            .collect();
+        let unique: std::collections::HashSet<&&str> = names.iter().collect();
+        assert_eq!(unique.len(), names.len());
+        for name in names {
+            assert!(!name.is_empty() && !name.chars().any(lexer::is_whitespace));
+        }
+        assert!(data
+            .labels
+            .iter()
+            .all(|label| !label.description.is_empty()));
+        for terrain in data.terrains.iter() {
+            assert!(!terrain.description().is_empty());
+            assert!(!terrain.versions.is_empty());
+        }
+        assert!(data
+            .assign_types
+            .iter()
+            .all(|constant| !constant.description().is_empty()));
+    }
+
+    /// Tests that built-in labels are described using the data file.
+    #[test]
+    fn label_description() {
+        let label = built_in_label("REGICIDE").unwrap();
+        assert_eq!(label.description(), Some("The Regicide game mode."));
+        assert_eq!(label.label_type(), Some(&LabelType::GameMode));
+    }
+
+    /// Tests parsing the numbers of player and team labels.
+    #[test]
+    fn player_setup_numbers() {
+        assert_eq!(
+            player_setup("3_PLAYER_GAME"),
+            Some(PlayerSetup::PlayerCount(3))
+        );
+        assert_eq!(
+            player_setup("PLAYER7_TEAM2"),
+            Some(PlayerSetup::PlayerInTeam { player: 7, team: 2 })
+        );
+        assert_eq!(player_setup("REGICIDE"), None);
+    }
+
+    /// Tests parsing constants from their names, with suggestions for unknown names.
+    #[test]
+    fn parse_names() {
+        let grass: Terrain = "GRASS".parse().unwrap();
+        assert_eq!(grass.name(), "GRASS");
+        assert_eq!(
+            Section::try_from("<PLAYER_SETUP>"),
+            Ok(Section::PlayerSetup)
+        );
+        assert_eq!("hd".parse(), Ok(GameVersion::Hd));
+        assert_eq!(parse_assign_type("AT_TEAM").unwrap().name(), "AT_TEAM");
+
+        let error = "GRASSS".parse::<Terrain>().unwrap_err();
+        assert_eq!(error.suggestion(), Some("GRASS"));
+        assert_eq!(
+            error.to_string(),
+            "Unknown terrain `GRASSS`, did you mean `GRASS`?"
+        );
+        let error = Section::try_from("<LAND_GENERATON>").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unknown section `<LAND_GENERATON>`, did you mean `<LAND_GENERATION>`?"
+        );
+        let error = parse_assign_type("XYZZY_PLUGH").unwrap_err();
+        assert_eq!(error.to_string(), "Unknown assign type `XYZZY_PLUGH`.");
+    }
+
+    /// Tests that the completions cover each domain without duplicates.
+    #[test]
+    fn completions() {
+        let completions = all_completions();
+        let find = |name| completions.iter().find(|c| c.name() == name).unwrap();
+        assert_eq!(find("create_object").kind(), CompletionKind::Command);
+        assert_eq!(find("<PLAYER_SETUP>").kind(), CompletionKind::Section);
+        assert_eq!(find("GRASS").kind(), CompletionKind::Terrain);
+        assert_eq!(find("GRASS").description(), Some("Grass."));
+        assert_eq!(find("REGICIDE").kind(), CompletionKind::Label);
+        assert_eq!(find("PLAYER3_TEAM1").kind(), CompletionKind::Label);
+        assert_eq!(find("AT_TEAM").kind(), CompletionKind::AssignType);
+        assert_eq!(find("AMOUNT_GOLD").kind(), CompletionKind::Resource);
+        let names: HashSet<&str> = completions.iter().map(Completion::name).collect();
+        assert_eq!(names.len(), completions.len());
+    }
+
+    /// Tests that each deprecated constant is replaced by a current terrain.
+    #[test]
+    fn deprecated_replacements() {
+        assert!(!deprecated_constants().is_empty());
+        for constant in deprecated_constants() {
+            assert!(any_terrain_from_name(constant.name()).is_none());
+            assert!(terrain_from_name(constant.replacement(), GameVersion::De).is_some());
+        }
+    }
+
+    /// Tests that an unknown terrain is not found.
+    #[test]
+    fn terrain_unknown() {
+        assert!(terrain_from_name("NOT_A_TERRAIN", GameVersion::De).is_none());
+    }
 }
@@ -0,0 +1,92 @@
+//! A map script's path and text together with its lexemes, so that features needing the
+//! original source, such as rendering snippets or slicing spans, need not read or lex
+//! the file again.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    lexer::{self, LexemeFile},
+    span::Span,
+};
+
+/// A map script's path, its text, and the lexemes lexed from the text once on
+/// construction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceFile {
+    /// The path of the file, which for a script not read from a file only names it.
+    path: PathBuf,
+    /// The text of the file.
+    text: String,
+    /// The lexemes of `text`.
+    lexemes: LexemeFile,
+}
+
+impl SourceFile {
+    /// Reads and lexes the map script at `path`.
+    /// Returns an IO error if the file cannot be read or is not valid UTF-8.
+    pub fn from_path(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Ok(Self::from_str(path, text))
+    }
+
+    /// Lexes the map script `text`, named by `path`, such as `maps/a.rms` for an unsaved
+    /// file. No file is read.
+    pub fn from_str(path: impl Into<PathBuf>, text: impl Into<String>) -> Self {
+        let text = text.into();
+        let lexemes = lexer::lex_str(&text);
+        Self {
+            path: path.into(),
+            text,
+            lexemes,
+        }
+    }
+
+    /// Returns the path of this file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the text of this file.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the lexemes of this file.
+    pub fn lexemes(&self) -> &LexemeFile {
+        &self.lexemes
+    }
+
+    /// Returns the lexemes of this file, consuming the file.
+    pub fn into_lexemes(self) -> LexemeFile {
+        self.lexemes
+    }
+
+    /// Returns the text of this file within `span`, or `None` if the span's bytes are not
+    /// a range of this file's text.
+    pub fn slice(&self, span: &Span) -> Option<&str> {
+        self.text.get(span.byte_range())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the text, lexemes, and spans of a file constructed from a string agree.
+    #[test]
+    fn from_str_round_trip() {
+        let text = "/* a */\r\nbase_terrain\tGRASS\n";
+        let source = SourceFile::from_str("maps/a.rms", text);
+        assert_eq!(source.path(), Path::new("maps/a.rms"));
+        assert_eq!(source.text(), text);
+        assert_eq!(source.lexemes(), &lexer::lex_str(text));
+        assert_eq!(source.lexemes().to_source_string(), text);
+        for lexeme in source.lexemes().lexemes() {
+            let info = lexeme.get_info();
+            assert_eq!(source.slice(&info.span()), Some(info.characters()));
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! The location of a range of source text, shared by lexemes, logical lines, and
+//! diagnostics.
+
+use std::ops::Range;
+
+/// A range of source text, located by its 1-indexed lines and columns and its 0-indexed
+/// byte offsets within the file.
+/// The end line, end column, and end byte are those of the final character, except the
+/// end byte is exclusive.
+///
+/// Spans are ordered by their start positions, then by their end positions.
+// The derived orderings depend on the order of the fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    /// The 1-indexed line number of the first character.
+    start_line: usize,
+    /// The 1-indexed column number of the first character.
+    start_column: usize,
+    /// The 1-indexed line number of the final character.
+    end_line: usize,
+    /// The 1-indexed column number of the final character.
+    end_column: usize,
+    /// The 0-indexed offset of the first byte.
+    start_byte: usize,
+    /// The 0-indexed offset after the final byte.
+    end_byte: usize,
+}
+
+impl Span {
+    /// Constructs a new span from the line and column of its first character, the line
+    /// and column of its final character, and its range of byte offsets.
+    pub fn new(
+        start_line: usize,
+        start_column: usize,
+        end_line: usize,
+        end_column: usize,
+        bytes: Range<usize>,
+    ) -> Self {
+        debug_assert!(start_line >= 1 && start_line <= end_line);
+        debug_assert!(bytes.start <= bytes.end);
+        Self {
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+            start_byte: bytes.start,
+            end_byte: bytes.end,
+        }
+    }
+
+    /// Returns the 1-indexed line number of this span's first character.
+    pub fn start_line(&self) -> usize {
+        self.start_line
+    }
+
+    /// Returns the 1-indexed column number of this span's first character.
+    pub fn start_column(&self) -> usize {
+        self.start_column
+    }
+
+    /// Returns the 1-indexed line number of this span's final character.
+    pub fn end_line(&self) -> usize {
+        self.end_line
+    }
+
+    /// Returns the 1-indexed column number of this span's final character.
+    pub fn end_column(&self) -> usize {
+        self.end_column
+    }
+
+    /// Returns the range of 0-indexed byte offsets of this span within the file.
+    pub fn byte_range(&self) -> Range<usize> {
+        self.start_byte..self.end_byte
+    }
+
+    /// Returns the smallest span covering both this span and `other`.
+    pub fn merge(&self, other: &Span) -> Span {
+        let (start, end) = (self.min(other), self.max_end(other));
+        Span {
+            start_line: start.start_line,
+            start_column: start.start_column,
+            end_line: end.end_line,
+            end_column: end.end_column,
+            start_byte: start.start_byte,
+            end_byte: end.end_byte,
+        }
+    }
+
+    /// Returns whichever of this span and `other` ends later.
+    fn max_end<'a>(&'a self, other: &'a Span) -> &'a Span {
+        if (other.end_line, other.end_column) > (self.end_line, self.end_column) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{annotater::AnnotatedFile, lexer, tokenizer};
+
+    /// Tests merging two spans on the same line.
+    #[test]
+    fn merge_same_line() {
+        let a = Span::new(2, 3, 2, 5, 10..13);
+        let b = Span::new(2, 9, 2, 12, 16..20);
+        let merged = Span::new(2, 3, 2, 12, 10..20);
+        assert_eq!(a.merge(&b), merged);
+        assert_eq!(b.merge(&a), merged);
+        assert!(a < b);
+    }
+
+    /// Tests merging spans across lines, including a span containing the other.
+    #[test]
+    fn merge_across_lines() {
+        let a = Span::new(1, 4, 3, 2, 3..25);
+        let b = Span::new(2, 1, 2, 6, 10..16);
+        let c = Span::new(3, 5, 4, 1, 28..35);
+        assert_eq!(a.merge(&b), a);
+        assert_eq!(c.merge(&a), Span::new(1, 4, 4, 1, 3..35));
+        let mut spans = vec![c, a, b];
+        spans.sort();
+        assert_eq!(spans, vec![a, b, c]);
+    }
+
+    /// Tests that lexemes and logical lines share the same span representation.
+    #[test]
+    fn shared_spans() {
+        let file = AnnotatedFile::annotate(&lexer::lex_str("é create_land\n  GRASS"));
+        let lexeme_span = file.tokens()[2].token().get_info().span();
+        assert_eq!(
+            (lexeme_span.start_line(), lexeme_span.start_column()),
+            (1, 3)
+        );
+        assert_eq!(lexeme_span.end_column(), 13);
+        assert_eq!(lexeme_span.byte_range(), 3..14);
+
+        let lines = tokenizer::logical_lines(&file);
+        let span = lines[1].span();
+        assert_eq!((span.start_line(), span.start_column()), (1, 3));
+        assert_eq!((span.end_line(), span.end_column()), (2, 7));
+        assert_eq!(span.byte_range(), 3..22);
+    }
+}
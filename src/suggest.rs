@@ -0,0 +1,45 @@
+//! Suggestions of similar names for likely typos.
+
+/// Returns the number of single character insertions, deletions, and substitutions
+/// required to transform `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Returns the candidate closest to `name`, if one is close enough to be a likely typo.
+pub(crate) fn did_you_mean<'a>(
+    name: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let threshold = (name.chars().count() / 3).max(2);
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the edit distances of insertions, deletions, and substitutions.
+    #[test]
+    fn distances() {
+        assert_eq!(edit_distance("GRASS", "GRASS"), 0);
+        assert_eq!(edit_distance("GRAS", "GRASS"), 1);
+        assert_eq!(edit_distance("GRASS", "GLASS"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
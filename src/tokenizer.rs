@@ -1 +1,631 @@
 //! Tokenizer for converting lexemes to tokens.
+
+use crate::{annotater::AnnotatedFile, lexer::Lexeme, rms_data, span::Span};
+
+/// A command together with its arguments, which may span multiple physical lines.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LogicalLine {
+    /// The indices within the annotated file of the logical line's text tokens,
+    /// in order. Comment tokens are excluded.
+    token_indices: Vec<usize>,
+    /// The span from the first token through the final token.
+    span: Span,
+}
+
+impl LogicalLine {
+    /// Returns the indices within the annotated file of this logical line's text tokens.
+    pub fn token_indices(&self) -> &[usize] {
+        &self.token_indices
+    }
+
+    /// Returns the span from the first token through the final token.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Returns the 1-indexed line number of the first token.
+    pub fn start_line(&self) -> usize {
+        self.span.start_line()
+    }
+
+    /// Returns the 1-indexed start column of the first token.
+    pub fn start_column(&self) -> usize {
+        self.span.start_column()
+    }
+
+    /// Returns the 1-indexed line number of the final token.
+    pub fn end_line(&self) -> usize {
+        self.span.end_line()
+    }
+
+    /// Returns the 1-indexed end column of the final token.
+    pub fn end_column(&self) -> usize {
+        self.span.end_column()
+    }
+
+    /// Returns the arguments following the command of this logical line, each a list of
+    /// the indices of its tokens within `file`, which must be the file of this line.
+    /// An `rnd` call written with separate tokens, such as `rnd ( 1 , 4 )`, is a single
+    /// argument, as is every other token.
+    pub fn arguments(&self, file: &AnnotatedFile) -> Vec<Vec<usize>> {
+        let text = |i: usize| file.tokens()[i].token().get_info().characters();
+        let mut arguments = vec![];
+        let mut rest = self.token_indices.get(1..).unwrap_or_default();
+        while !rest.is_empty() {
+            let call: Vec<&str> = rest.iter().take(6).map(|&i| text(i)).collect();
+            let len = match call[..] {
+                ["rnd", "(", _, ",", _, ")"] => 6,
+                _ => 1,
+            };
+            arguments.push(rest[..len].to_vec());
+            rest = &rest[len..];
+        }
+        arguments
+    }
+}
+
+/// A `create_object` command together with the property commands that configure it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ObjectBlock {
+    /// The logical line of the `create_object` command and its object constant.
+    command: LogicalLine,
+    /// The logical lines of the property commands, such as `number_of_objects`, in order.
+    properties: Vec<LogicalLine>,
+}
+
+impl ObjectBlock {
+    /// Returns the logical line of the `create_object` command.
+    pub fn command(&self) -> &LogicalLine {
+        &self.command
+    }
+
+    /// Returns the logical lines of the property commands, in order.
+    pub fn properties(&self) -> &[LogicalLine] {
+        &self.properties
+    }
+
+    /// Returns the name of the object created by this block, or `None` if the
+    /// `create_object` command has no argument.
+    /// The tokens are indexed within `file`, which must be the file of this block.
+    pub fn object_name<'a>(&self, file: &'a AnnotatedFile) -> Option<&'a str> {
+        let &i = self.command.token_indices.get(1)?;
+        Some(file.tokens()[i].token().get_info().characters())
+    }
+
+    /// Returns the text of the arguments of the final property command named `name`,
+    /// or `None` if the block has no such command.
+    /// The tokens are indexed within `file`, which must be the file of this block.
+    pub fn property_arguments<'a>(
+        &self,
+        file: &'a AnnotatedFile,
+        name: &str,
+    ) -> Option<Vec<&'a str>> {
+        let text = |i: &usize| file.tokens()[*i].token().get_info().characters();
+        let property = self
+            .properties
+            .iter()
+            .rev()
+            .find(|line| text(&line.token_indices[0]) == name)?;
+        Some(property.token_indices[1..].iter().map(text).collect())
+    }
+}
+
+/// The value of a numeric argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NumericValue {
+    /// A numeric literal, such as `50`.
+    Literal(i64),
+    /// An `rnd` call choosing a number from `min` through `max`, such as `rnd(10,20)`.
+    Random {
+        /// The least number that may be chosen.
+        min: i64,
+        /// The greatest number that may be chosen.
+        max: i64,
+    },
+}
+
+/// A numeric argument of a command, such as the `50` of `land_position 50 50`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NumericParam {
+    /// The name of the command.
+    command: String,
+    /// The 1-indexed position of the argument among the command's arguments.
+    position: usize,
+    /// The value of the argument.
+    value: NumericValue,
+    /// The span of the argument, covering every token of a spaced `rnd` call.
+    span: Span,
+}
+
+impl NumericParam {
+    /// Returns the name of the command of this argument.
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Returns the 1-indexed position of this argument among the command's arguments.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the value of this argument.
+    pub fn value(&self) -> NumericValue {
+        self.value
+    }
+
+    /// Returns the 1-indexed line number of the start of this argument.
+    pub fn line_number(&self) -> usize {
+        self.span.start_line()
+    }
+
+    /// Returns the span of this argument.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+}
+
+/// Returns `true` if `c` separates the sub-tokens of a text lexeme, `false` otherwise.
+fn is_punctuation(c: char) -> bool {
+    matches!(c, '(' | ')' | ',')
+}
+
+/// Splits the text lexeme `lexeme` before and after each `(`, `)`, and `,`, so that a
+/// call glued without whitespace, such as `rnd(1,4)`, yields the same sub-tokens as the
+/// spaced `rnd ( 1 , 4 )`, each with its own columns and byte offsets.
+/// Whitespace, line breaks, and text without punctuation are returned whole.
+pub fn split_punctuation(lexeme: &Lexeme) -> Vec<Lexeme> {
+    if !matches!(lexeme, Lexeme::Text(_)) {
+        return vec![lexeme.clone()];
+    }
+    let mut sub_tokens = vec![];
+    let mut rest = lexeme.clone();
+    loop {
+        let characters = rest.get_info().characters();
+        let split = match characters.chars().position(is_punctuation) {
+            // Splits after the punctuation, unless it is the final character.
+            Some(0) => (characters.chars().count() > 1).then_some(1),
+            position => position,
+        };
+        let Some(split) = split else {
+            sub_tokens.push(rest);
+            return sub_tokens;
+        };
+        let (first, second) = rest.split_at(split);
+        sub_tokens.push(first);
+        rest = second;
+    }
+}
+
+/// Returns the value of the argument with the sub-token texts `texts`, or `None` if it is
+/// not a numeric literal or an `rnd` call of two literals.
+fn numeric_value(texts: &[&str]) -> Option<NumericValue> {
+    match texts {
+        [text] => text.parse().ok().map(NumericValue::Literal),
+        ["rnd", "(", min, ",", max, ")"] => Some(NumericValue::Random {
+            min: min.parse().ok()?,
+            max: max.parse().ok()?,
+        }),
+        _ => None,
+    }
+}
+
+/// Returns every numeric argument of the commands of `file`, in order, such as for
+/// auditing the resource amounts and distances of a map script.
+/// Numeric literals and `rnd` calls of two literals are included. Constants, including
+/// those defined with `#const`, are not.
+pub fn numeric_parameters(file: &AnnotatedFile) -> Vec<NumericParam> {
+    let info = |i: usize| file.tokens()[i].token().get_info();
+    let mut parameters = vec![];
+    for line in logical_lines(file) {
+        let command = info(line.token_indices[0]).characters();
+        if !is_statement_start(command) {
+            continue;
+        }
+        for (i, argument) in line.arguments(file).iter().enumerate() {
+            let sub_tokens: Vec<Lexeme> = argument
+                .iter()
+                .flat_map(|&j| split_punctuation(file.tokens()[j].token()))
+                .collect();
+            let texts: Vec<&str> = sub_tokens
+                .iter()
+                .map(|sub_token| sub_token.get_info().characters())
+                .collect();
+            let Some(value) = numeric_value(&texts) else {
+                continue;
+            };
+            let first = info(argument[0]).span();
+            let last = info(argument[argument.len() - 1]).span();
+            parameters.push(NumericParam {
+                command: String::from(command),
+                position: i + 1,
+                value,
+                span: first.merge(&last),
+            });
+        }
+    }
+    parameters
+}
+
+/// Returns `true` if `text` begins a new logical line, `false` if it is an argument.
+///
+/// Following the RMS convention that commands are lowercase and constants are uppercase,
+/// a logical line begins with a token starting with a lowercase letter, a directive
+/// starting with `#`, a section header starting with `<`, or a curly brace.
+/// An `rnd` call is an argument, even though it begins with a lowercase letter.
+fn is_statement_start(text: &str) -> bool {
+    if text == "rnd" || text.starts_with("rnd(") {
+        return false;
+    }
+    text == "{"
+        || text == "}"
+        || text
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c == '#' || c == '<')
+}
+
+/// Groups the text tokens of `file` that are not within comments into logical lines.
+///
+/// A logical line consists of a command followed by its arguments, continuing across
+/// physical line breaks until the next command, section header, directive, curly brace,
+/// or blank line. Lines containing only comments are not blank.
+/// A command that takes no arguments, such as `random_placement`, is a logical line by
+/// itself, so the token following it begins a new logical line.
+/// Arguments that follow a blank line, without a command, form their own logical line.
+pub fn logical_lines(file: &AnnotatedFile) -> Vec<LogicalLine> {
+    let mut logical_lines = vec![];
+    let mut current: Option<LogicalLine> = None;
+    // Whether the current physical line has any text, including comments.
+    let mut line_has_text = false;
+    // Whether the command of the current logical line takes arguments.
+    let mut takes_arguments = true;
+    for (i, token) in file.tokens().iter().enumerate() {
+        let info = match token.token() {
+            Lexeme::LineBreak(_) => {
+                if !line_has_text {
+                    logical_lines.extend(current.take());
+                }
+                line_has_text = false;
+                continue;
+            }
+            Lexeme::Whitespace(_) => continue,
+            Lexeme::Text(info) => info,
+        };
+        line_has_text = true;
+        if token.is_comment() {
+            continue;
+        }
+        match current.as_mut() {
+            Some(line) if takes_arguments && !is_statement_start(info.characters()) => {
+                line.token_indices.push(i);
+                line.span = line.span.merge(&info.span());
+            }
+            _ => {
+                logical_lines.extend(current.take());
+                takes_arguments = rms_data::command(info.characters())
+                    .is_none_or(|command| !command.takes_no_arguments());
+                current = Some(LogicalLine {
+                    token_indices: vec![i],
+                    span: info.span(),
+                });
+            }
+        }
+    }
+    logical_lines.extend(current);
+    logical_lines
+}
+
+/// Groups each `create_object` command of `file` with its following property commands.
+///
+/// A block continues until the next `create_object` command or section header.
+/// Curly braces and the commands of `if` and `start_random` blocks are not properties,
+/// though the properties within those blocks belong to the enclosing object block.
+pub fn object_blocks(file: &AnnotatedFile) -> Vec<ObjectBlock> {
+    let mut blocks = vec![];
+    let mut current: Option<ObjectBlock> = None;
+    for line in logical_lines(file) {
+        let command = file.tokens()[line.token_indices[0]]
+            .token()
+            .get_info()
+            .characters();
+        match command {
+            "create_object" => {
+                blocks.extend(current.take());
+                current = Some(ObjectBlock {
+                    command: line,
+                    properties: vec![],
+                });
+            }
+            _ if command.starts_with('<') => blocks.extend(current.take()),
+            "{" | "}" | "if" | "elseif" | "else" | "endif" | "start_random" | "percent_chance"
+            | "end_random" => {}
+            _ if command.starts_with('#') => {}
+            _ => {
+                if let Some(block) = current.as_mut() {
+                    block.properties.push(line);
+                }
+            }
+        }
+    }
+    blocks.extend(current);
+    blocks
+}
+
+/// Returns the 1-indexed number and nesting depth of each line of `file`.
+///
+/// The depth is the number of `if`, `start_random`, and curly brace blocks enclosing
+/// the line, so a formatter may indent each line by its depth.
+/// Lines beginning with the opening or closing token of a block, or with a branch such
+/// as `elseif`, `else`, or `percent_chance`, sit at the depth outside of the block.
+/// As is conventional, sections do not increase the depth.
+/// Lines containing only comments or whitespace have the depth of the enclosing blocks.
+pub fn indentation_levels(file: &AnnotatedFile) -> Vec<(usize, usize)> {
+    let mut levels = vec![];
+    let mut depth = 0usize;
+    // The depth of the current line, determined by its first code token.
+    let mut line_depth: Option<usize> = None;
+    let mut line_number = 1;
+    for token in file.tokens() {
+        let info = match token.token() {
+            Lexeme::LineBreak(_) => {
+                levels.push((line_number, line_depth.take().unwrap_or(depth)));
+                line_number += 1;
+                continue;
+            }
+            Lexeme::Whitespace(_) => continue,
+            Lexeme::Text(_) if token.is_comment() => continue,
+            Lexeme::Text(info) => info,
+        };
+        match info.characters() {
+            "if" | "start_random" | "{" => {
+                line_depth.get_or_insert(depth);
+                depth += 1;
+            }
+            "endif" | "end_random" | "}" => {
+                depth = depth.saturating_sub(1);
+                line_depth.get_or_insert(depth);
+            }
+            "elseif" | "else" | "percent_chance" => {
+                line_depth.get_or_insert(depth.saturating_sub(1));
+            }
+            _ => {
+                line_depth.get_or_insert(depth);
+            }
+        }
+    }
+    if file
+        .tokens()
+        .last()
+        .is_some_and(|t| !matches!(t.token(), Lexeme::LineBreak(_)))
+    {
+        levels.push((line_number, line_depth.unwrap_or(depth)));
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+
+    /// Returns the text of each logical line of `source`, joined by spaces.
+    fn lines(source: &str) -> Vec<String> {
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        logical_lines(&file)
+            .iter()
+            .map(|line| {
+                let texts: Vec<&str> = line
+                    .token_indices()
+                    .iter()
+                    .map(|&i| file.tokens()[i].token().get_info().characters())
+                    .collect();
+                texts.join(" ")
+            })
+            .collect()
+    }
+
+    /// Tests the nesting depths of the lines of a small nested script.
+    #[test]
+    fn nesting_depths() {
+        let source = "<LAND_GENERATION>\nif A\n  create_land {\n    /* note */\n    start_random\n    percent_chance 50\n      base_size 7\n    end_random\n  }\nelse\n\n  create_land { }\nendif";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let depths: Vec<usize> = indentation_levels(&file)
+            .iter()
+            .enumerate()
+            .map(|(i, &(line_number, depth))| {
+                assert_eq!(line_number, i + 1);
+                depth
+            })
+            .collect();
+        assert_eq!(depths, vec![0, 0, 1, 2, 2, 2, 3, 2, 1, 0, 1, 1, 0]);
+    }
+
+    /// Tests that arguments wrapping onto the next line belong to their command.
+    #[test]
+    fn wrapped_arguments() {
+        let source = "create_land {\n  land_position\n    50 /* x */\n    50\n  base_size 7\n}";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let logical = logical_lines(&file);
+        assert_eq!(
+            lines(source),
+            vec![
+                "create_land",
+                "{",
+                "land_position 50 50",
+                "base_size 7",
+                "}"
+            ]
+        );
+        assert_eq!((logical[2].start_line(), logical[2].start_column()), (2, 3));
+        assert_eq!((logical[2].end_line(), logical[2].end_column()), (4, 6));
+    }
+
+    /// Tests that the token following a command taking no arguments begins a new logical
+    /// line, even if it looks like an argument.
+    #[test]
+    fn commands_without_arguments() {
+        let source = "create_object GOLD {\n  set_gaia_object_only 12\n  set_tight_grouping\n  number_of_objects\n    4\n}\nrandom_placement\nGRASS";
+        assert_eq!(
+            lines(source),
+            vec![
+                "create_object GOLD",
+                "{",
+                "set_gaia_object_only",
+                "12",
+                "set_tight_grouping",
+                "number_of_objects 4",
+                "}",
+                "random_placement",
+                "GRASS"
+            ]
+        );
+    }
+
+    /// Tests that properties attach to the preceding of two consecutive object blocks.
+    #[test]
+    fn consecutive_object_blocks() {
+        let source = "<OBJECTS_GENERATION>\ncreate_object RELIC {\n  number_of_objects 5\n  set_gaia_object_only\n}\ncreate_object GOLD {\n  if LARGE_MAP\n    number_of_objects 7\n  endif\n  min_distance_to_players 12\n}\n<PLAYER_SETUP>\nrandom_placement";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let blocks = object_blocks(&file);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].object_name(&file), Some("RELIC"));
+        assert_eq!(blocks[0].properties().len(), 2);
+        assert_eq!(
+            blocks[0].property_arguments(&file, "number_of_objects"),
+            Some(vec!["5"])
+        );
+        assert_eq!(
+            blocks[0].property_arguments(&file, "set_gaia_object_only"),
+            Some(vec![])
+        );
+        assert_eq!(blocks[1].object_name(&file), Some("GOLD"));
+        assert_eq!(blocks[1].command().start_line(), 6);
+        assert_eq!(
+            blocks[1].property_arguments(&file, "number_of_objects"),
+            Some(vec!["7"])
+        );
+        assert_eq!(
+            blocks[1].property_arguments(&file, "min_distance_to_players"),
+            Some(vec!["12"])
+        );
+        assert_eq!(
+            blocks[1].property_arguments(&file, "set_gaia_object_only"),
+            None
+        );
+        assert_eq!(blocks[1].properties().len(), 2);
+    }
+
+    /// Tests that a spaced `rnd` call is a single argument.
+    #[test]
+    fn spaced_rnd_argument() {
+        let source = "land_position rnd ( 1 , 4 ) 50 rnd(2,3)";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let arguments = logical_lines(&file)[0].arguments(&file);
+        let lengths: Vec<usize> = arguments.iter().map(Vec::len).collect();
+        assert_eq!(lengths, vec![6, 1, 1]);
+        assert_eq!(
+            file.tokens()[arguments[1][0]]
+                .token()
+                .get_info()
+                .characters(),
+            "50"
+        );
+    }
+
+    /// Tests that a blank line ends a logical line and that `rnd` calls are arguments.
+    #[test]
+    fn blank_line_and_rnd() {
+        let source = "number_of_objects rnd(1,4)\n\n  5\nterrain_cost GRASS 1";
+        assert_eq!(
+            lines(source),
+            vec!["number_of_objects rnd(1,4)", "5", "terrain_cost GRASS 1"]
+        );
+    }
+
+    /// Tests extracting literal and `rnd` numeric arguments with their commands,
+    /// positions, and spans, skipping constants.
+    #[test]
+    fn numeric_parameters_of_file() {
+        let source = "#const SIZE 5\ncreate_land {\n  base_size SIZE\n  land_position rnd ( 10 , 20 ) 50\n}\nnumber_of_objects rnd(1,4) /* 9 */";
+        let file = AnnotatedFile::annotate(&lexer::lex_str(source));
+        let parameters = numeric_parameters(&file);
+        let parameters: Vec<(&str, usize, NumericValue, usize, usize, usize)> = parameters
+            .iter()
+            .map(|p| {
+                let span = p.span();
+                (
+                    p.command(),
+                    p.position(),
+                    p.value(),
+                    p.line_number(),
+                    span.start_column(),
+                    span.end_column(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            parameters,
+            vec![
+                ("#const", 2, NumericValue::Literal(5), 1, 13, 13),
+                (
+                    "land_position",
+                    1,
+                    NumericValue::Random { min: 10, max: 20 },
+                    4,
+                    17,
+                    31
+                ),
+                ("land_position", 2, NumericValue::Literal(50), 4, 33, 34),
+                (
+                    "number_of_objects",
+                    1,
+                    NumericValue::Random { min: 1, max: 4 },
+                    6,
+                    19,
+                    26
+                ),
+            ]
+        );
+    }
+
+    /// Tests that a glued `rnd` call splits into six sub-tokens with the columns and byte
+    /// offsets of their characters.
+    #[test]
+    fn split_glued_rnd() {
+        let file = lexer::lex_str("number_of_objects  rnd(1,40)");
+        let lexeme = &file.lexemes()[2];
+        let sub_tokens = split_punctuation(lexeme);
+        let sub_tokens: Vec<(&str, usize, usize, std::ops::Range<usize>)> = sub_tokens
+            .iter()
+            .map(|sub_token| {
+                let info = sub_token.get_info();
+                assert!(matches!(sub_token, Lexeme::Text(_)));
+                (
+                    info.characters(),
+                    info.start_column(),
+                    info.end_column(),
+                    info.byte_range(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            sub_tokens,
+            vec![
+                ("rnd", 20, 22, 19..22),
+                ("(", 23, 23, 22..23),
+                ("1", 24, 24, 23..24),
+                (",", 25, 25, 24..25),
+                ("40", 26, 27, 25..27),
+                (")", 28, 28, 27..28),
+            ]
+        );
+        assert_eq!(
+            split_punctuation(&file.lexemes()[0]),
+            vec![file.lexemes()[0].clone()]
+        );
+        assert_eq!(
+            split_punctuation(&file.lexemes()[1]),
+            vec![file.lexemes()[1].clone()]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Validates that map files are text that can be lexed, for cleaning up a map pack.
+
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::lexer;
+
+/// The reason a file is not a valid map script.
+#[derive(Debug)]
+pub enum InvalidReason {
+    /// The file could not be read.
+    Unreadable(io::Error),
+    /// The file contains a null byte at the given 0-indexed offset, so it is likely binary.
+    Binary(usize),
+    /// The file is not UTF-8; the first invalid byte is at the given 0-indexed offset.
+    NotUtf8(usize),
+}
+
+impl Display for InvalidReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unreadable(e) => write!(f, "could not be read: {e}"),
+            Self::Binary(offset) => {
+                write!(f, "contains a null byte at offset {offset}, likely binary")
+            }
+            Self::NotUtf8(offset) => write!(f, "is not valid UTF-8 at offset {offset}"),
+        }
+    }
+}
+
+/// Returns `Ok` if `bytes` are map script text, otherwise the reason they are not.
+/// Text containing a null byte is considered binary.
+pub fn validate_bytes(bytes: &[u8]) -> Result<(), InvalidReason> {
+    if let Some(offset) = bytes.iter().position(|&b| b == 0) {
+        return Err(InvalidReason::Binary(offset));
+    }
+    std::str::from_utf8(bytes).map_err(|e| InvalidReason::NotUtf8(e.valid_up_to()))?;
+    Ok(())
+}
+
+/// Returns `Ok` if the file at `path` is map script text that can be lexed,
+/// otherwise the reason it is not.
+pub fn validate_file(path: &Path) -> Result<(), InvalidReason> {
+    let bytes = fs::read(path).map_err(InvalidReason::Unreadable)?;
+    validate_bytes(&bytes)?;
+    lexer::lex(path).map_err(InvalidReason::Unreadable)?;
+    Ok(())
+}
+
+/// Validates every file directly in the directory `dir`, ignoring subdirectories.
+/// Returns pairs of each file's path and its validation result, sorted by path,
+/// or an IO error if the directory cannot be read.
+pub fn validate_dir(dir: &Path) -> io::Result<Vec<(PathBuf, Result<(), InvalidReason>)>> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let result = validate_file(&path);
+            (path, result)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that text is valid while binary and non-UTF-8 bytes are not.
+    #[test]
+    fn bytes() {
+        assert!(validate_bytes("/* é */\r\n".as_bytes()).is_ok());
+        assert!(matches!(
+            validate_bytes(b"ab\0c"),
+            Err(InvalidReason::Binary(2))
+        ));
+        assert!(matches!(
+            validate_bytes(b"ab\xffc"),
+            Err(InvalidReason::NotUtf8(2))
+        ));
+    }
+
+    /// Tests a directory mixing a valid script and a binary blob.
+    #[test]
+    fn mixed_directory() {
+        let dir = std::env::temp_dir().join("aoe2-rms-validate-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("map.rms"), "<PLAYER_SETUP>\n").unwrap();
+        fs::write(dir.join("blob.bin"), [0x89, b'P', b'N', b'G', 0, 0]).unwrap();
+        let report = validate_dir(&dir).unwrap();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].0, dir.join("blob.bin"));
+        assert_eq!(
+            report[0].1.as_ref().unwrap_err().to_string(),
+            "contains a null byte at offset 4, likely binary"
+        );
+        assert_eq!(report[1].0, dir.join("map.rms"));
+        assert!(report[1].1.is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+//! Functions exported to JavaScript with `wasm-bindgen` for rendering and checking map
+//! scripts in the browser. No function reads or writes files.
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    annotater::AnnotatedFile,
+    checker::{self, CheckOptions},
+    export, lexer,
+    pipeline::Pipeline,
+};
+
+/// Returns the self-contained html of the map script `source`, with the stylesheet
+/// inlined.
+#[wasm_bindgen]
+pub fn render_rms(source: &str) -> String {
+    Pipeline::new().render_string(source)
+}
+
+/// Returns the diagnostics of the map script `source`, checked with the default options,
+/// as a JSON array of objects with the fields `path`, which is empty, `line`,
+/// `start_column`, `end_column`, `severity`, `code`, and `message`.
+#[wasm_bindgen]
+pub fn diagnostics_json(source: &str) -> String {
+    let annotated_file = AnnotatedFile::annotate_owned(lexer::lex_str(source));
+    let diagnostics = checker::check(&annotated_file, &CheckOptions::default());
+    export::diagnostics_json("", &diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the exported functions return html and a JSON array of diagnostics.
+    #[test]
+    fn exported_functions() {
+        let html = render_rms("<PLAYER_SETUP>\nassign_to AT_NOTHING 0 0 0");
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(">AT_NOTHING<div"));
+        let diagnostics: serde_json::Value = serde_json::from_str(&diagnostics_json(
+            "<PLAYER_SETUP>\nassign_to AT_NOTHING 0 0 0",
+        ))
+        .unwrap();
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+        assert_eq!(diagnostics[0]["code"], "invalid-assign-type");
+        assert_eq!(diagnostics[0]["line"], 2);
+    }
+}
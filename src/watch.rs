@@ -0,0 +1,124 @@
+//! Watches a directory of map scripts, reporting each changed file once its changes
+//! settle, for re-rendering maps as they are edited.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+/// Collects the paths of changed files, holding each until it has gone unchanged for a
+/// delay, so that the rapid successive events of a single save yield one change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Debouncer {
+    /// How long a file must go unchanged before its change is ready.
+    delay: Duration,
+    /// The time of the latest event of each file whose change is not yet ready.
+    pending: HashMap<PathBuf, Instant>,
+}
+
+impl Debouncer {
+    /// Constructs a new debouncer whose changes are ready once a file has gone unchanged
+    /// for `delay`.
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Records an event changing the file at `path` at the time `now`, postponing the
+    /// file's change until `delay` after `now`.
+    pub fn record(&mut self, path: PathBuf, now: Instant) {
+        self.pending.insert(path, now);
+    }
+
+    /// Returns the earliest time at which a pending change becomes ready, or `None` if
+    /// no change is pending.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.values().min().map(|&time| time + self.delay)
+    }
+
+    /// Removes and returns the paths of the files that have gone unchanged for the delay
+    /// at the time `now`, in sorted order.
+    pub fn take_ready(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut ready: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, &time)| now.duration_since(time) >= self.delay)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in ready.iter() {
+            self.pending.remove(path);
+        }
+        ready.sort();
+        ready
+    }
+}
+
+/// Watches the files directly in the directory `dir`, calling `on_change` with the path
+/// of each file that is created or modified, once it has gone unchanged for `delay`.
+/// Runs until the watch fails, returning its error.
+pub fn watch(dir: &Path, delay: Duration, mut on_change: impl FnMut(&Path)) -> notify::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    let mut debouncer = Debouncer::new(delay);
+    loop {
+        let received = match debouncer.next_deadline() {
+            Some(deadline) => {
+                receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    let now = Instant::now();
+                    for path in event.paths {
+                        debouncer.record(path, now);
+                    }
+                }
+            }
+            Ok(Err(e)) => return Err(e),
+            Err(RecvTimeoutError::Timeout) => (),
+            // The watcher holds the sender until it is dropped at the end of the function.
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        for path in debouncer.take_ready(Instant::now()) {
+            on_change(&path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that rapid successive events of a file yield one change, once the file has
+    /// gone unchanged for the delay.
+    #[test]
+    fn debounce_rapid_events() {
+        let delay = Duration::from_millis(100);
+        let mut debouncer = Debouncer::new(delay);
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let (a, b) = (PathBuf::from("maps/a.rms"), PathBuf::from("maps/b.rms"));
+        assert_eq!(debouncer.next_deadline(), None);
+        for millis in [0, 30, 60, 90] {
+            debouncer.record(a.clone(), at(millis));
+            assert!(debouncer.take_ready(at(millis)).is_empty());
+        }
+        debouncer.record(b.clone(), at(120));
+        assert_eq!(debouncer.next_deadline(), Some(at(190)));
+        assert!(debouncer.take_ready(at(189)).is_empty());
+        assert_eq!(debouncer.take_ready(at(190)), vec![a.clone()]);
+        assert_eq!(debouncer.next_deadline(), Some(at(220)));
+        debouncer.record(a.clone(), at(200));
+        assert_eq!(debouncer.take_ready(at(300)), vec![a, b]);
+        assert_eq!(debouncer.next_deadline(), None);
+    }
+}
@@ -0,0 +1,37 @@
+//! Snapshot test for the annotater.
+//!
+//! Each map in `maps/` is annotated and serialized with `export::token_dump`, then
+//! compared against its snapshot in `tests/snapshots/`.
+//! Set the `UPDATE_SNAPSHOTS` environment variable to regenerate the snapshots after an
+//! intended change, then review the differences before committing them.
+
+use std::{fs, path::PathBuf};
+
+use aoe2_rms::{annotater::AnnotatedFile, export, lexer};
+
+/// Tests that annotating each map matches its committed snapshot.
+#[test]
+fn annotated_maps_match_snapshots() {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let mut mismatches = vec![];
+    for result in fs::read_dir("maps/").unwrap() {
+        let path = result.unwrap().path();
+        if !path.is_file() {
+            continue;
+        }
+        let annotated_file = AnnotatedFile::annotate(&lexer::lex(&path).unwrap());
+        let dump = export::token_dump(&annotated_file);
+        let mut snapshot = PathBuf::from("tests/snapshots");
+        snapshot.push(path.file_name().unwrap());
+        snapshot.set_extension("txt");
+        if update {
+            fs::write(&snapshot, dump).unwrap();
+        } else if fs::read_to_string(&snapshot).ok().as_ref() != Some(&dump) {
+            mismatches.push(snapshot.display().to_string());
+        }
+    }
+    assert!(
+        mismatches.is_empty(),
+        "Snapshots differ, rerun with `UPDATE_SNAPSHOTS=1` if the change is intended: {mismatches:?}"
+    );
+}
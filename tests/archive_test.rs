@@ -0,0 +1,53 @@
+//! Integration test for writing a map pack to a zip archive.
+#![cfg(feature = "zip")]
+
+use std::{fs, io::Read, path::PathBuf, process::Command};
+
+/// Tests that the archive contains the html of each map beside the linked stylesheet,
+/// which includes the rules of the selected highlight theme.
+#[test]
+fn writes_zip_archive() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("zip_archive");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("maps")).unwrap();
+    fs::create_dir_all(dir.join("style")).unwrap();
+    fs::write(dir.join("maps/a.rms"), "/* a */ /* b */\n<PLAYER_SETUP>\n").unwrap();
+    fs::write(dir.join("maps/b.rms"), "<LAND_GENERATION>\n").unwrap();
+    fs::copy("style/style.css", dir.join("style/style.css")).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_aoe2-rms"))
+        .args([
+            "--quiet",
+            "--zip",
+            "pack.zip",
+            "--highlight-theme",
+            "game-editor",
+        ])
+        .current_dir(&dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(!dir.join("out").exists());
+
+    let mut archive = zip::ZipArchive::new(fs::File::open(dir.join("pack.zip")).unwrap()).unwrap();
+    let mut names: Vec<&str> = archive.file_names().collect();
+    names.sort();
+    assert_eq!(names, vec!["a.html", "b.html", "style.css"]);
+
+    let mut read = |name| {
+        let mut text = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    };
+    let html = read("a.html");
+    assert!(html.contains("<link rel=\"stylesheet\" href=\"style.css\" />"));
+    assert!(html.contains("comment-1"));
+    let css = read("style.css");
+    assert!(css.starts_with(&fs::read_to_string("style/style.css").unwrap()));
+    assert!(css.contains(":has(.comment-1:hover) .comment-1"));
+    assert!(css.contains(".section {\n  color: #804000;\n}"));
+}
@@ -0,0 +1,71 @@
+//! Golden tests for the html writers.
+//!
+//! Small known files are rendered with `write_debug_file` and
+//! `write_annotated_debug_file`, then compared byte for byte against the expected html in
+//! `tests/golden/`.
+//! Set the `UPDATE_SNAPSHOTS` environment variable to regenerate the expected files after
+//! an intended change, then review the differences before committing them.
+//! Line breaks of the expected files are normalized to `\n` before comparing, so the
+//! tests pass when the files are checked out with `\r\n` line endings.
+
+use std::{fs, path::PathBuf};
+
+use aoe2_rms::{annotater::AnnotatedFile, html_writer, lexer};
+
+/// The sources rendered by the golden tests, each named by the stem of its golden files.
+/// The sources use both kinds of line endings, which the writers do not reproduce.
+const SOURCES: [(&str, &str); 2] = [
+    (
+        "with_comment",
+        "/* A <small> map. */\r\n<PLAYER_SETUP>\r\n  random_placement /* x */\n",
+    ),
+    (
+        "without_comment",
+        "<LAND_GENERATION>\nbase_terrain GRASS\r\n\tcreate_player_lands { }",
+    ),
+];
+
+/// Compares `html` with the golden file `name`, or overwrites the golden file with `html`
+/// if `update` is `true`.
+/// Returns `false` if the html differs from the golden file, `true` otherwise.
+fn matches_golden(name: &str, html: &str, update: bool) -> bool {
+    let golden = PathBuf::from("tests/golden").join(name);
+    if update {
+        fs::write(&golden, html).unwrap();
+        return true;
+    }
+    let expected = fs::read_to_string(&golden).map(|s| s.replace("\r\n", "\n"));
+    expected.ok().as_deref() == Some(html)
+}
+
+/// Tests that the debug files of the lexemes and annotated tokens match their golden files.
+#[test]
+fn html_matches_golden_files() {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let mut mismatches = vec![];
+    for (name, source) in SOURCES {
+        let lexemes = lexer::lex_str(source);
+
+        let output = dir.join(format!("golden_{name}.html"));
+        html_writer::write_debug_file(&lexemes, &output).unwrap();
+        let html = fs::read_to_string(&output).unwrap();
+        let golden = format!("{name}.html");
+        if !matches_golden(&golden, &html, update) {
+            mismatches.push(golden);
+        }
+
+        let output = dir.join(format!("golden_{name}_annotated.html"));
+        let annotated_file = AnnotatedFile::annotate(&lexemes);
+        html_writer::write_annotated_debug_file(&annotated_file, &output).unwrap();
+        let html = fs::read_to_string(&output).unwrap();
+        let golden = format!("{name}_annotated.html");
+        if !matches_golden(&golden, &html, update) {
+            mismatches.push(golden);
+        }
+    }
+    assert!(
+        mismatches.is_empty(),
+        "Html differs from the golden files, rerun with `UPDATE_SNAPSHOTS=1` if the change is intended: {mismatches:?}"
+    );
+}
@@ -3,6 +3,7 @@
 use std::{fs, path::PathBuf};
 
 use aoe2_rms::lexer;
+use proptest::prelude::*;
 
 /// Tests that the lexing process preserves enough information to copy a file without changes.
 #[test]
@@ -21,3 +22,84 @@ fn copy_files() {
         assert_eq!(source_text, output_text);
     }
 }
+
+/// Characters that exercise line break, whitespace, and multi-byte handling.
+const SPECIAL_CHARACTERS: &[&str] = &[
+    "\r", "\n", "\r\n", "\t", " ", "\u{b}", "\u{c}", "\u{feff}", "\u{a0}", "\u{200b}", "é", "語",
+    "🗺", "/*", "*/", "{", "}", "<", ">",
+];
+
+/// Generates strings built from a mix of ascii text and the `SPECIAL_CHARACTERS`.
+fn source_strategy() -> impl Strategy<Value = String> {
+    let piece = prop_oneof![
+        "[a-zA-Z0-9_#]{1,8}",
+        proptest::sample::select(SPECIAL_CHARACTERS).prop_map(String::from),
+        any::<char>().prop_map(String::from),
+    ];
+    proptest::collection::vec(piece, 0..64).prop_map(|pieces| pieces.concat())
+}
+
+proptest! {
+    /// Tests that lexing an arbitrary string and reconstructing it yields the same string.
+    #[test]
+    fn round_trip_string(source in source_strategy()) {
+        let lexemes = lexer::lex_str(&source);
+        prop_assert_eq!(lexemes.to_source_string(), source);
+    }
+
+    /// Tests that the columns of each line are contiguous and count characters.
+    #[test]
+    fn contiguous_columns(source in source_strategy()) {
+        let lexemes = lexer::lex_str(&source);
+        let mut line_number = 1;
+        let mut next_column = 1;
+        for lexeme in lexemes.lexemes() {
+            let info = lexeme.get_info();
+            prop_assert_eq!(info.line_number(), line_number);
+            prop_assert_eq!(info.start_column(), next_column);
+            prop_assert_eq!(
+                info.end_column() + 1 - info.start_column(),
+                info.characters().chars().count()
+            );
+            next_column = info.end_column() + 1;
+            if let lexer::Lexeme::LineBreak(_) = lexeme {
+                line_number += 1;
+                next_column = 1;
+            }
+        }
+    }
+}
+
+/// Tests that writing lexemes to a file reproduces a string with every special character.
+#[test]
+fn round_trip_write_to_path() {
+    let source = SPECIAL_CHARACTERS.join("x");
+    let lexemes = lexer::lex_str(&source);
+    let pb = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("round_trip.rms");
+    lexemes.write_to_path(&pb).unwrap();
+    assert_eq!(fs::read_to_string(&pb).unwrap(), source);
+}
+
+/// Tests that a missing file in a batch is an error without affecting the other files.
+#[test]
+fn lex_batch() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("lex_batch");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.rms"), "<PLAYER_SETUP>\n").unwrap();
+    fs::write(dir.join("c.rms"), "/* c */").unwrap();
+    let paths = vec![dir.join("a.rms"), dir.join("b.rms"), dir.join("c.rms")];
+
+    let results = lexer::lex_all(&paths);
+    let result_paths: Vec<&PathBuf> = results.iter().map(|(path, _)| path).collect();
+    assert_eq!(result_paths, paths.iter().collect::<Vec<_>>());
+    assert_eq!(
+        results[0].1.as_ref().unwrap().to_source_string(),
+        "<PLAYER_SETUP>\n"
+    );
+    assert_eq!(
+        results[1].1.as_ref().unwrap_err().kind(),
+        std::io::ErrorKind::NotFound
+    );
+    assert_eq!(results[2].1.as_ref().unwrap().to_source_string(), "/* c */");
+}
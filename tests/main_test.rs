@@ -0,0 +1,252 @@
+//! Integration test for running the application.
+
+use std::{fs, path::PathBuf, process::Command};
+
+/// Tests that a map is converted when the `out` directory does not yet exist.
+#[test]
+fn creates_output_directory() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("fresh_clone");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("maps")).unwrap();
+    fs::create_dir_all(dir.join("style")).unwrap();
+    fs::write(dir.join("maps/test.rms"), "/* map */\n<PLAYER_SETUP>\n").unwrap();
+    fs::copy("style/style.css", dir.join("style/style.css")).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_aoe2-rms"))
+        .arg("--quiet")
+        .current_dir(&dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(dir.join("out/style.css").is_file());
+    assert!(dir.join("out/test.html").is_file());
+}
+
+/// Tests that each line of the `--format jsonl` output is an independent JSON object.
+#[test]
+fn diagnostics_jsonl() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("jsonl");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("maps")).unwrap();
+    fs::write(dir.join("maps/a.rms"), "if UNKNOWN\nendif\n").unwrap();
+    fs::write(dir.join("maps/b.rms"), "assign_to AT_NOTHING 0 0 0\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_aoe2-rms"))
+        .args(["--check", "--format", "jsonl", "a.rms", "b.rms"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    // The invalid assign type is an error.
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let records: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0]["path"], "maps/a.rms");
+    assert_eq!(records[0]["code"], "unknown-label");
+    assert_eq!(records[2]["path"], "maps/b.rms");
+    assert_eq!(records[2]["code"], "invalid-assign-type");
+}
+
+/// Tests that `--fail-on warning` fails on a file with only warnings, unlike the default.
+#[test]
+fn fail_on_warning() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("fail_on");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("maps")).unwrap();
+    fs::write(
+        dir.join("maps/w.rms"),
+        "<PLAYER_SETUP>\nif UNKNOWN\nendif\n",
+    )
+    .unwrap();
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_aoe2-rms"))
+            .args(args)
+            .current_dir(&dir)
+            .output()
+            .unwrap()
+    };
+    let output = run(&["--check", "w.rms"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "maps/w.rms:2:4: warning[unknown-label]: Unknown label `UNKNOWN`.\n"
+    );
+    assert_eq!(
+        run(&["--check", "--fail-on", "warning", "w.rms"])
+            .status
+            .code(),
+        Some(1)
+    );
+    assert_eq!(
+        run(&["--check", "--fail-on", "info", "w.rms"])
+            .status
+            .code(),
+        Some(1)
+    );
+    assert!(run(&["--check", "--fail-on", "never", "w.rms"])
+        .status
+        .success());
+}
+
+/// Tests that an unclosed comment fails the check only under `--strict-comments`, and
+/// that the map renders either way.
+#[test]
+fn strict_comments() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("strict_comments");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("maps")).unwrap();
+    fs::create_dir_all(dir.join("style")).unwrap();
+    fs::write(
+        dir.join("maps/c.rms"),
+        "<PLAYER_SETUP>
+/* note
+random_placement
+",
+    )
+    .unwrap();
+    fs::copy("style/style.css", dir.join("style/style.css")).unwrap();
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_aoe2-rms"))
+            .args(args)
+            .current_dir(&dir)
+            .output()
+            .unwrap()
+    };
+    let output = run(&["--check", "c.rms"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "maps/c.rms:2:1: warning[unmatched-comment-delimiter]: Unclosed `/*` comments out the rest of the file.\n"
+    );
+    let output = run(&[
+        "--check",
+        "--strict-comments",
+        "--fail-on",
+        "error",
+        "c.rms",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("maps/c.rms:2:1: error[unmatched-comment-delimiter]"));
+    assert!(run(&["--quiet", "c.rms"]).status.success());
+    assert!(dir.join("out/c.html").is_file());
+}
+
+/// Tests that `--explain` prints the explanation of a known code and fails for an
+/// unknown code.
+#[test]
+fn explain() {
+    let run = |code: &str| {
+        Command::new(env!("CARGO_BIN_EXE_aoe2-rms"))
+            .args(["--explain", code])
+            .output()
+            .unwrap()
+    };
+    let output = run("RMS001");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("RMS001 (unknown-label)\n\nA condition of an `if`"));
+    assert!(stdout.contains("Fixed:\n\n    #define GRASS_MAP\n    if GRASS_MAP\n"));
+    assert_eq!(run("invalid-assign-type").stdout, run("rms006").stdout);
+
+    let output = run("RMS999");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("Unknown diagnostic code `RMS999`"));
+}
+
+/// Tests that the defaults of an `.rms-tools.toml` file are applied, that a flag
+/// overrides them, and that a missing configured maps folder is reported.
+#[test]
+fn config_file() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("config");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("scripts")).unwrap();
+    fs::write(
+        dir.join(".rms-tools.toml"),
+        "maps_dir = \"scripts\"\nfail_on = \"warning\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("scripts/w.rms"),
+        "<PLAYER_SETUP>\nif UNKNOWN\nendif\n",
+    )
+    .unwrap();
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_aoe2-rms"))
+            .args(args)
+            .current_dir(&dir)
+            .output()
+            .unwrap()
+    };
+    let output = run(&["--check", "w.rms"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "scripts/w.rms:2:4: warning[unknown-label]: Unknown label `UNKNOWN`.\n"
+    );
+    assert!(run(&["--check", "--fail-on", "error", "w.rms"])
+        .status
+        .success());
+
+    fs::write(dir.join(".rms-tools.toml"), "maps_dir = \"sripts\"\n").unwrap();
+    let output = run(&[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("Could not read `sripts`.\n"));
+}
+
+/// Tests that the timings of every stage are printed only with `--profile`, and that
+/// the flag is rejected with `--combined`.
+#[test]
+fn profile() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("profile");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("maps")).unwrap();
+    fs::create_dir_all(dir.join("style")).unwrap();
+    fs::write(dir.join("maps/a.rms"), "/* map */\n<PLAYER_SETUP>\n").unwrap();
+    fs::copy("style/style.css", dir.join("style/style.css")).unwrap();
+
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_aoe2-rms"))
+            .args(args)
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert!(!String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("profile"));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert!(!run(&["--quiet"]).contains("profile"));
+    let stdout = run(&["--quiet", "--profile"]);
+    let line = stdout
+        .lines()
+        .find(|line| line.starts_with("profile `maps/a.rms`: "))
+        .unwrap();
+    for stage in ["lex ", "annotate ", "write ", "total "] {
+        assert!(line.contains(stage), "`{line}` is missing `{stage}`");
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_aoe2-rms"))
+        .args(["--profile", "--combined"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "`--profile` cannot be combined with `--combined` or `--zip`.\n"
+    );
+}
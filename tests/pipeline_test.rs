@@ -0,0 +1,55 @@
+//! Integration test for the pipeline builder.
+
+use std::{fs, path::PathBuf};
+
+use aoe2_rms::{annotater::AnnotatedFile, html_writer, lexer, pipeline::Pipeline};
+
+/// Tests that the pipeline renders the same html as lexing, annotating, and writing manually.
+#[test]
+fn same_output_as_manual_steps() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let source = dir.join("pipeline_source.rms");
+    fs::write(
+        &source,
+        "/* map */\nif REGICIDE\n  base_terrain GRASS\nendif\n",
+    )
+    .unwrap();
+
+    let options = html_writer::HtmlOptions {
+        source_map: true,
+        ..html_writer::HtmlOptions::default()
+    };
+    let manual = dir.join("pipeline_manual.html");
+    let annotated_file = AnnotatedFile::annotate(&lexer::lex(&source).unwrap());
+    html_writer::write_annotated_debug_file_with_options(&annotated_file, &manual, &options)
+        .unwrap();
+    let manual = fs::read_to_string(&manual).unwrap();
+
+    let pipeline = Pipeline::new().source_map(true);
+    assert_eq!(pipeline.render_file(&source).unwrap(), manual);
+    let rendered = dir.join("pipeline_rendered.html");
+    pipeline.render_to(&source, &rendered).unwrap();
+    assert_eq!(fs::read_to_string(&rendered).unwrap(), manual);
+    let timed = dir.join("pipeline_timed.html");
+    let (annotated_file, timings) = pipeline.render_to_timed(&source, &timed).unwrap();
+    assert_eq!(annotated_file, pipeline.annotate_file(&source).unwrap());
+    assert_eq!(fs::read_to_string(&timed).unwrap(), manual);
+    assert_eq!(
+        timings.total(),
+        timings.lex + timings.annotate + timings.write
+    );
+}
+
+/// Tests that rendering a string returns a complete document with the stylesheet inlined.
+#[test]
+fn render_string_is_self_contained() {
+    let html = Pipeline::new().render_string("/* map */\r\nbase_terrain GRASS");
+    assert!(html.starts_with("<!DOCTYPE html>\n<html lang=\"en\">\n"));
+    assert!(html.ends_with("  </body>\n</html>\n"));
+    assert!(!html.contains("<link rel=\"stylesheet\""));
+    assert!(html.contains(html_writer::STYLESHEET));
+    assert!(html.contains(":has(.comment-0:hover) .comment-0"));
+    assert!(html.contains(">GRASS<div class=\"card\">"));
+    assert_eq!(html.matches("<style>").count(), 1);
+    assert_eq!(html.matches("</style>").count(), 1);
+}